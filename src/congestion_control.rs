@@ -0,0 +1,576 @@
+/*!
+
+End-host congestion control, reacting to the explicit congestion notification (ECN) marks set by the routers.
+
+Routers such as the Basic router with the `ecn_marking` field mark the packets that leave through congested ports. When a packet is consumed its destination echoes back whether it was marked, and the echo reaches the origin server after `acknowledge_delay` cycles. The echoes are not modelled as network traffic. The `CongestionControl` of each server uses them to throttle the injection of new packets towards each destination.
+
+It is configured in the `congestion_control` field of the `Configuration`, being `None` by default.
+
+see [`new_congestion_control`](fn.new_congestion_control.html) for documentation on the configuration syntax of predefined congestion controls.
+
+*/
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use quantifiable_derive::Quantifiable;//the derive macro
+use crate::config_parser::ConfigurationValue;
+use crate::quantify::Quantifiable;
+use crate::{Plugs,match_object_panic};
+
+///A congestion control mechanism of a server. It decides when new packets may be injected towards each destination.
+pub trait CongestionControl : Quantifiable + Debug
+{
+	///Whether a new packet of `size` phits towards `destination` may be injected at `cycle`.
+	fn can_send(&mut self, destination:usize, size:usize, cycle:usize) -> bool;
+	///Notifies that a packet of `size` phits towards `destination` has been handed to the network.
+	///It is also called for the packets sent again by the retransmission protocol, which are not restrained by `can_send`.
+	fn notify_sent(&mut self, destination:usize, size:usize, cycle:usize);
+	///Notifies the echo of a packet of `size` phits consumed at `destination`, with whether it was marked on its way.
+	fn notify_echo(&mut self, destination:usize, size:usize, marked:bool, cycle:usize);
+	///Notifies that a packet of `size` phits towards `destination` is considered lost, because the retransmission protocol has timed out waiting for it. Its echo is not expected anymore.
+	fn notify_loss(&mut self, _destination:usize, _size:usize, _cycle:usize)
+	{
+	}
+	///The cycles that the echoes take to reach the origin. `None` if the mechanism does not use them, so the destinations do not send them.
+	fn acknowledge_delay(&self) -> Option<usize>;
+}
+
+#[derive(Debug)]
+pub struct CongestionControlBuilderArgument<'a>
+{
+	///A ConfigurationValue::Object defining the congestion control.
+	pub cv: &'a ConfigurationValue,
+	///The user defined plugs. In case the congestion control needs to create elements.
+	pub plugs: &'a Plugs,
+	///The index of the server using the congestion control.
+	pub server_index: usize,
+}
+
+/**Build a new congestion control. Each server gets its own from the `congestion_control` field of the `Configuration`.
+
+### None
+The default. Packets are injected as soon as the flow control of the network allows it.
+```ignore
+None
+```
+
+### DCQCN
+A rate-based control as the one of RoCEv2. Each flow has a current and a target rate, as fractions of `line_rate` phits per cycle. Each marked echo increases the estimated congestion `alpha`, and cuts the current rate according to it at most once every `decrease_interval` cycles. Every `rate_increase_period` cycles without cuts the rate recovers, first halving the distance to the target rate during `fast_recovery_steps` periods and then also increasing the target rate by `additive_increase`. The `alpha` decays every `alpha_update_period` cycles.
+```ignore
+DCQCN{
+	alpha_gain: 0.00390625,//weight of each update of alpha. 1/256 by default.
+	alpha_update_period: 100,
+	rate_increase_period: 100,
+	decrease_interval: 50,
+	fast_recovery_steps: 5,
+	additive_increase: 0.05,
+	minimum_rate: 0.01,
+	line_rate: 1,
+	acknowledge_delay: 0,
+}
+```
+All fields are optional, with the shown defaults.
+
+### DCTCP
+A window-based control as Data Center TCP. Each flow may have up to `window` packets not echoed yet. Once per window of echoes the fraction of marked ones updates `alpha`. If there were marks the window is multiplied by `1-alpha/2`, otherwise it grows by one packet, up to `maximum_window`.
+```ignore
+DCTCP{
+	initial_window: 10,
+	maximum_window: 64,
+	alpha_gain: 0.0625,//weight of each update of alpha. 1/16 by default.
+	acknowledge_delay: 0,
+}
+```
+All fields are optional, with the shown defaults. Packets dropped in a lossy network are never echoed. When the retransmission protocol times out waiting for one, it stops counting as in flight.
+*/
+pub fn new_congestion_control(arg:CongestionControlBuilderArgument) -> Box<dyn CongestionControl>
+{
+	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
+	{
+		match arg.plugs.congestion_controls.get(cv_name)
+		{
+			Some(builder) => return builder(arg),
+			_ => (),
+		};
+		match cv_name.as_ref()
+		{
+			"None" => Box::new(NoCongestionControl::new(arg)),
+			"DCQCN" => Box::new(DCQCN::new(arg)),
+			"DCTCP" => Box::new(DCTCP::new(arg)),
+			_ => panic!("Unknown congestion control {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a CongestionControl from a non-Object");
+	}
+}
+
+///Does not limit the injection.
+#[derive(Debug,Quantifiable)]
+pub struct NoCongestionControl
+{
+}
+
+impl CongestionControl for NoCongestionControl
+{
+	fn can_send(&mut self, _destination:usize, _size:usize, _cycle:usize) -> bool
+	{
+		true
+	}
+	fn notify_sent(&mut self, _destination:usize, _size:usize, _cycle:usize)
+	{
+	}
+	fn notify_echo(&mut self, _destination:usize, _size:usize, _marked:bool, _cycle:usize)
+	{
+	}
+	fn acknowledge_delay(&self) -> Option<usize>
+	{
+		None
+	}
+}
+
+impl NoCongestionControl
+{
+	pub fn new(arg:CongestionControlBuilderArgument) -> NoCongestionControl
+	{
+		match_object_panic!(arg.cv,"None",_value,);
+		NoCongestionControl{}
+	}
+}
+
+///The state of a flow in DCQCN.
+#[derive(Debug,Quantifiable)]
+struct DCQCNFlow
+{
+	///The rate at which the flow is currently injecting, in phits per cycle.
+	current_rate: f64,
+	///The rate to recover after a cut.
+	target_rate: f64,
+	///The estimation of the congestion.
+	alpha: f64,
+	///Number of rate increases since the last cut.
+	increase_steps: usize,
+	///The cycle of the last cut, if any.
+	last_decrease: Option<usize>,
+	///The cycle up to which the decay of alpha has been applied.
+	last_alpha_update: usize,
+	///The cycle up to which the rate increases have been applied.
+	last_rate_increase: usize,
+	///The first cycle in which the rate allows to inject the next packet.
+	next_send_cycle: f64,
+}
+
+///A rate-based congestion control as the one of RoCEv2.
+#[derive(Debug,Quantifiable)]
+pub struct DCQCN
+{
+	///Weight of each update of alpha.
+	alpha_gain: f64,
+	///Cycles between decays of alpha.
+	alpha_update_period: usize,
+	///Cycles between increases of the rate.
+	rate_increase_period: usize,
+	///Minimum number of cycles between cuts of the rate of a flow.
+	decrease_interval: usize,
+	///Number of increases that only approach the target rate.
+	fast_recovery_steps: usize,
+	///Increase of the target rate in each step after the fast recovery, in phits per cycle.
+	additive_increase: f64,
+	///The lowest rate of a flow, in phits per cycle.
+	minimum_rate: f64,
+	///The greatest rate of a flow, in phits per cycle.
+	line_rate: f64,
+	///Cycles that the echoes take to reach the origin.
+	acknowledge_delay: usize,
+	///The state of each flow, indexed by its destination.
+	flows: BTreeMap<usize,DCQCNFlow>,
+}
+
+impl CongestionControl for DCQCN
+{
+	fn can_send(&mut self, destination:usize, _size:usize, cycle:usize) -> bool
+	{
+		let flow = self.update_flow(destination,cycle);
+		flow.next_send_cycle <= cycle as f64
+	}
+	fn notify_sent(&mut self, destination:usize, size:usize, cycle:usize)
+	{
+		let flow = self.update_flow(destination,cycle);
+		flow.next_send_cycle = flow.next_send_cycle.max(cycle as f64) + size as f64 / flow.current_rate;
+	}
+	fn notify_echo(&mut self, destination:usize, _size:usize, marked:bool, cycle:usize)
+	{
+		if !marked
+		{
+			return;
+		}
+		let alpha_gain = self.alpha_gain;
+		let decrease_interval = self.decrease_interval;
+		let minimum_rate = self.minimum_rate;
+		let flow = self.update_flow(destination,cycle);
+		let can_decrease = match flow.last_decrease
+		{
+			Some(last) => cycle >= last + decrease_interval,
+			None => true,
+		};
+		if can_decrease
+		{
+			flow.target_rate = flow.current_rate;
+			flow.current_rate = (flow.current_rate * (1.0 - flow.alpha/2.0)).max(minimum_rate);
+			flow.increase_steps = 0;
+			flow.last_decrease = Some(cycle);
+			flow.last_rate_increase = cycle;
+		}
+		//Every marked echo raises alpha, even if the rate is not cut.
+		flow.alpha = (1.0-alpha_gain)*flow.alpha + alpha_gain;
+		flow.last_alpha_update = cycle;
+	}
+	fn acknowledge_delay(&self) -> Option<usize>
+	{
+		Some(self.acknowledge_delay)
+	}
+}
+
+impl DCQCN
+{
+	pub fn new(arg:CongestionControlBuilderArgument) -> DCQCN
+	{
+		let mut alpha_gain = 1.0/256.0;
+		let mut alpha_update_period = 100;
+		let mut rate_increase_period = 100;
+		let mut decrease_interval = 50;
+		let mut fast_recovery_steps = 5;
+		let mut additive_increase = 0.05;
+		let mut minimum_rate = 0.01;
+		let mut line_rate = 1.0;
+		let mut acknowledge_delay = 0;
+		match_object_panic!(arg.cv,"DCQCN",value,
+			"alpha_gain" => match value
+			{
+				&ConfigurationValue::Number(f) => alpha_gain=f,
+				_ => panic!("bad value for alpha_gain"),
+			},
+			"alpha_update_period" => match value
+			{
+				&ConfigurationValue::Number(f) => alpha_update_period=f as usize,
+				_ => panic!("bad value for alpha_update_period"),
+			},
+			"rate_increase_period" => match value
+			{
+				&ConfigurationValue::Number(f) => rate_increase_period=f as usize,
+				_ => panic!("bad value for rate_increase_period"),
+			},
+			"decrease_interval" => match value
+			{
+				&ConfigurationValue::Number(f) => decrease_interval=f as usize,
+				_ => panic!("bad value for decrease_interval"),
+			},
+			"fast_recovery_steps" => match value
+			{
+				&ConfigurationValue::Number(f) => fast_recovery_steps=f as usize,
+				_ => panic!("bad value for fast_recovery_steps"),
+			},
+			"additive_increase" => match value
+			{
+				&ConfigurationValue::Number(f) => additive_increase=f,
+				_ => panic!("bad value for additive_increase"),
+			},
+			"minimum_rate" => match value
+			{
+				&ConfigurationValue::Number(f) => minimum_rate=f,
+				_ => panic!("bad value for minimum_rate"),
+			},
+			"line_rate" => match value
+			{
+				&ConfigurationValue::Number(f) => line_rate=f,
+				_ => panic!("bad value for line_rate"),
+			},
+			"acknowledge_delay" => match value
+			{
+				&ConfigurationValue::Number(f) => acknowledge_delay=f as usize,
+				_ => panic!("bad value for acknowledge_delay"),
+			},
+		);
+		if alpha_update_period==0 || rate_increase_period==0
+		{
+			panic!("The periods of DCQCN must be positive.");
+		}
+		if minimum_rate<=0.0 || minimum_rate>line_rate
+		{
+			panic!("The minimum_rate of DCQCN must be positive and not greater than the line_rate.");
+		}
+		DCQCN{
+			alpha_gain,
+			alpha_update_period,
+			rate_increase_period,
+			decrease_interval,
+			fast_recovery_steps,
+			additive_increase,
+			minimum_rate,
+			line_rate,
+			acknowledge_delay,
+			flows: BTreeMap::new(),
+		}
+	}
+	///Gets the flow towards `destination`, applying the decays and increases due up to `cycle`.
+	fn update_flow(&mut self, destination:usize, cycle:usize) -> &mut DCQCNFlow
+	{
+		let line_rate = self.line_rate;
+		let flow = self.flows.entry(destination).or_insert_with(||DCQCNFlow{
+			current_rate: line_rate,
+			target_rate: line_rate,
+			alpha: 1.0,
+			increase_steps: 0,
+			last_decrease: None,
+			last_alpha_update: cycle,
+			last_rate_increase: cycle,
+			next_send_cycle: 0.0,
+		});
+		let alpha_periods = (cycle - flow.last_alpha_update) / self.alpha_update_period;
+		if alpha_periods>0
+		{
+			flow.alpha *= (1.0-self.alpha_gain).powi(alpha_periods as i32);
+			flow.last_alpha_update += alpha_periods * self.alpha_update_period;
+		}
+		let increase_periods = (cycle - flow.last_rate_increase) / self.rate_increase_period;
+		for _ in 0..increase_periods
+		{
+			if flow.current_rate>=line_rate
+			{
+				//Nothing left to recover.
+				break;
+			}
+			if flow.increase_steps >= self.fast_recovery_steps
+			{
+				flow.target_rate = (flow.target_rate + self.additive_increase).min(line_rate);
+			}
+			flow.current_rate = (flow.current_rate + flow.target_rate)/2.0;
+			if line_rate - flow.current_rate < 1e-6
+			{
+				flow.current_rate = line_rate;
+			}
+			flow.increase_steps+=1;
+		}
+		flow.last_rate_increase += increase_periods * self.rate_increase_period;
+		flow
+	}
+}
+
+///The state of a flow in DCTCP.
+#[derive(Debug,Quantifiable)]
+struct DCTCPFlow
+{
+	///The maximum number of packets not echoed.
+	window: f64,
+	///The estimation of the congestion.
+	alpha: f64,
+	///Number of packets sent and not echoed yet.
+	in_flight: usize,
+	///Echoes received in the current observation window.
+	echoed: usize,
+	///Marked echoes received in the current observation window.
+	marked: usize,
+	///The number of echoes that complete the current observation window.
+	observation_size: usize,
+}
+
+///A window-based congestion control as Data Center TCP.
+#[derive(Debug,Quantifiable)]
+pub struct DCTCP
+{
+	///The window of a new flow, in packets.
+	initial_window: f64,
+	///The greatest window of a flow, in packets.
+	maximum_window: f64,
+	///Weight of each update of alpha.
+	alpha_gain: f64,
+	///Cycles that the echoes take to reach the origin.
+	acknowledge_delay: usize,
+	///The state of each flow, indexed by its destination.
+	flows: BTreeMap<usize,DCTCPFlow>,
+}
+
+impl CongestionControl for DCTCP
+{
+	fn can_send(&mut self, destination:usize, _size:usize, _cycle:usize) -> bool
+	{
+		let flow = self.get_flow(destination);
+		(flow.in_flight as f64) < flow.window.floor().max(1.0)
+	}
+	fn notify_sent(&mut self, destination:usize, _size:usize, _cycle:usize)
+	{
+		self.get_flow(destination).in_flight+=1;
+	}
+	fn notify_echo(&mut self, destination:usize, _size:usize, marked:bool, _cycle:usize)
+	{
+		let alpha_gain = self.alpha_gain;
+		let maximum_window = self.maximum_window;
+		let flow = self.get_flow(destination);
+		flow.in_flight = flow.in_flight.saturating_sub(1);
+		flow.echoed+=1;
+		if marked
+		{
+			flow.marked+=1;
+		}
+		if flow.echoed >= flow.observation_size
+		{
+			let fraction = flow.marked as f64 / flow.echoed as f64;
+			flow.alpha = (1.0-alpha_gain)*flow.alpha + alpha_gain*fraction;
+			if flow.marked>0
+			{
+				flow.window = (flow.window * (1.0 - flow.alpha/2.0)).max(1.0);
+			}
+			else
+			{
+				flow.window = (flow.window + 1.0).min(maximum_window);
+			}
+			flow.echoed=0;
+			flow.marked=0;
+			flow.observation_size = flow.window.ceil() as usize;
+		}
+	}
+	fn notify_loss(&mut self, destination:usize, _size:usize, _cycle:usize)
+	{
+		//The packet will never be echoed, so it no longer counts against the window.
+		let flow = self.get_flow(destination);
+		flow.in_flight = flow.in_flight.saturating_sub(1);
+	}
+	fn acknowledge_delay(&self) -> Option<usize>
+	{
+		Some(self.acknowledge_delay)
+	}
+}
+
+impl DCTCP
+{
+	pub fn new(arg:CongestionControlBuilderArgument) -> DCTCP
+	{
+		let mut initial_window = 10.0;
+		let mut maximum_window = 64.0;
+		let mut alpha_gain = 1.0/16.0;
+		let mut acknowledge_delay = 0;
+		match_object_panic!(arg.cv,"DCTCP",value,
+			"initial_window" => match value
+			{
+				&ConfigurationValue::Number(f) => initial_window=f,
+				_ => panic!("bad value for initial_window"),
+			},
+			"maximum_window" => match value
+			{
+				&ConfigurationValue::Number(f) => maximum_window=f,
+				_ => panic!("bad value for maximum_window"),
+			},
+			"alpha_gain" => match value
+			{
+				&ConfigurationValue::Number(f) => alpha_gain=f,
+				_ => panic!("bad value for alpha_gain"),
+			},
+			"acknowledge_delay" => match value
+			{
+				&ConfigurationValue::Number(f) => acknowledge_delay=f as usize,
+				_ => panic!("bad value for acknowledge_delay"),
+			},
+		);
+		if initial_window<1.0 || maximum_window<initial_window
+		{
+			panic!("DCTCP requires 1<=initial_window<=maximum_window.");
+		}
+		DCTCP{
+			initial_window,
+			maximum_window,
+			alpha_gain,
+			acknowledge_delay,
+			flows: BTreeMap::new(),
+		}
+	}
+	fn get_flow(&mut self, destination:usize) -> &mut DCTCPFlow
+	{
+		let initial_window = self.initial_window;
+		self.flows.entry(destination).or_insert_with(||DCTCPFlow{
+			window: initial_window,
+			alpha: 0.0,
+			in_flight: 0,
+			echoed: 0,
+			marked: 0,
+			observation_size: initial_window.ceil() as usize,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::tests::parse_configuration;
+
+	fn argument<'a>(cv:&'a ConfigurationValue, plugs:&'a Plugs) -> CongestionControlBuilderArgument<'a>
+	{
+		CongestionControlBuilderArgument{ cv, plugs, server_index:0 }
+	}
+
+	///Sends packets to the destination 1 while allowed, returning how many.
+	fn send_while_allowed(control:&mut dyn CongestionControl, cycle:usize) -> usize
+	{
+		let mut sent = 0;
+		while control.can_send(1,4,cycle)
+		{
+			control.notify_sent(1,4,cycle);
+			sent+=1;
+		}
+		sent
+	}
+
+	#[test]
+	fn dctcp_grows_and_cuts_the_window()
+	{
+		let plugs = Plugs::default();
+		let cv = parse_configuration("DCTCP { initial_window: 4, maximum_window: 64, alpha_gain: 0.5 }");
+		let mut control = DCTCP::new(argument(&cv,&plugs));
+		assert_eq!(send_while_allowed(&mut control,0),4);
+		//A whole window of unmarked echoes grows the window by one packet.
+		for _ in 0..4
+		{
+			control.notify_echo(1,4,false,10);
+		}
+		assert_eq!(send_while_allowed(&mut control,10),5);
+		//A whole window of marked echoes sets alpha to 0.5 and cuts the window by alpha/2, to 3.75 packets.
+		for _ in 0..5
+		{
+			control.notify_echo(1,4,true,20);
+		}
+		assert!((control.flows[&1].window-3.75).abs()<1e-9);
+		assert_eq!(send_while_allowed(&mut control,20),3);
+		//The packets given up by the retransmission protocol free their place in the window.
+		control.notify_loss(1,4,30);
+		assert_eq!(send_while_allowed(&mut control,30),1);
+	}
+
+	#[test]
+	fn dcqcn_cuts_and_recovers_the_rate()
+	{
+		let plugs = Plugs::default();
+		let cv = parse_configuration("DCQCN { rate_increase_period: 100, decrease_interval: 50, fast_recovery_steps: 5 }");
+		let mut control = DCQCN::new(argument(&cv,&plugs));
+		//At the line rate a packet of 4 phits takes 4 cycles.
+		assert!(control.can_send(1,4,0));
+		control.notify_sent(1,4,0);
+		assert!(!control.can_send(1,4,3));
+		assert!(control.can_send(1,4,4));
+		//A marked echo with the initial alpha of 1 halves the rate, so the next packet takes 8 cycles.
+		control.notify_echo(1,4,true,4);
+		assert!((control.flows[&1].current_rate-0.5).abs()<1e-9);
+		control.notify_sent(1,4,4);
+		assert!(!control.can_send(1,4,11));
+		assert!(control.can_send(1,4,12));
+		//Marks within the decrease interval do not cut the rate again.
+		control.notify_echo(1,4,true,30);
+		assert!((control.flows[&1].current_rate-0.5).abs()<1e-9);
+		//Each period without cuts halves the distance to the target rate.
+		assert!(control.can_send(1,4,104));
+		assert!((control.flows[&1].current_rate-0.75).abs()<1e-9);
+		assert!(control.can_send(1,4,204));
+		assert!((control.flows[&1].current_rate-0.875).abs()<1e-9);
+	}
+}
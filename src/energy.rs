@@ -0,0 +1,203 @@
+/*!
+
+Accounting of the energy consumed by the network.
+
+The energy of the links is configured in each `LinkClass`, with `energy_per_phit` for each phit that traverses a link and `static_power` for each cycle of each direction of a link. The links between routers may enter a low-power state after `sleep_after` cycles without traffic, consuming `sleep_static_power` per cycle instead. The first phit sent through a sleeping link pays an additional `wake_up_delay`, and the phits sent behind it cannot overtake it.
+```ignore
+LinkClass{
+	delay: 1,
+	energy_per_phit: 3,//default 0
+	static_power: 0.2,//default 0
+	sleep_after: 100,//by default links never sleep
+	wake_up_delay: 20,//default 0
+	sleep_static_power: 0.02,//default 0
+}
+```
+
+The energy of the routers is configured in the `energy` field of the `Configuration`. Its presence enables the `energy` record in the result, with the total energy, its components, the average power, and the energy per delivered bit, in the same units as the configuration.
+```ignore
+energy: EnergyModel{
+	buffer_write: 1,//per phit written into an input buffer of a router
+	buffer_read: 1,//per phit read from a buffer of a router
+	crossbar_traversal: 2,//per phit traversing the crossbar of a router
+	port_static_power: 0.1,//per cycle and router port
+	bits_per_phit: 64,//to compute the energy per delivered bit. Defaults to 1
+}
+```
+All fields of the `EnergyModel` are optional, being 0 by default except `bits_per_phit`. The costs cannot be negative.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+///The energy costs of the routers. Those of the links are given in their `LinkClass`.
+#[derive(Debug,Clone)]
+pub struct EnergyModel
+{
+	///Energy to write a phit into a buffer.
+	pub buffer_write: f64,
+	///Energy to read a phit from a buffer.
+	pub buffer_read: f64,
+	///Energy for a phit to traverse the crossbar.
+	pub crossbar_traversal: f64,
+	///Energy consumed by each router port in each cycle.
+	pub port_static_power: f64,
+	///The size of a phit in bits.
+	pub bits_per_phit: f64,
+}
+
+impl EnergyModel
+{
+	pub fn new(cv:&ConfigurationValue) -> EnergyModel
+	{
+		let mut buffer_write=0.0;
+		let mut buffer_read=0.0;
+		let mut crossbar_traversal=0.0;
+		let mut port_static_power=0.0;
+		let mut bits_per_phit=1.0;
+		match_object_panic!(cv,"EnergyModel",value,
+			"buffer_write" => match value
+			{
+				&ConfigurationValue::Number(f) => buffer_write=f,
+				_ => panic!("bad value for buffer_write"),
+			},
+			"buffer_read" => match value
+			{
+				&ConfigurationValue::Number(f) => buffer_read=f,
+				_ => panic!("bad value for buffer_read"),
+			},
+			"crossbar_traversal" => match value
+			{
+				&ConfigurationValue::Number(f) => crossbar_traversal=f,
+				_ => panic!("bad value for crossbar_traversal"),
+			},
+			"port_static_power" => match value
+			{
+				&ConfigurationValue::Number(f) => port_static_power=f,
+				_ => panic!("bad value for port_static_power"),
+			},
+			"bits_per_phit" => match value
+			{
+				&ConfigurationValue::Number(f) => bits_per_phit=f,
+				_ => panic!("bad value for bits_per_phit"),
+			},
+		);
+		for (name,value) in [("buffer_write",buffer_write),("buffer_read",buffer_read),("crossbar_traversal",crossbar_traversal),("port_static_power",port_static_power)].iter()
+		{
+			if *value<0.0
+			{
+				panic!("The {} of the EnergyModel cannot be negative, but it is {}.",name,value);
+			}
+		}
+		if bits_per_phit<=0.0
+		{
+			panic!("The bits_per_phit of the EnergyModel must be positive, but it is {}.",bits_per_phit);
+		}
+		EnergyModel{
+			buffer_write,
+			buffer_read,
+			crossbar_traversal,
+			port_static_power,
+			bits_per_phit,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::config_parser::ConfigurationValue;
+	use crate::tests::{parse_configuration,test_plugs,new_simulation,send_message,advance_until,last_consumption};
+
+	///Runs 2 routers with a server each, sending 16 phits from the server 0 to the server 1 at each of the given cycles.
+	///Returns the cycle in which the last message is consumed and the `energy` record of the result.
+	fn energy_record(link_class:&str, sending_cycles:&[usize]) -> (usize,Vec<(String,ConfigurationValue)>)
+	{
+		let plugs = test_plugs();
+		let mut simulation = new_simulation(&format!("Configuration
+		{{
+			random_seed: 1,
+			warmup: 0,
+			measured: 1000,
+			topology: Hamming {{ sides: [2], servers_per_router: 1 }},
+			traffic: Scripted,
+			maximum_packet_size: 16,
+			router: Basic {{ virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], delay: 0, buffer_size: 64, bubble: false, flit_size: 16, intransit_priority: false, allow_request_busy_port: true, output_priorize_lowest_label: false, output_buffer_size: 32 }},
+			routing: Shortest,
+			link_classes: [ LinkClass {{ delay: 1, {} }}, LinkClass {{ delay: 1, energy_per_phit: 5 }} ],
+			energy: EnergyModel {{ buffer_write: 1, buffer_read: 2, crossbar_traversal: 4, port_static_power: 0.5, bits_per_phit: 8 }},
+			launch_configurations: [],
+		}}",link_class),&plugs);
+		for &cycle in sending_cycles
+		{
+			advance_until(&mut simulation,cycle);
+			send_message(&mut simulation,0,1,16);
+		}
+		advance_until(&mut simulation,1000);
+		assert_eq!(simulation.network.servers[1].statistics.consumed_messages,sending_cycles.len());
+		let mut output = vec![];
+		simulation.write_result(&mut output);
+		let result = parse_configuration(&String::from_utf8(output).expect("the result is not text"));
+		let mut record = None;
+		if let ConfigurationValue::Object(_,content) = result
+		{
+			for (name,value) in content
+			{
+				if name=="energy"
+				{
+					if let ConfigurationValue::Object(_,energy) = value
+					{
+						record=Some(energy);
+					}
+				}
+			}
+		}
+		(last_consumption(&simulation,1),record.expect("there is no energy record in the result"))
+	}
+
+	fn field(record:&[(String,ConfigurationValue)], name:&str) -> f64
+	{
+		match record.iter().find(|(key,_)|key==name)
+		{
+			Some((_,ConfigurationValue::Number(x))) => *x,
+			_ => panic!("there is no number {} in the energy record",name),
+		}
+	}
+
+	#[test]
+	fn energy_totals_follow_the_phit_count()
+	{
+		let (_,record) = energy_record("energy_per_phit: 3",&[0]);
+		//The 16 phits cross the server link into the router 0, the link between routers, and the server link into the server 1.
+		assert_eq!(field(&record,"link_dynamic_energy"),16.0*(5.0+3.0+5.0));
+		assert_eq!(field(&record,"link_static_energy"),0.0);
+		//They are written into the buffers of both routers and read and switched out of both.
+		assert_eq!(field(&record,"buffer_energy"),32.0*1.0+32.0*2.0);
+		assert_eq!(field(&record,"crossbar_energy"),32.0*4.0);
+		//Each router has a port towards the other router and one towards its server.
+		assert_eq!(field(&record,"router_static_energy"),4.0*1000.0*0.5);
+		let total = 16.0*13.0 + 96.0 + 128.0 + 2000.0;
+		assert_eq!(field(&record,"total_energy"),total);
+		assert_eq!(field(&record,"average_power"),total/1000.0);
+		assert_eq!(field(&record,"energy_per_delivered_bit"),total/(16.0*8.0));
+		assert_eq!(field(&record,"link_wake_ups"),0.0);
+	}
+
+	#[test]
+	fn sleeping_links_pay_the_wake_up_delay()
+	{
+		let (awake_arrival,awake_record) = energy_record("static_power: 0.2",&[0,500]);
+		let (sleeping_arrival,sleeping_record) = energy_record("static_power: 0.2, sleep_after: 100, wake_up_delay: 20, sleep_static_power: 0.02",&[0,500]);
+		//The link between routers falls asleep after the first message and delays the leading phit of the second one.
+		assert_eq!(sleeping_arrival,awake_arrival+20);
+		assert_eq!(field(&sleeping_record,"link_wake_ups"),1.0);
+		assert_eq!(field(&awake_record,"link_wake_ups"),0.0);
+		//Each direction sleeps from 100 cycles after its last transmission, but the direction towards the router 0 never transmits.
+		let sleeping_fraction = field(&sleeping_record,"link_sleeping_fraction");
+		assert!(sleeping_fraction>0.5 && sleeping_fraction<1.0,"link_sleeping_fraction={}",sleeping_fraction);
+		assert!(field(&sleeping_record,"link_static_energy")<field(&awake_record,"link_static_energy"));
+		assert_eq!(field(&awake_record,"link_static_energy"),2.0*1000.0*0.2);
+		assert_eq!(field(&awake_record,"link_sleeping_fraction"),0.0);
+	}
+}
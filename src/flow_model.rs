@@ -0,0 +1,364 @@
+/*!
+
+An analytic flow model to estimate the load of each link without running the cycle simulator.
+
+The flow model reads the same `Configuration` object as the simulator. It builds the `topology`, the `routing` and the `pattern` of the `traffic`, which must be a `HomogeneousTraffic`. The `link_classes` give the capacity of each link, from their `transference_speed`. Other fields only relevant to the simulator, such as the `router`, are ignored.
+
+Each server sends a unit of load, scaled by `Pattern::load_scale`, split in `samples` flows with the destinations given by the pattern. Each flow is routed by asking `Routing::next` for candidates. The load is split uniformly among the different exit ports that are candidates, as an ideal adaptive routing would do. Routing states that are indistinguishable are merged, so routings that only depend on the current router do not suffer an explosion of paths.
+
+The result includes the maximum link load divided by the capacity of the link, from which the saturation throughput is deduced. Random patterns and routings are averaged over the samples, so use a large enough `samples` for them. Deterministic ones are exact with a single sample.
+
+It is used by [`file_main`](../fn.file_main.html) when given the `flow_model` free argument.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use rand::{rngs::StdRng,SeedableRng};
+
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Topology,new_topology,TopologyBuilderArgument,Location};
+use crate::routing::{RoutingInfo,Routing,new_routing,RoutingBuilderArgument};
+use crate::pattern::{Pattern,new_pattern,PatternBuilderArgument};
+use crate::{Plugs,LinkClass,match_object_panic,get_git_id,get_version_number};
+
+///Estimates the load of the links assuming an ideal splitting of the flows among the routing candidates.
+pub struct FlowModel
+{
+	///The random number generator, for random patterns and routings.
+	rng: RefCell<StdRng>,
+	///The network.
+	topology: Box<dyn Topology>,
+	///The routing to be evaluated.
+	routing: Box<dyn Routing>,
+	///The pattern of the traffic, from the servers into the servers.
+	pattern: Box<dyn Pattern>,
+	///Number of servers generating traffic.
+	servers: usize,
+	///The load offered by each server in the configuration, in phits per cycle.
+	load: f64,
+	///Number of destinations requested to the pattern by each server.
+	samples: usize,
+	///Number of virtual channels given to the routing. Taken from the `router` when it has the field `virtual_channels`.
+	virtual_channels: usize,
+	///The classes of the links, to know their capacity. When empty every link carries one phit per cycle.
+	link_classes: Vec<LinkClass>,
+	///`link_load[router][port]` is the load through the link leaving `router` by `port`, relative to the injection load of each server.
+	link_load: Vec<Vec<f64>>,
+	///The load received by each server, relative to the injection load of each server.
+	ejection_load: Vec<f64>,
+	///The load that has traversed each router to router link, summed over all links. Used to compute the average hops.
+	average_hops: f64,
+}
+
+impl FlowModel
+{
+	///Builds the flow model from a `Configuration` object.
+	///`samples` is the number of flows generated by each server.
+	pub fn new(cv:&ConfigurationValue, plugs:&Plugs, samples:usize) -> FlowModel
+	{
+		let mut seed: Option<usize> = None;
+		let mut topology = None;
+		let mut traffic = None;
+		let mut routing = None;
+		let mut virtual_channels = 1;
+		let mut link_classes = vec![];
+		match_object_panic!(cv,"Configuration",value,
+			"random_seed" => match value
+			{
+				&ConfigurationValue::Number(f) => seed=Some(f.round() as usize),
+				_ => panic!("bad value for random_seed"),
+			}
+			"topology" => topology=Some(value),
+			"traffic" => traffic=Some(value),
+			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs})),
+			"link_classes" => match value
+			{
+				&ConfigurationValue::Array(ref l) => link_classes=l.iter().map(LinkClass::new).collect(),
+				_ => panic!("bad value for link_classes"),
+			}
+			//Simulator fields.
+			"router" => if let &ConfigurationValue::Object(_,ref router_pairs) = value
+			{
+				//Routings may filter candidates by virtual channel, so we require as many as the router has.
+				for &(ref router_name,ref router_value) in router_pairs
+				{
+					if let ("virtual_channels",&ConfigurationValue::Number(f)) = (router_name.as_ref(),router_value)
+					{
+						virtual_channels=f as usize;
+					}
+				}
+			}
+			"warmup" | "measured" | "maximum_packet_size" | "server_queue_size" | "launch_configurations" | "retransmission" | "congestion_control" | "energy" => (),
+			"statistics_temporal_step" | "statistics_server_percentiles" | "statistics_packet_percentiles" | "statistics_packet_definitions" | "statistics_message_size_buckets" => (),
+		);
+		let seed=seed.expect("There were no random_seed");
+		let topology=topology.expect("There were no topology");
+		let traffic=traffic.expect("There were no traffic");
+		let mut routing=routing.expect("There were no routing");
+		if samples==0
+		{
+			panic!("The flow model requires at least one sample per server");
+		}
+		let mut pattern = None;
+		let mut servers = None;
+		let mut load = None;
+		match_object_panic!(traffic,"HomogeneousTraffic",value,
+			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs})),
+			"servers" => match value
+			{
+				&ConfigurationValue::Number(f) => servers=Some(f as usize),
+				_ => panic!("bad value for servers"),
+			}
+			"load" => match value
+			{
+				&ConfigurationValue::Number(f) => load=Some(f),
+				_ => panic!("bad value for load"),
+			}
+			"message_size" | "arrival" => (),
+		);
+		let mut pattern=pattern.expect("There were no pattern");
+		let servers=servers.expect("There were no servers");
+		let load=load.expect("There were no load");
+		let rng=RefCell::new(StdRng::from_seed({
+			let mut std_rng_seed = [0u8;32];
+			for (index,value) in seed.to_ne_bytes().iter().enumerate()
+			{
+				std_rng_seed[index]=*value;
+			}
+			std_rng_seed
+		}));
+		let topology=new_topology(TopologyBuilderArgument{
+			cv:topology,
+			plugs,
+			rng:&rng,
+		});
+		routing.initialize(&topology,&rng);
+		pattern.initialize(servers,servers,&topology,&rng);
+		let link_load = (0..topology.num_routers()).map(|router|vec![0.0;topology.ports(router)]).collect();
+		let ejection_load = vec![0.0;topology.num_servers()];
+		FlowModel{
+			rng,
+			topology,
+			routing,
+			pattern,
+			servers,
+			load,
+			samples,
+			virtual_channels,
+			link_classes,
+			link_load,
+			ejection_load,
+			average_hops: 0.0,
+		}
+	}
+	///Routes all the flows, accumulating their load into the links.
+	pub fn run(&mut self)
+	{
+		for origin in 0..self.servers
+		{
+			let weight = self.pattern.load_scale(origin) as f64 / self.samples as f64;
+			for _ in 0..self.samples
+			{
+				let destination = self.pattern.get_destination(origin,&self.topology,&self.rng);
+				if origin!=destination
+				{
+					self.route_flow(origin,destination,weight);
+				}
+			}
+		}
+	}
+	///Splits the `weight` of a flow from `origin` to `destination` among the candidates given by the routing.
+	fn route_flow(&mut self, origin:usize, destination:usize, weight:f64)
+	{
+		let link_load = &mut self.link_load;
+		let ejection_load = &mut self.ejection_load;
+		let average_hops = &mut self.average_hops;
+		let topology = self.topology.as_ref();
+		split_flow(self.routing.as_ref(),topology,origin,destination,weight,self.virtual_channels,&self.rng,&mut |router,port,port_weight|{
+			link_load[router][port] += port_weight;
+			match topology.neighbour(router,port).0
+			{
+				Location::ServerPort(server) => ejection_load[server] += port_weight,
+				_ => *average_hops += port_weight,
+			}
+		});
+	}
+	///The phits per cycle that the links of class `link_class` can carry.
+	fn capacity(&self, link_class:usize) -> f64
+	{
+		if self.link_classes.is_empty()
+		{
+			1.0
+		}
+		else
+		{
+			self.link_classes[link_class].capacity()
+		}
+	}
+	///The maximum load of a link between routers divided by its capacity, relative to the injection load of each server.
+	pub fn maximum_link_load(&self) -> f64
+	{
+		let topology = self.topology.as_ref();
+		(0..topology.num_routers()).flat_map(|router|
+			topology.neighbour_router_iter(router).map(move |item|self.link_load[router][item.port_index] / self.capacity(item.link_class))
+		).fold(0.0,f64::max)
+	}
+	///The maximum load of a link into a server divided by its capacity, relative to the injection load of each server.
+	pub fn maximum_ejection_load(&self) -> f64
+	{
+		let topology = self.topology.as_ref();
+		self.ejection_load.iter().enumerate().map(|(server,load)|load / self.capacity(topology.server_neighbour(server).1)).fold(0.0,f64::max)
+	}
+	///The injection load per server at which some link between routers becomes saturated, in phits per cycle.
+	///It is limited by the capacity of the links from the servers, as no server can inject faster than its link.
+	///The ejection links are not considered, since sampling random patterns makes their load very noisy. Check `maximum_ejection_load` in the result for patterns with hotspots.
+	pub fn saturation_throughput(&self) -> f64
+	{
+		let topology = self.topology.as_ref();
+		let injection_limit = (0..self.servers).map(|server|self.capacity(topology.server_neighbour(server).1)).fold(f64::INFINITY,f64::min);
+		(1.0 / self.maximum_link_load()).min(injection_limit)
+	}
+	///Writes the results as a `Result` object, similar to the ones of the simulator.
+	pub fn write_result(&self, output:&mut dyn Write)
+	{
+		let topology = self.topology.as_ref();
+		let (total_load,total_links) = (0..topology.num_routers()).fold((0.0,0),|(load,links),router|{
+			let router_load : f64 = topology.neighbour_router_iter(router).map(|item|self.link_load[router][item.port_index] / self.capacity(item.link_class)).sum();
+			(load+router_load, links+topology.neighbour_router_iter(router).count())
+		});
+		let average_link_load = total_load / total_links as f64;
+		let maximum_link_load = self.maximum_link_load();
+		let maximum_ejection_load = self.maximum_ejection_load();
+		//Self messages are not routed, so the hops are averaged over the ejected load.
+		let average_hops = self.average_hops / self.ejection_load.iter().sum::<f64>();
+		let saturation_throughput = self.saturation_throughput();
+		let result_content = vec![
+			(String::from("maximum_link_load"),ConfigurationValue::Number(maximum_link_load)),
+			(String::from("average_link_load"),ConfigurationValue::Number(average_link_load)),
+			(String::from("maximum_ejection_load"),ConfigurationValue::Number(maximum_ejection_load)),
+			(String::from("average_packet_hops"),ConfigurationValue::Number(average_hops)),
+			(String::from("saturation_throughput"),ConfigurationValue::Number(saturation_throughput)),
+			//The values at the load of the configuration.
+			(String::from("offered_load"),ConfigurationValue::Number(self.load)),
+			(String::from("maximum_link_utilization"),ConfigurationValue::Number(maximum_link_load*self.load)),
+			(String::from("accepted_load"),ConfigurationValue::Number(self.load.min(saturation_throughput))),
+			(String::from("git_id"),ConfigurationValue::Literal(get_git_id().to_string())),
+			(String::from("version_number"),ConfigurationValue::Literal(get_version_number().to_string())),
+		];
+		let result=ConfigurationValue::Object(String::from("Result"),result_content);
+		writeln!(output,"{}",result).unwrap();
+	}
+}
+
+///Splits the `weight` of a flow from `origin` to `destination` among the candidates given by the routing, as an ideal adaptive routing would do.
+///Calls `accumulate(router,port,weight)` each time some weight goes through the link leaving `router` by `port`, including the final link to the destination server.
+///Routing states that are indistinguishable are merged.
+pub fn split_flow(routing:&dyn Routing, topology:&dyn Topology, origin:usize, destination:usize, weight:f64, virtual_channels:usize, rng:&RefCell<StdRng>, accumulate:&mut dyn FnMut(usize,usize,f64))
+{
+	let source_router = match topology.server_neighbour(origin).0
+	{
+		Location::RouterPort{router_index,router_port:_} => router_index,
+		_ => panic!("The server is not attached to a router"),
+	};
+	let routing_info = RefCell::new(RoutingInfo::new());
+	routing.initialize_routing_info(&routing_info,topology,source_router,destination,rng);
+	//The states being routed, indexed by the current router and a description of the routing information.
+	let mut frontier : BTreeMap<(usize,String),(f64,RefCell<RoutingInfo>)> = BTreeMap::new();
+	frontier.insert((source_router,format!("{:?}",routing_info.borrow())),(weight,routing_info));
+	let maximum_hops = topology.num_routers() * 4;
+	let mut hops = 0;
+	while !frontier.is_empty()
+	{
+		if hops>maximum_hops
+		{
+			panic!("The flow from {} to {} has not reached its destination after {} hops",origin,destination,hops);
+		}
+		let mut next_frontier : BTreeMap<(usize,String),(f64,RefCell<RoutingInfo>)> = BTreeMap::new();
+		for ((current_router,_),(state_weight,routing_info)) in frontier.into_iter()
+		{
+			let candidates = routing.next(&routing_info.borrow(),topology,current_router,destination,virtual_channels,rng).candidates;
+			let mut ports : Vec<usize> = candidates.iter().map(|candidate|candidate.port).collect();
+			ports.sort_unstable();
+			ports.dedup();
+			if ports.is_empty()
+			{
+				panic!("The routing gave no candidates for the flow from {} to {} at router {}",origin,destination,current_router);
+			}
+			let port_weight = state_weight / ports.len() as f64;
+			for port in ports
+			{
+				accumulate(current_router,port,port_weight);
+				match topology.neighbour(current_router,port).0
+				{
+					Location::ServerPort(server) =>
+					{
+						if server!=destination
+						{
+							panic!("The flow from {} to {} has reached the server {}",origin,destination,server);
+						}
+					},
+					Location::RouterPort{router_index,router_port} =>
+					{
+						let branch_info = routing_info.clone();
+						let requested = candidates.iter().find(|candidate|candidate.port==port).unwrap();
+						routing.performed_request(requested,&branch_info,topology,current_router,destination,virtual_channels,rng);
+						branch_info.borrow_mut().hops+=1;
+						routing.update_routing_info(&branch_info,topology,router_index,router_port,destination,rng);
+						let key = (router_index,format!("{:?}",branch_info.borrow()));
+						match next_frontier.get_mut(&key)
+						{
+							Some(entry) => entry.0 += port_weight,
+							None => { next_frontier.insert(key,(port_weight,branch_info)); },
+						}
+					},
+					Location::None => panic!("The routing gave a candidate to an unconnected port"),
+				}
+			}
+		}
+		frontier = next_frontier;
+		hops += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::tests::{parse_configuration,test_plugs};
+
+	fn tornado_on_ring(link_classes:&str) -> FlowModel
+	{
+		let plugs = test_plugs();
+		let cv = parse_configuration(&format!("Configuration
+		{{
+			random_seed: 1,
+			topology: Torus {{ sides: [8], servers_per_router: 1 }},
+			traffic: HomogeneousTraffic {{ pattern: Tornado, servers: 8, load: 1.0, message_size: 16 }},
+			routing: Shortest,
+			link_classes: {},
+		}}",link_classes));
+		let mut model = FlowModel::new(&cv,&plugs,1);
+		model.run();
+		model
+	}
+
+	#[test]
+	fn tornado_saturates_by_the_link_capacity()
+	{
+		//The tornado on a ring of 8 sends each flow 3 hops in the same direction, so each link carries 3 flows.
+		let model = tornado_on_ring("[ LinkClass { delay: 1 }, LinkClass { delay: 1 } ]");
+		assert!((model.maximum_link_load()-3.0).abs()<1e-9);
+		assert!((model.saturation_throughput()-1.0/3.0).abs()<1e-9);
+		let model = tornado_on_ring("[ LinkClass { delay: 1, transference_speed: [1,2] }, LinkClass { delay: 1 } ]");
+		assert!((model.maximum_link_load()-6.0).abs()<1e-9);
+		assert!((model.saturation_throughput()-1.0/6.0).abs()<1e-9);
+		//With fast links between routers the servers cannot inject faster than their own links.
+		let model = tornado_on_ring("[ LinkClass { delay: 1, transference_speed: 2 }, LinkClass { delay: 1, transference_speed: [1,2] } ]");
+		assert!((model.maximum_link_load()-1.5).abs()<1e-9);
+		assert!((model.maximum_ejection_load()-2.0).abs()<1e-9);
+		assert!((model.saturation_throughput()-0.5).abs()<1e-9);
+	}
+}
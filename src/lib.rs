@@ -136,21 +136,29 @@ Configuration
 		message_size: 16,//The size in phits of the messages created by the servers.
 	},
 	maximum_packet_size: 16,//Messages of greater length will be broken into several packets.
-	router: Basic//The router is another object with a large description
+	router: Basic//The router is another object with a large description. OutputQueued is an ideal router to compare against. VirtualOutputQueued avoids the head-of-line blocking at the inputs.
 	{
 		//The number of virtual channels. The basic router sets a buffer for each virtual channel in each port, both at input and output.
 		virtual_channels: 8,
 		//Policies that filter the candidate routes given by the routing algorithm. They may be used to break deadlock or to give preference to some choices.
 		//EnforceFlowControl must be included to actually use flow control restrictions.
 		virtual_channel_policies: [ EnforceFlowControl, WideHops{width:1}, LowestSinghWeight{extra_congestion:0, extra_distance:0, aggregate_buffers:true, use_internal_space:true}, Random ],
-		delay: 0,//not actually implemted in the basic router. In the future it may be removed or actually implemented.
+		//Cycles of each pipeline stage. Leading phits pay all of them, the rest of phits only the switch traversal.
+		routing_delay: 0,
+		virtual_channel_allocation_delay: 0,
+		switch_allocation_delay: 0,
+		switch_traversal_delay: 0,
+		switch_allocator: Token,//how to match the requests to the crossbar. Token, Random, ISLIP{iterations:2}, Wavefront, or OldestFirst. With output buffers it allocates the moves into them, and if omitted every selected input advances.
 		buffer_size: 64,//phits available in each input buffer
-		bubble: false,//to enable bubble mechanism in Cartesian topologies.
+		transmission_mechanism: SimpleVirtualChannels,//or SharedBuffer{reserved_size:16} to share the space of each port among its virtual channels, or PauseFrames{headroom:24, xon_threshold:16, reaction_delay:0} for on/off flow control instead of credits, or Lossy to drop the packets that do not fit in the next buffer.
+		bubble: false,//to enable bubble mechanism in Cartesian topologies, or in the rings declared by the BubbleRings routing, each one being a link class or a set of ports. Hamming dimensions and dragonfly global links are not rings.
 		flit_size: 16,//set to maximum_packet_size to have Virtual Cut-Through.
 		intransit_priority: false,//whether to give preference to transit over injection.
 		allow_request_busy_port: true,//whether to allow input buffer to make requests to ports that are transmitting
 		output_buffer_size:32,//Available phits in each output_buffer.
+		crossbar_speedup: 1,//phits moved through each crossbar connection per cycle. An optional output_speedup limits the phits received by each output port per cycle. Both require output buffers.
 		output_priorize_lowest_label: true,//whether arbiters give priority to requests with lowest label.
+		//ecn_marking: EcnMarking{minimum_threshold:16, maximum_threshold:48, maximum_probability:0.2},//to mark the packets that leave through congested ports.
 	},
 	routing: ![//Algorithm to provide candidate exit ports.
 		Shortest { legend_name: "shortest" },
@@ -167,11 +175,20 @@ Configuration
 		LinkClass {
 			//In random regular graphs all router--router links have the same class.
 			delay:1,
+			transference_speed: [1,1],//x phits every y cycles. It defaults to a phit per cycle.
+			//Optionally, the energy of the links and their low-power state. See the energy module.
+			//energy_per_phit: 3, static_power: 0.2, sleep_after: 100, wake_up_delay: 20, sleep_static_power: 0.02,
 		},
 		//The last class always correspond to the links between server and router
 		LinkClass { delay: 1},
 		//In a dragonfly topology we would have 0=server, 1=routers from same group, 2=routers from different groups.
 	],
+	//Optionally, the servers may send again the packets dropped by lossy routers. SelectiveRepeat has the same fields. See the retransmission module.
+	//retransmission: GoBackN{window:16, timeout:2000, acknowledge_delay:100},
+	//The servers may throttle their injection from the ECN marks echoed by the destinations. None by default, or DCQCN or DCTCP. See the congestion_control module.
+	//congestion_control: DCTCP{initial_window:10, maximum_window:64, acknowledge_delay:100},
+	//The energy costs of the routers. Its presence adds the energy record to the result. See the energy module.
+	//energy: EnergyModel{buffer_write:1, buffer_read:1, crossbar_traversal:2, port_static_power:0.1, bits_per_phit:64},
 	launch_configurations: [
 		//We may put here options to send to the SLURM system.
 		Slurm
@@ -280,6 +297,10 @@ pub mod policies;
 pub mod experiments;
 pub mod config;
 pub mod error;
+pub mod flow_model;
+pub mod retransmission;
+pub mod congestion_control;
+pub mod energy;
 
 use std::rc::Rc;
 use std::boxed::Box;
@@ -302,8 +323,9 @@ use rand::{rngs::StdRng,SeedableRng};
 use config_parser::{ConfigurationValue,Expr};
 use topology::{Topology,new_topology,TopologyBuilderArgument,Location,
 	multistage::{Stage,StageBuilderArgument}};
-use traffic::{Traffic,new_traffic,TrafficBuilderArgument,TrafficError};
+use traffic::{Traffic,new_traffic,TrafficBuilderArgument,TrafficError,ServerTrafficState};
 use router::{Router,new_router,RouterBuilderArgument,TransmissionFromServer,TransmissionMechanism,StatusAtEmissor};
+use router::allocator::{SwitchAllocator,SwitchAllocatorBuilderArgument};
 use routing::{RoutingInfo,Routing,new_routing,RoutingBuilderArgument};
 use event::{EventQueue,Event};
 use quantify::Quantifiable;
@@ -311,6 +333,9 @@ use experiments::{Experiment,Action,ExperimentOptions};
 use policies::{VirtualChannelPolicy,VCPolicyBuilderArgument};
 use pattern::{Pattern,PatternBuilderArgument};
 use config::flatten_configuration_value;
+use retransmission::{RetransmissionConfiguration,ServerRetransmission};
+use congestion_control::{CongestionControl,new_congestion_control,CongestionControlBuilderArgument};
+use energy::EnergyModel;
 
 #[derive(Clone,Quantifiable)]
 struct ServerStatistics
@@ -352,7 +377,7 @@ impl ServerStatistics
 }
 
 ///The objects that create and consume traffic to/from the network.
-#[derive(Clone,Quantifiable)]
+#[derive(Quantifiable)]
 pub struct Server
 {
 	///The index of the server in the network.
@@ -371,16 +396,34 @@ pub struct Server
 	consumed_phits: BTreeMap<*const Message,usize>,
 	///Statistics local to the server.
 	statistics: ServerStatistics,
+	///The state of the retransmission protocol, if any.
+	retransmission: Option<ServerRetransmission>,
+	///Decides when new packets can be injected.
+	congestion_control: Box<dyn CongestionControl>,
+	///The echoes of the consumed packets towards their origins, as `(cycle_to_deliver,origin,size,marked)`.
+	congestion_echoes: VecDeque<(usize,usize,usize,bool)>,
+	///The traffic is not asked to generate messages in this server until this cycle, as told by `ServerTrafficState::WaitingCycle`.
+	traffic_waiting_until: usize,
 }
 
 impl Server
 {
 	///Consumes a phit
-	fn consume(&mut self, phit:Rc<Phit>, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)
+	fn consume(&mut self, phit:Rc<Phit>, traffic:&mut dyn Traffic, routing:&dyn Routing, statistics:&mut Statistics, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)
 	{
 		self.statistics.consumed_phits+=1;
 		//statistics.consumed_phits+=1;
 		statistics.track_consumed_phit(cycle);
+		//Duplicated or out of order packets are discarded by the retransmission protocol and do not count towards their message.
+		let accepted = match self.retransmission
+		{
+			Some(ref mut retransmission) => retransmission.accept_phit(&phit,cycle),
+			None => true,
+		};
+		if !accepted
+		{
+			statistics.current_measurement.discarded_phits+=1;
+		}
 		let message=phit.packet.message.clone();
 		let message_ptr=message.as_ref() as *const Message;
 		//println!("phit consumed at server {}: stats {:?}",self.index,statistics);
@@ -389,7 +432,11 @@ impl Server
 			None => 1,
 			Some(x) => x+1,
 		};
-		if cp==message.size
+		if !accepted
+		{
+			//Nothing to add to the message.
+		}
+		else if cp==message.size
 		{
 			//The whole message has been consumed
 			self.statistics.consumed_messages+=1;
@@ -399,6 +446,7 @@ impl Server
 			self.statistics.cycle_last_consumed_message = cycle;
 			//statistics.total_message_delay+=cycle-message.creation_cycle;
 			statistics.track_message_delay(cycle-message.creation_cycle,cycle);
+			statistics.track_message_size(message.size,cycle-message.creation_cycle);
 			self.consumed_phits.remove(&message_ptr);
 			if !traffic.try_consume(self.index,message,cycle,topology,rng)
 			{
@@ -419,10 +467,23 @@ impl Server
 			//	println!("server.consumed_phits.len()={}",n);
 			//}
 		}
-		if phit.is_end()
+		//The packets discarded by the retransmission protocol give no feedback, as they are not consumed.
+		if phit.is_end() && accepted
 		{
 			//statistics.consumed_packets+=1;
 			statistics.track_consumed_packet(cycle,&phit.packet);
+			let marked = *phit.packet.ecn_marked.borrow();
+			if marked
+			{
+				statistics.current_measurement.ecn_marked_packets+=1;
+			}
+			if let Some(delay) = self.congestion_control.acknowledge_delay()
+			{
+				self.congestion_echoes.push_back((cycle+delay,phit.packet.message.origin,phit.packet.size,marked));
+			}
+			//Give end-to-end feedback to the routing.
+			let network_delay = cycle - *phit.packet.cycle_into_network.borrow();
+			routing.performed_consumption(&phit.packet.routing_info,topology.as_ref(),phit.packet.message.destination,network_delay,marked,cycle,rng);
 			//let hops=phit.packet.routing_info.borrow().hops;
 			//statistics.total_packet_hops+=hops;
 			//statistics.track_packet_hops(hops,cycle);
@@ -436,6 +497,10 @@ impl Server
 				println!("phit tail has been consuming without haing consumed a whole packet.");
 			}
 		}
+		else if phit.is_end()
+		{
+			routing.performed_loss(&phit.packet.routing_info,topology.as_ref(),phit.packet.message.destination,cycle,rng);
+		}
 	}
 }
 
@@ -565,6 +630,12 @@ pub struct Packet
 	pub cycle_into_network: RefCell<usize>,
 	///Extra info tracked for some special statistics.
 	pub extra: RefCell<Option<PacketExtraInfo>>,
+	///The position of the packet in the flow from its origin to its destination, when using a retransmission protocol.
+	pub sequence_number: Option<usize>,
+	///Whether the packet has been dropped by a lossy router. The phits behind the leading one are discarded as they arrive.
+	pub dropped: RefCell<bool>,
+	///Whether a router has marked the packet as having gone through a congested port (ECN).
+	pub ecn_marked: RefCell<bool>,
 }
 
 ///An application message, broken into packets
@@ -604,8 +675,19 @@ pub struct LinkClass
 {
 	///Cycles the phit needs to move from one endpoint to the other endpoint.
 	pub delay: usize,
-	//(x,y) means x phits each y cycles ??
-	//transference_speed: (usize,usize)
+	///(x,y) means x phits each y cycles. Given as `transference_speed: [x,y]` in the configuration, or as a single integer for x phits per cycle.
+	///By default it is (1,1).
+	pub transference_speed: (usize,usize),
+	///Energy consumed by each phit traversing the link. See the `energy` module.
+	pub energy_per_phit: f64,
+	///Energy consumed by each direction of the link in each cycle it is not sleeping.
+	pub static_power: f64,
+	///If not None, the links between routers enter a low-power state after this number of cycles without traffic.
+	pub sleep_after: Option<usize>,
+	///Additional delay of the first phit sent through a sleeping link.
+	pub wake_up_delay: usize,
+	///Energy consumed by each direction of the link in each cycle it is sleeping.
+	pub sleep_static_power: f64,
 }
 
 impl LinkClass
@@ -613,6 +695,12 @@ impl LinkClass
 	fn new(cv:&ConfigurationValue) -> LinkClass
 	{
 		let mut delay=None;
+		let mut transference_speed=(1,1);
+		let mut energy_per_phit=0.0;
+		let mut static_power=0.0;
+		let mut sleep_after=None;
+		let mut wake_up_delay=0;
+		let mut sleep_static_power=0.0;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="LinkClass"
@@ -628,7 +716,41 @@ impl LinkClass
 						&ConfigurationValue::Number(f) => delay=Some(f as usize),
 						_ => panic!("bad value for delay"),
 					},
-					"transference_speed" => (),//FIXME
+					"transference_speed" => match value
+					{
+						&ConfigurationValue::Number(f) => transference_speed=(f as usize,1),
+						&ConfigurationValue::Array(ref a) => match a.as_slice()
+						{
+							&[ConfigurationValue::Number(x),ConfigurationValue::Number(y)] => transference_speed=(x as usize,y as usize),
+							_ => panic!("bad value for transference_speed"),
+						},
+						_ => panic!("bad value for transference_speed"),
+					},
+					"energy_per_phit" => match value
+					{
+						&ConfigurationValue::Number(f) => energy_per_phit=f,
+						_ => panic!("bad value for energy_per_phit"),
+					},
+					"static_power" => match value
+					{
+						&ConfigurationValue::Number(f) => static_power=f,
+						_ => panic!("bad value for static_power"),
+					},
+					"sleep_after" => match value
+					{
+						&ConfigurationValue::Number(f) => sleep_after=Some(f as usize),
+						_ => panic!("bad value for sleep_after"),
+					},
+					"wake_up_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => wake_up_delay=f as usize,
+						_ => panic!("bad value for wake_up_delay"),
+					},
+					"sleep_static_power" => match value
+					{
+						&ConfigurationValue::Number(f) => sleep_static_power=f,
+						_ => panic!("bad value for sleep_static_power"),
+					},
 					_ => panic!("Nothing to do with field {} in LinkClass",name),
 				}
 			}
@@ -638,10 +760,32 @@ impl LinkClass
 			panic!("Trying to create a LinkClass from a non-Object");
 		}
 		let delay=delay.expect("There were no delay");
+		if transference_speed.0==0 || transference_speed.1==0
+		{
+			panic!("The transference_speed of a LinkClass must have positive values, got {:?}",transference_speed);
+		}
 		LinkClass{
 			delay,
+			transference_speed,
+			energy_per_phit,
+			static_power,
+			sleep_after,
+			wake_up_delay,
+			sleep_static_power,
 		}
 	}
+	///The number of phits that can start to traverse the link at the given cycle.
+	///The phits are spread as evenly as possible, so that at most x phits start every y cycles.
+	pub fn phits_at_cycle(&self, cycle:usize) -> usize
+	{
+		let (x,y)=self.transference_speed;
+		((cycle+1)*x)/y - (cycle*x)/y
+	}
+	///The average number of phits per cycle that the link can carry.
+	pub fn capacity(&self) -> f64
+	{
+		self.transference_speed.0 as f64 / self.transference_speed.1 as f64
+	}
 }
 
 ///Statistics captured for each link.
@@ -649,19 +793,90 @@ impl LinkClass
 struct LinkStatistics
 {
 	phit_arrivals: usize,
+	///The packets dropped by the router at the end of the link because there was no space for them.
+	dropped_packets: usize,
+	///The phits per cycle that the link can carry, from the `transference_speed` of its class.
+	capacity: f64,
+	///The `sleep_after` of its class, if the link goes between routers.
+	sleep_after: Option<usize>,
+	///The `wake_up_delay` of its class.
+	wake_up_delay: usize,
+	///The cycle in which the last phit was sent through the link.
+	last_transmission: usize,
+	///The cycle in which the last phit sent through the link arrives.
+	last_arrival: usize,
+	///Cycles the link has been sleeping, not including the current idle period.
+	sleeping_cycles: usize,
+	///Number of times the link has been woken up.
+	wake_ups: usize,
 }
 
 impl LinkStatistics
 {
-	fn new() -> LinkStatistics
+	fn new(link_class:&LinkClass, between_routers:bool) -> LinkStatistics
 	{
 		LinkStatistics{
 			phit_arrivals: 0,
+			dropped_packets: 0,
+			capacity: link_class.capacity(),
+			sleep_after: if between_routers { link_class.sleep_after } else { None },
+			wake_up_delay: link_class.wake_up_delay,
+			last_transmission: 0,
+			last_arrival: 0,
+			sleeping_cycles: 0,
+			wake_ups: 0,
+		}
+	}
+	///The delay of a phit sent through the link at `cycle` when its nominal delay is `delay`.
+	///If the link was sleeping it pays the `wake_up_delay`. The phits never overtake the previous ones.
+	///Only the sleeping cycles since `begin_cycle` are counted.
+	fn transmission_delay(&mut self, cycle:usize, delay:usize, begin_cycle:usize) -> usize
+	{
+		let sleep_after = match self.sleep_after
+		{
+			Some(sleep_after) => sleep_after,
+			None => return delay,
+		};
+		let mut arrival = cycle + delay;
+		let sleep_begin = self.last_transmission + sleep_after;
+		if cycle > sleep_begin
+		{
+			arrival += self.wake_up_delay;
+			if cycle > begin_cycle
+			{
+				self.sleeping_cycles += cycle - sleep_begin.max(begin_cycle);
+				self.wake_ups += 1;
+			}
+		}
+		arrival = arrival.max(self.last_arrival);
+		self.last_arrival = arrival;
+		self.last_transmission = cycle;
+		arrival - cycle
+	}
+	///The cycles the link has been sleeping since `begin_cycle` until `cycle`.
+	fn sleeping_cycles_until(&self, cycle:usize, begin_cycle:usize) -> usize
+	{
+		match self.sleep_after
+		{
+			Some(sleep_after) =>
+			{
+				let sleep_begin = (self.last_transmission + sleep_after).max(begin_cycle);
+				self.sleeping_cycles + cycle.saturating_sub(sleep_begin)
+			},
+			None => 0,
 		}
 	}
+	///The fraction of the capacity of the link used during the given number of cycles.
+	fn utilization(&self, cycles:usize) -> f64
+	{
+		self.phit_arrivals as f64 / cycles as f64 / self.capacity
+	}
 	fn reset(&mut self)
 	{
 		self.phit_arrivals=0;
+		self.dropped_packets=0;
+		self.sleeping_cycles=0;
+		self.wake_ups=0;
 	}
 }
 
@@ -687,6 +902,18 @@ pub struct StatisticMeasurement
 	total_packet_hops: usize,
 	///Count of consumed packets indexed by the number of hops it made.
 	total_packet_per_hop_count: Vec<usize>,
+	///Number of packets dropped by lossy routers.
+	dropped_packets: usize,
+	///Number of packets sent again by the retransmission protocol.
+	retransmitted_packets: usize,
+	///Number of phits in the packets sent again by the retransmission protocol.
+	retransmitted_phits: usize,
+	///Number of consumed phits that the retransmission protocol discarded, as duplicated or out of order.
+	discarded_phits: usize,
+	///Number of consumed packets that were marked by the routers as having gone through congestion.
+	ecn_marked_packets: usize,
+	///Number of cycles in which a server had a packet ready but its congestion control did not allow to inject it.
+	congestion_throttled_cycles: usize,
 }
 
 impl StatisticMeasurement
@@ -739,6 +966,18 @@ struct StatisticPacketMeasurement
 	delay: usize,
 }
 
+///The messages consumed with sizes in some range.
+#[derive(Debug,Default,Clone)]
+struct StatisticMessageSizeMeasurement
+{
+	///Number of messages consumed.
+	consumed_messages: usize,
+	///Number of phits of the consumed messages.
+	consumed_phits: usize,
+	///Accumulated delay of the consumed messages.
+	total_message_delay: usize,
+}
+
 ///All the global statistics captured.
 #[derive(Debug)]
 pub struct Statistics
@@ -772,13 +1011,20 @@ pub struct Statistics
 	///For each definition of packet statistics, we have a vector with an element for each actual value of `keys`.
 	///Each of these elements have that value of `key`, together with the averages and the count.
 	packet_defined_statistics_measurement: Vec< Vec< (Vec<ConfigurationValue>,Vec<f32>,usize) >>,
+	///The greatest size of each bucket into which the consumed messages are classified. There is an additional bucket for the greater sizes.
+	///The buckets appear in the `message_size_statistics` field of the result file if this is not empty.
+	message_size_buckets: Vec<usize>,
+	///The measurement of each bucket in `message_size_buckets`, plus the one of the messages greater than all of them.
+	message_size_measurement: Vec<StatisticMessageSizeMeasurement>,
 }
 
 impl Statistics
 {
-	fn new(statistics_temporal_step:usize, server_percentiles: Vec<u8>, packet_percentiles: Vec<u8>, statistics_packet_definitions:Vec<(Vec<Expr>,Vec<Expr>)>, topology: &dyn Topology)->Statistics
+	fn new(statistics_temporal_step:usize, server_percentiles: Vec<u8>, packet_percentiles: Vec<u8>, statistics_packet_definitions:Vec<(Vec<Expr>,Vec<Expr>)>, message_size_buckets: Vec<usize>, topology: &dyn Topology, link_classes: &[LinkClass])->Statistics
 	{
 		let packet_defined_statistics_measurement = vec![ vec![]; statistics_packet_definitions.len() ];
+		assert!(message_size_buckets.windows(2).all(|pair|pair[0]<pair[1]),"The statistics_message_size_buckets must be increasing.");
+		let message_size_measurement = if message_size_buckets.is_empty() { vec![] } else { vec![ Default::default(); message_size_buckets.len()+1 ] };
 		Statistics{
 			//begin_cycle:0,
 			//created_phits:0,
@@ -789,7 +1035,10 @@ impl Statistics
 			//total_packet_hops:0,
 			//total_packet_per_hop_count:Vec::new(),
 			current_measurement: Default::default(),
-			link_statistics: (0..topology.num_routers()).map(|i| (0..topology.ports(i)).map(|_|LinkStatistics::new()).collect() ).collect(),
+			link_statistics: (0..topology.num_routers()).map(|i| (0..topology.ports(i)).map(|j|{
+				let (location,link_class) = topology.neighbour(i,j);
+				LinkStatistics::new(&link_classes[link_class],matches!(location,Location::RouterPort{..}))
+			}).collect() ).collect(),
 			temporal_step: statistics_temporal_step,
 			temporal_statistics: vec![],
 			server_percentiles,
@@ -808,8 +1057,22 @@ impl Statistics
 				],
 			packet_defined_statistics_definitions:statistics_packet_definitions,
 			packet_defined_statistics_measurement,
+			message_size_buckets,
+			message_size_measurement,
 		}
 	}
+	///The average over the links between routers of the fraction of their capacity being used.
+	fn average_link_utilization(&self, network:&Network, cycles:usize) -> f64
+	{
+		let total_utilization:f64 = (0..network.topology.num_routers()).map(|i|(0..network.topology.degree(i)).map(|j|self.link_statistics[i][j].utilization(cycles)).sum::<f64>()).sum();
+		let total_links: usize = (0..network.topology.num_routers()).map(|i|network.topology.degree(i)).sum();
+		total_utilization / total_links as f64
+	}
+	///The greatest fraction of its capacity used by a link.
+	fn maximum_link_utilization(&self, cycles:usize) -> f64
+	{
+		self.link_statistics.iter().flat_map(|rls|rls.iter().map(|ls|ls.utilization(cycles))).fold(0f64,f64::max)
+	}
 	///Print in stdout a header showing the statistical columns to be periodically printed.
 	fn print_header(&self)
 	{
@@ -842,6 +1105,10 @@ impl Statistics
 		//self.total_packet_per_hop_count=Vec::new();
 		self.current_measurement=Default::default();
 		self.current_measurement.begin_cycle=next_cycle;
+		for measurement in self.message_size_measurement.iter_mut()
+		{
+			*measurement=Default::default();
+		}
 		for server in network.servers.iter_mut()
 		{
 			server.statistics.reset();
@@ -962,6 +1229,17 @@ impl Statistics
 			self.temporal_statistics[index].consumed_messages+=1;
 		}
 	}
+	fn track_message_size(&mut self, size:usize, delay:usize)
+	{
+		if !self.message_size_measurement.is_empty()
+		{
+			let index = self.message_size_buckets.partition_point(|&maximum|maximum<size);
+			let measurement = &mut self.message_size_measurement[index];
+			measurement.consumed_messages+=1;
+			measurement.consumed_phits+=size;
+			measurement.total_message_delay+=delay;
+		}
+	}
 	fn track_created_phit(&mut self, cycle:usize)
 	{
 		self.current_measurement.created_phits+=1;
@@ -1085,17 +1363,8 @@ impl ReportColumn
 			ReportColumnKind::AverageMessageDelay => format!("{}",statistics.current_measurement.total_message_delay as f64/statistics.current_measurement.consumed_messages as f64),
 			ReportColumnKind::AveragePacketNetworkDelay => format!("{}",statistics.current_measurement.total_packet_network_delay as f64/statistics.current_measurement.consumed_packets as f64),
 			ReportColumnKind::AveragePacketHops => format!("{}",statistics.current_measurement.total_packet_hops as f64 / statistics.current_measurement.consumed_packets as f64),
-			ReportColumnKind::AverageLinkUtilization =>
-			{
-				let total_arrivals:usize = (0..network.topology.num_routers()).map(|i|(0..network.topology.degree(i)).map(|j|statistics.link_statistics[i][j].phit_arrivals).sum::<usize>()).sum();
-				let total_links: usize = (0..network.topology.num_routers()).map(|i|network.topology.degree(i)).sum();
-				format!("{}",total_arrivals as f64 / cycles as f64 / total_links as f64)
-			},
-			ReportColumnKind::MaximumLinkUtilization =>
-			{
-				let maximum_arrivals:usize = statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).max().unwrap()).max().unwrap();
-				format!("{}",maximum_arrivals as f64 / cycles as f64)
-			},
+			ReportColumnKind::AverageLinkUtilization => format!("{}",statistics.average_link_utilization(network,cycles)),
+			ReportColumnKind::MaximumLinkUtilization => format!("{}",statistics.maximum_link_utilization(cycles)),
 		};
 		format!("{value:width$}",value=value,width=self.width)
 	}
@@ -1143,6 +1412,10 @@ pub struct Simulation<'a>
 	///Attemps to generate traffic that fails because of the limit are tracked into the `missed_generations` statistic.
 	///Note that packets are not generated until it is the turn for the message to be sent to a router.
 	pub server_queue_size: usize,
+	///The protocol used by the servers to send again the packets dropped by lossy routers. By default there is none.
+	pub retransmission: Option<RetransmissionConfiguration>,
+	///The energy costs of the routers. If given, the energy consumed by the network is included in the result.
+	pub energy: Option<EnergyModel>,
 	///The queue of events guiding the simulation.
 	pub event_queue: EventQueue,
 	///The current cycle, i.e, the current discrete time.
@@ -1174,7 +1447,11 @@ impl<'a> Simulation<'a>
 		let mut statistics_server_percentiles: Vec<u8> = vec![];
 		let mut statistics_packet_percentiles: Vec<u8> = vec![];
 		let mut statistics_packet_definitions:Vec< (Vec<Expr>,Vec<Expr>) > = vec![];
+		let mut statistics_message_size_buckets: Vec<usize> = vec![];
 		let mut server_queue_size = None;
+		let mut retransmission = None;
+		let mut congestion_control = None;
+		let mut energy = None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="Configuration"
@@ -1218,6 +1495,9 @@ impl<'a> Simulation<'a>
 						_ => panic!("bad value for server_queue_size"),
 					}
 					"router" => router_cfg=Some(&value),
+					"retransmission" => retransmission=Some(RetransmissionConfiguration::new(value)),
+					"congestion_control" => congestion_control=Some(value),
+					"energy" => energy=Some(EnergyModel::new(value)),
 					"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs})),
 					"link_classes" => match value
 					{
@@ -1250,6 +1530,14 @@ impl<'a> Simulation<'a>
 						}).collect(),
 						_ => panic!("bad value for statistics_packet_percentiles"),
 					}
+					"statistics_message_size_buckets" => match value
+					{
+						&ConfigurationValue::Array(ref l) => statistics_message_size_buckets=l.iter().map(|v|match v{
+							&ConfigurationValue::Number(f) => f as usize,
+							x => panic!("{} is a bad value for statistics_message_size_buckets",x),
+						}).collect(),
+						_ => panic!("bad value for statistics_message_size_buckets"),
+					}
 					"statistics_packet_definitions" => match value
 					{
 						&ConfigurationValue::Array(ref l) => statistics_packet_definitions=l.iter().map(|definition|match definition {
@@ -1325,8 +1613,11 @@ impl<'a> Simulation<'a>
 			plugs,
 			topology:topology.as_ref(),
 			maximum_packet_size,
+			link_classes:&link_classes,
 			statistics_temporal_step,
 		})).collect();
+		let default_congestion_control = ConfigurationValue::Object(String::from("None"),vec![]);
+		let congestion_control = congestion_control.unwrap_or(&default_congestion_control);
 		let servers=(0..num_servers).map(|index|{
 			let port=topology.server_neighbour(index);
 			let router_status=match port.0
@@ -1355,6 +1646,14 @@ impl<'a> Simulation<'a>
 				stored_phits:VecDeque::new(),
 				consumed_phits: BTreeMap::new(),
 				statistics: ServerStatistics::new(),
+				retransmission: retransmission.as_ref().map(ServerRetransmission::new),
+				congestion_control: new_congestion_control(CongestionControlBuilderArgument{
+					cv:congestion_control,
+					plugs,
+					server_index:index,
+				}),
+				congestion_echoes: VecDeque::new(),
+				traffic_waiting_until: 0,
 			}
 		}).collect();
 		let traffic=new_traffic(TrafficBuilderArgument{
@@ -1363,7 +1662,7 @@ impl<'a> Simulation<'a>
 			topology:&topology,
 			rng:&rng,
 		});
-		let statistics=Statistics::new(statistics_temporal_step,statistics_server_percentiles,statistics_packet_percentiles,statistics_packet_definitions,topology.as_ref());
+		let statistics=Statistics::new(statistics_temporal_step,statistics_server_percentiles,statistics_packet_percentiles,statistics_packet_definitions,statistics_message_size_buckets,topology.as_ref(),&link_classes);
 		Simulation{
 			configuration: cv.clone(),
 			seed,
@@ -1380,6 +1679,8 @@ impl<'a> Simulation<'a>
 			routing,
 			link_classes,
 			server_queue_size,
+			retransmission,
+			energy,
 			event_queue: EventQueue::new(1000),
 			cycle:0,
 			statistics,
@@ -1399,6 +1700,7 @@ impl<'a> Simulation<'a>
 			{
 				self.statistics.reset(self.cycle,&mut self.network);
 				self.routing.reset_statistics(self.cycle);
+				self.traffic.reset_statistics(self.cycle);
 			}
 			if self.traffic.is_finished()
 			{
@@ -1457,6 +1759,11 @@ impl<'a> Simulation<'a>
 							}
 							let mut brouter=self.network.routers[router].borrow_mut();
 							brouter.insert(phit.clone(),port,&self.rng);
+							if phit.is_begin() && *phit.packet.dropped.borrow()
+							{
+								self.statistics.link_statistics[router][port].dropped_packets+=1;
+								self.statistics.current_measurement.dropped_packets+=1;
+							}
 							if brouter.pending_events()==0
 							{
 								brouter.add_pending_event();
@@ -1478,6 +1785,11 @@ impl<'a> Simulation<'a>
 								},
 								_ => (),
 							};
+							//Once the routing information is updated, so that the routing knows where the packet was lost.
+							if phit.is_begin() && *phit.packet.dropped.borrow()
+							{
+								self.routing.performed_loss(&phit.packet.routing_info, self.network.topology.as_ref(), phit.packet.message.destination,self.cycle,&self.rng);
+							}
 						},
 						&Location::ServerPort(server) =>
 						{
@@ -1485,7 +1797,7 @@ impl<'a> Simulation<'a>
 							{
 								panic!("Packet reached wrong server, {} instead of {}!\n",server,phit.packet.message.destination);
 							}
-							self.network.servers[server].consume(phit.clone(),self.traffic.deref_mut(),&mut self.statistics,self.cycle,&self.network.topology,&self.rng);
+							self.network.servers[server].consume(phit.clone(),self.traffic.deref_mut(),self.routing.as_ref(),&mut self.statistics,self.cycle,&self.network.topology,&self.rng);
 						}
 						&Location::None => panic!("Phit went nowhere previous={:?}",previous),
 					};
@@ -1554,8 +1866,13 @@ impl<'a> Simulation<'a>
 				{
 					let new_events=element.borrow_mut().process(self);
 					//element.borrow_mut().clear_pending_events();//now done by process itself
-					for ge in new_events.into_iter()
+					for mut ge in new_events.into_iter()
 					{
+						if let Event::PhitToLocation{new:Location::RouterPort{router_index,router_port},..} = ge.event
+						{
+							//Links may be sleeping.
+							ge.delay = self.statistics.link_statistics[router_index][router_port].transmission_delay(self.cycle,ge.delay,self.statistics.current_measurement.begin_cycle);
+						}
 						self.event_queue.enqueue(ge);
 					}
 				},
@@ -1564,12 +1881,58 @@ impl<'a> Simulation<'a>
 		}
 		//println!("Done cycle-end events");
 		let num_servers=self.network.servers.len();
+		//Deliver the echoes of the consumed packets to the congestion control of their origins.
+		let mut echoes = vec![];
+		for (iserver,server) in self.network.servers.iter_mut().enumerate()
+		{
+			while let Some(&(delivery,origin,size,marked)) = server.congestion_echoes.front()
+			{
+				if delivery>self.cycle
+				{
+					break;
+				}
+				server.congestion_echoes.pop_front();
+				echoes.push((origin,iserver,size,marked));
+			}
+		}
+		for (origin,destination,size,marked) in echoes
+		{
+			self.network.servers[origin].congestion_control.notify_echo(destination,size,marked,self.cycle);
+		}
+		if self.retransmission.is_some()
+		{
+			//Deliver the acknowledgements to the origins of the flows and look for the packets to send again.
+			let mut acknowledgements = vec![];
+			for (iserver,server) in self.network.servers.iter_mut().enumerate()
+			{
+				let retransmission = server.retransmission.as_mut().expect("server without retransmission");
+				for (origin,sequence_number) in retransmission.take_acknowledgements(self.cycle)
+				{
+					acknowledgements.push((origin,iserver,sequence_number));
+				}
+			}
+			for (origin,destination,sequence_number) in acknowledgements
+			{
+				self.network.servers[origin].retransmission.as_mut().expect("server without retransmission").acknowledge(destination,sequence_number);
+			}
+			for server in self.network.servers.iter_mut()
+			{
+				for (destination,size) in server.retransmission.as_mut().expect("server without retransmission").check_timeouts(self.cycle)
+				{
+					server.congestion_control.notify_loss(destination,size,self.cycle);
+				}
+			}
+		}
 		for (iserver,server) in self.network.servers.iter_mut().enumerate()
 		{
 			//println!("credits of {} = {}",iserver,server.credits);
 			if let (Location::RouterPort{router_index: index,router_port: port},link_class)=server.port
 			{
-				if self.traffic.should_generate(iserver,self.cycle,&self.rng)
+				if self.cycle<server.traffic_waiting_until
+				{
+					//The server is idle in the traffic until that cycle.
+				}
+				else if self.traffic.should_generate(iserver,self.cycle,&self.rng)
 				{
 					if server.stored_messages.len()<self.server_queue_size {
 						match self.traffic.generate_message(iserver,self.cycle,&self.network.topology,&self.rng)
@@ -1595,42 +1958,9 @@ impl<'a> Simulation<'a>
 						server.statistics.missed_generations += 1;
 					}
 				}
-				if server.stored_packets.len()==0 && server.stored_messages.len()>0
-				{
-					let message=server.stored_messages.pop_front().expect("There are not messages in queue");
-					let mut size=message.size;
-					while size>0
-					{
-						let ps=if size>self.maximum_packet_size
-						{
-							self.maximum_packet_size
-						}
-						else
-						{
-							size
-						};
-						server.stored_packets.push_back(Rc::new(Packet{
-							size:ps,
-							routing_info: RefCell::new(RoutingInfo::new()),
-							message:message.clone(),
-							index:0,
-							cycle_into_network:RefCell::new(0),
-							extra: RefCell::new(None),
-						}));
-						size-=ps;
-					}
-				}
-				if server.stored_phits.len()==0 && server.stored_packets.len()>0
+				else if let ServerTrafficState::WaitingCycle{cycle} = self.traffic.server_state(iserver,self.cycle)
 				{
-					let packet=server.stored_packets.pop_front().expect("There are not packets in queue");
-					for index in 0..packet.size
-					{
-						server.stored_phits.push_back(Rc::new(Phit{
-							packet:packet.clone(),
-							index,
-							virtual_channel: RefCell::new(None),
-						}));
-					}
+					server.traffic_waiting_until = cycle;
 				}
 				//if server.stored_phits.len()>0 && server.credits>0
 				//{
@@ -1645,13 +1975,102 @@ impl<'a> Simulation<'a>
 				//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
 				//	server.credits-=1;
 				//}
-				if server.stored_phits.len()>0
+				//Whether the congestion control has held a packet back in this cycle. Counted once even if the link could carry several phits.
+				let mut throttled = false;
+				//The server link may carry several phits in a cycle, or may not be available in this one.
+				for _ in 0..self.link_classes[link_class].phits_at_cycle(self.cycle)
 				{
-					//Do not extract the phit until we know whether we can transmit it.
-					let phit=server.stored_phits.front().expect("There are not phits");
-					if server.router_status.can_transmit(&phit,0)
+					if server.stored_packets.len()==0 && server.stored_messages.len()>0
 					{
+						let message=server.stored_messages.pop_front().expect("There are not messages in queue");
+						let mut size=message.size;
+						while size>0
+						{
+							let ps=if size>self.maximum_packet_size
+							{
+								self.maximum_packet_size
+							}
+							else
+							{
+								size
+							};
+							server.stored_packets.push_back(Rc::new(Packet{
+								size:ps,
+								routing_info: RefCell::new(RoutingInfo::new()),
+								message:message.clone(),
+								index:0,
+								cycle_into_network:RefCell::new(0),
+								extra: RefCell::new(None),
+								sequence_number: None,
+								dropped: RefCell::new(false),
+								ecn_marked: RefCell::new(false),
+							}));
+							size-=ps;
+						}
+					}
+					if server.stored_phits.len()==0
+					{
+						//The congestion control only restrains the new packets, not the retransmissions.
+						//Still, it is notified of every packet sent, as each of them is either echoed or given up by the retransmission protocol.
+						let new_allowed = match server.stored_packets.front()
+						{
+							Some(packet) => server.congestion_control.can_send(packet.message.destination,packet.size,self.cycle),
+							None => true,
+						};
+						let retransmitted = match server.retransmission
+						{
+							Some(ref mut retransmission) => retransmission.next_retransmission(),
+							None => None,
+						};
+						let packet = if let Some(packet)=retransmitted
+						{
+							self.statistics.current_measurement.retransmitted_packets+=1;
+							self.statistics.current_measurement.retransmitted_phits+=packet.size;
+							Some(packet)
+						}
+						else if !new_allowed
+						{
+							throttled = true;
+							None
+						}
+						else
+						{
+							match server.retransmission
+							{
+								Some(ref mut retransmission) => retransmission.next_new_packet(&mut server.stored_packets),
+								None => server.stored_packets.pop_front(),
+							}
+						};
+						if let Some(packet)=packet
+						{
+							server.congestion_control.notify_sent(packet.message.destination,packet.size,self.cycle);
+							for index in 0..packet.size
+							{
+								server.stored_phits.push_back(Rc::new(Phit{
+									packet:packet.clone(),
+									index,
+									virtual_channel: RefCell::new(None),
+								}));
+							}
+						}
+					}
+					if server.stored_phits.len()>0
+					{
+						//Do not extract the phit until we know whether we can transmit it.
+						let phit=server.stored_phits.front().expect("There are not phits");
+						if !server.router_status.can_transmit(&phit,0)
+						{
+							break;
+						}
 						let phit=server.stored_phits.pop_front().expect("There are not phits");
+						if phit.is_begin()
+						{
+							//The timeout of the retransmission protocol starts when the packet enters the network.
+							if let Some(ref mut retransmission) = server.retransmission
+							{
+								retransmission.notify_injection(&phit.packet,self.cycle);
+							}
+						}
 						let event=Event::PhitToLocation{
 							phit,
 							previous: Location::ServerPort(iserver),
@@ -1664,6 +2083,14 @@ impl<'a> Simulation<'a>
 						self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
 						server.router_status.notify_outcoming_phit(0,self.cycle);
 					}
+					else
+					{
+						break;
+					}
+				}
+				if throttled
+				{
+					self.statistics.current_measurement.congestion_throttled_cycles+=1;
 				}
 			}
 			else
@@ -1708,11 +2135,8 @@ impl<'a> Simulation<'a>
 		let total_packet_per_hop_count=measurement.total_packet_per_hop_count.iter().map(|&count|ConfigurationValue::Number(count as f64)).collect();
 		//let total_arrivals:usize = self.statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).sum::<usize>()).sum();
 		//let total_links:usize = self.statistics.link_statistics.iter().map(|rls|rls.len()).sum();
-		let total_arrivals:usize = (0..self.network.topology.num_routers()).map(|i|(0..self.network.topology.degree(i)).map(|j|self.statistics.link_statistics[i][j].phit_arrivals).sum::<usize>()).sum();
-		let total_links: usize = (0..self.network.topology.num_routers()).map(|i|self.network.topology.degree(i)).sum();
-		let average_link_utilization = total_arrivals as f64 / cycles as f64 / total_links as f64;
-		let maximum_arrivals:usize = self.statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).max().unwrap()).max().unwrap();
-		let maximum_link_utilization = maximum_arrivals as f64 / cycles as f64;
+		let average_link_utilization = self.statistics.average_link_utilization(&self.network,cycles);
+		let maximum_link_utilization = self.statistics.maximum_link_utilization(cycles);
 		let server_average_cycle_last_created_phit : f64 = (self.network.servers.iter().map(|s|s.statistics.cycle_last_created_phit).sum::<usize>() as f64)/(self.network.servers.len() as f64);
 		let server_average_cycle_last_consumed_message : f64 = (self.network.servers.iter().map(|s|s.statistics.cycle_last_consumed_message).sum::<usize>() as f64)/(self.network.servers.len() as f64);
 		let server_average_missed_generations : f64 = (self.network.servers.iter().map(|s|s.statistics.missed_generations).sum::<usize>() as f64)/(self.network.servers.len() as f64);
@@ -1739,6 +2163,10 @@ impl<'a> Simulation<'a>
 			(String::from("git_id"),ConfigurationValue::Literal(format!("{}",git_id))),
 			(String::from("version_number"),ConfigurationValue::Literal(format!("{}",version_number))),
 		];
+		if let Some(content)=self.traffic.statistics(self.cycle)
+		{
+			result_content.push((String::from("traffic_statistics"),content));
+		}
 		if let Some(content)=self.routing.statistics(self.cycle)
 		{
 			result_content.push((String::from("routing_statistics"),content));
@@ -1747,6 +2175,94 @@ impl<'a> Simulation<'a>
 		{
 			result_content.push((String::from("router_aggregated_statistics"),content));
 		}
+		if self.retransmission.is_some() || measurement.dropped_packets>0
+		{
+			//The goodput excludes the phits discarded by the retransmission protocol, while the accepted_load includes them.
+			let goodput=(measurement.consumed_phits-measurement.discarded_phits) as f64/cycles as f64/num_servers as f64;
+			let retransmitted_load=measurement.retransmitted_phits as f64/cycles as f64/num_servers as f64;
+			let maximum_link_dropped_packets=self.statistics.link_statistics.iter().flat_map(|rls|rls.iter().map(|ls|ls.dropped_packets)).max().unwrap_or(0);
+			//Indexed by router and port, as the links.
+			let link_dropped_packets=self.statistics.link_statistics.iter().map(|rls|ConfigurationValue::Array(rls.iter().map(|ls|ConfigurationValue::Number(ls.dropped_packets as f64)).collect())).collect();
+			let loss_content = vec![
+				(String::from("dropped_packets"),ConfigurationValue::Number(measurement.dropped_packets as f64)),
+				(String::from("maximum_link_dropped_packets"),ConfigurationValue::Number(maximum_link_dropped_packets as f64)),
+				(String::from("link_dropped_packets"),ConfigurationValue::Array(link_dropped_packets)),
+				(String::from("retransmitted_packets"),ConfigurationValue::Number(measurement.retransmitted_packets as f64)),
+				(String::from("retransmitted_load"),ConfigurationValue::Number(retransmitted_load)),
+				(String::from("goodput"),ConfigurationValue::Number(goodput)),
+			];
+			result_content.push((String::from("loss_statistics"),ConfigurationValue::Object(String::from("LossStatistics"),loss_content)));
+		}
+		if let Some(ref energy) = self.energy
+		{
+			let topology = &self.network.topology;
+			let mut link_dynamic_energy = 0f64;
+			let mut link_static_energy = 0f64;
+			let mut router_link_arrivals = 0;
+			let mut total_arrivals = 0;
+			let mut sleeping_cycles = 0;
+			let mut wake_ups = 0;
+			let mut router_links = 0;
+			let mut router_ports = 0;
+			for (router,router_links_statistics) in self.statistics.link_statistics.iter().enumerate()
+			{
+				router_ports += router_links_statistics.len();
+				for (port,link) in router_links_statistics.iter().enumerate()
+				{
+					let (location,link_class) = topology.neighbour(router,port);
+					let link_class = &self.link_classes[link_class];
+					let sleeping = link.sleeping_cycles_until(self.cycle,measurement.begin_cycle);
+					link_dynamic_energy += link.phit_arrivals as f64 * link_class.energy_per_phit;
+					link_static_energy += (cycles-sleeping) as f64 * link_class.static_power + sleeping as f64 * link_class.sleep_static_power;
+					total_arrivals += link.phit_arrivals;
+					if let Location::RouterPort{..} = location
+					{
+						router_link_arrivals += link.phit_arrivals;
+						sleeping_cycles += sleeping;
+						wake_ups += link.wake_ups;
+						router_links += 1;
+					}
+				}
+			}
+			//The links from the routers into the servers.
+			for (index,server) in self.network.servers.iter().enumerate()
+			{
+				let link_class = &self.link_classes[topology.server_neighbour(index).1];
+				link_dynamic_energy += server.statistics.consumed_phits as f64 * link_class.energy_per_phit;
+				link_static_energy += cycles as f64 * link_class.static_power;
+			}
+			//Every phit leaving a router has been read from a buffer and has traversed its crossbar.
+			let router_departures = router_link_arrivals + measurement.consumed_phits;
+			let buffer_energy = total_arrivals as f64 * energy.buffer_write + router_departures as f64 * energy.buffer_read;
+			let crossbar_energy = router_departures as f64 * energy.crossbar_traversal;
+			let router_static_energy = (router_ports * cycles) as f64 * energy.port_static_power;
+			let total_energy = link_dynamic_energy + link_static_energy + buffer_energy + crossbar_energy + router_static_energy;
+			let delivered_bits = (measurement.consumed_phits-measurement.discarded_phits) as f64 * energy.bits_per_phit;
+			let energy_content = vec![
+				(String::from("total_energy"),ConfigurationValue::Number(total_energy)),
+				(String::from("average_power"),ConfigurationValue::Number(total_energy/cycles as f64)),
+				(String::from("energy_per_delivered_bit"),if delivered_bits>0.0 { ConfigurationValue::Number(total_energy/delivered_bits) } else { ConfigurationValue::None }),
+				(String::from("link_dynamic_energy"),ConfigurationValue::Number(link_dynamic_energy)),
+				(String::from("link_static_energy"),ConfigurationValue::Number(link_static_energy)),
+				(String::from("buffer_energy"),ConfigurationValue::Number(buffer_energy)),
+				(String::from("crossbar_energy"),ConfigurationValue::Number(crossbar_energy)),
+				(String::from("router_static_energy"),ConfigurationValue::Number(router_static_energy)),
+				(String::from("link_sleeping_fraction"),if router_links*cycles>0 { ConfigurationValue::Number(sleeping_cycles as f64/(router_links*cycles) as f64) } else { ConfigurationValue::None }),
+				(String::from("link_wake_ups"),ConfigurationValue::Number(wake_ups as f64)),
+			];
+			result_content.push((String::from("energy"),ConfigurationValue::Object(String::from("EnergyStatistics"),energy_content)));
+		}
+		if measurement.ecn_marked_packets>0 || measurement.congestion_throttled_cycles>0
+		{
+			let marked_fraction=measurement.ecn_marked_packets as f64/measurement.consumed_packets as f64;
+			let throttled_fraction=measurement.congestion_throttled_cycles as f64/cycles as f64/num_servers as f64;
+			let congestion_content = vec![
+				(String::from("ecn_marked_packets"),ConfigurationValue::Number(measurement.ecn_marked_packets as f64)),
+				(String::from("ecn_marked_fraction"),ConfigurationValue::Number(marked_fraction)),
+				(String::from("throttled_fraction"),ConfigurationValue::Number(throttled_fraction)),
+			];
+			result_content.push((String::from("congestion_statistics"),ConfigurationValue::Object(String::from("CongestionStatistics"),congestion_content)));
+		}
 		if let Ok(linux_process) = procfs::process::Process::myself()
 		{
 			let status = linux_process.status().expect("failed to get status of the self process");
@@ -1887,6 +2403,32 @@ impl<'a> Simulation<'a>
 			}
 			result_content.push( (String::from("packet_defined_statistics"),ConfigurationValue::Array(pds_content)) );
 		}
+		if !self.statistics.message_size_measurement.is_empty()
+		{
+			let mut minimum_size = 1;
+			let mut buckets_content = vec![];
+			for (index,bucket) in self.statistics.message_size_measurement.iter().enumerate()
+			{
+				let maximum_size = match self.statistics.message_size_buckets.get(index)
+				{
+					Some(&maximum) => ConfigurationValue::Number(maximum as f64),
+					None => ConfigurationValue::None,
+				};
+				let bucket_content = vec![
+					(String::from("minimum_size"),ConfigurationValue::Number(minimum_size as f64)),
+					(String::from("maximum_size"),maximum_size),
+					(String::from("consumed_messages"),ConfigurationValue::Number(bucket.consumed_messages as f64)),
+					(String::from("accepted_load"),ConfigurationValue::Number(bucket.consumed_phits as f64/cycles as f64/num_servers as f64)),
+					(String::from("average_message_delay"),if bucket.consumed_messages==0 { ConfigurationValue::None } else { ConfigurationValue::Number(bucket.total_message_delay as f64/bucket.consumed_messages as f64) }),
+				];
+				buckets_content.push( ConfigurationValue::Object(String::from("MessageSizeBucket"),bucket_content) );
+				if let Some(&maximum) = self.statistics.message_size_buckets.get(index)
+				{
+					minimum_size = maximum+1;
+				}
+			}
+			result_content.push( (String::from("message_size_statistics"),ConfigurationValue::Array(buckets_content)) );
+		}
 		let result=ConfigurationValue::Object(String::from("Result"),result_content);
 		writeln!(output,"{}",result).unwrap();
 	}
@@ -1938,6 +2480,8 @@ pub struct Plugs
 	traffics: BTreeMap<String,fn(TrafficBuilderArgument) -> Box<dyn Traffic> >,
 	patterns: BTreeMap<String, fn(PatternBuilderArgument) -> Box<dyn Pattern> >,
 	policies: BTreeMap<String, fn(VCPolicyBuilderArgument) -> Box<dyn VirtualChannelPolicy> >,
+	switch_allocators: BTreeMap<String, fn(SwitchAllocatorBuilderArgument) -> Box<dyn SwitchAllocator> >,
+	congestion_controls: BTreeMap<String, fn(CongestionControlBuilderArgument) -> Box<dyn CongestionControl> >,
 }
 
 impl Plugs
@@ -1971,6 +2515,14 @@ impl Plugs
 	{
 		self.patterns.insert(key,builder);
 	}
+	pub fn add_switch_allocator(&mut self, key:String, builder: fn(SwitchAllocatorBuilderArgument) -> Box<dyn SwitchAllocator>)
+	{
+		self.switch_allocators.insert(key,builder);
+	}
+	pub fn add_congestion_control(&mut self, key:String, builder: fn(CongestionControlBuilderArgument) -> Box<dyn CongestionControl>)
+	{
+		self.congestion_controls.insert(key,builder);
+	}
 }
 
 impl Debug for Plugs
@@ -1984,6 +2536,8 @@ impl Debug for Plugs
 		write!(f,"{};",self.traffics.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		write!(f,"{};",self.patterns.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		write!(f,"{};",self.policies.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
+		write!(f,"{};",self.switch_allocators.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
+		write!(f,"{};",self.congestion_controls.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		Ok(())
 	}
 }
@@ -1993,15 +2547,25 @@ impl Debug for Plugs
 /// `plugs` constains the plugged builder functions.
 /// `result_file` indicates where to write the results.
 /// `free_args` are free arguments. Those of the form `path=value` are used to override configurations.
+/// The free argument `flow_model` replaces the simulation by the analytic estimation of [flow_model::FlowModel], with `flow_model_samples=value` destinations per server (100 by default).
 pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,free_args:&[String])
 {
 	let mut contents = String::new();
 	file.read_to_string(&mut contents).expect("something went wrong reading the file");
 
 	let mut rewrites: Vec< (Expr,ConfigurationValue) > = vec![];
+	let mut flow_model_samples = None;
 	for arg in free_args
 	{
-		if let Some( (left,right) ) = arg.split_once('=')
+		if arg=="flow_model"
+		{
+			flow_model_samples.get_or_insert(100);
+		}
+		else if let Some(samples) = arg.strip_prefix("flow_model_samples=")
+		{
+			flow_model_samples = Some(samples.parse::<usize>().expect("bad value for flow_model_samples"));
+		}
+		else if let Some( (left,right) ) = arg.split_once('=')
 		{
 			let left_expr = match config_parser::parse_expression(left).expect("error parsing a free argument")
 			{
@@ -2048,6 +2612,17 @@ pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,fre
 						for (i,experiment) in experiments.iter().enumerate()
 						{
 							println!("experiment {} of {} is {:?}",i,experiments.len(),experiment);
+							if let Some(samples) = flow_model_samples
+							{
+								let mut model=flow_model::FlowModel::new(&experiment,plugs,samples);
+								model.run();
+								match results_file
+								{
+									Some(ref mut f) => model.write_result(f),
+									None => model.write_result(&mut stdout()),
+								};
+								continue;
+							}
 							let mut simulation=Simulation::new(&experiment,plugs);
 							simulation.run();
 							match results_file
@@ -2125,9 +2700,112 @@ pub fn get_version_number() -> &'static str
 
 
 #[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn it_works()
+	{
+		assert_eq!(2 + 2, 4);
+	}
+
+	///Parses a configuration written as in the configuration files.
+	pub fn parse_configuration(source:&str) -> ConfigurationValue
+	{
+		match config_parser::parse(source).expect("the configuration could not be parsed")
+		{
+			config_parser::Token::Value(value) => value,
+			token => panic!("Not a value. Got {:?}",token),
+		}
+	}
+
+	///A traffic that generates no messages and accepts every one. Given as `Scripted` in the configuration of the tests, which place their messages in the servers with `send_message`.
+	#[derive(Debug)]
+	pub struct ScriptedTraffic;
+
+	impl Quantifiable for ScriptedTraffic
+	{
+		fn total_memory(&self) -> usize
+		{
+			return size_of::<ScriptedTraffic>();
+		}
+		fn print_memory_breakdown(&self)
+		{
+			unimplemented!();
+		}
+		fn forecast_total_memory(&self) -> usize
+		{
+			unimplemented!();
+		}
+	}
+
+	impl Traffic for ScriptedTraffic
+	{
+		fn generate_message(&mut self, _origin:usize, _cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+		{
+			Err(TrafficError::OriginOutsideTraffic)
+		}
+		fn probability_per_cycle(&self, _server:usize) -> f32
+		{
+			0.0
+		}
+		fn try_consume(&mut self, _server:usize, _message: Rc<Message>, _cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> bool
+		{
+			true
+		}
+		fn is_finished(&self) -> bool
+		{
+			false
+		}
+		fn should_generate(&self, _server:usize, _cycle:usize, _rng: &RefCell<StdRng>) -> bool
+		{
+			false
+		}
+		fn server_state(&self, _server:usize, _cycle:usize) -> ServerTrafficState
+		{
+			ServerTrafficState::UnspecifiedWait
+		}
+	}
+
+	///The plugs of the tests, including the `Scripted` traffic.
+	pub fn test_plugs() -> Plugs
+	{
+		let mut plugs = Plugs::default();
+		plugs.add_traffic(String::from("Scripted"),|_arg|Box::new(ScriptedTraffic));
+		plugs
+	}
+
+	///Builds the simulation of a configuration written as in the configuration files.
+	pub fn new_simulation<'a>(source:&str, plugs:&'a Plugs) -> Simulation<'a>
+	{
+		Simulation::new(&parse_configuration(source),plugs)
+	}
+
+	///Places a new message in the queue of its origin server.
+	pub fn send_message(simulation:&mut Simulation, origin:usize, destination:usize, size:usize)
+	{
+		let message = Rc::new(Message{
+			origin,
+			destination,
+			size,
+			creation_cycle: simulation.cycle,
+		});
+		simulation.network.servers[origin].stored_messages.push_back(message);
+	}
+
+	///Advances the simulation up to the given cycle.
+	pub fn advance_until(simulation:&mut Simulation, cycle:usize)
+	{
+		while simulation.cycle<cycle
+		{
+			simulation.advance();
+		}
+	}
+
+	///The cycle in which the server consumed its last message.
+	pub fn last_consumption(simulation:&Simulation, server:usize) -> usize
+	{
+		simulation.network.servers[server].statistics.cycle_last_consumed_message
+	}
 }
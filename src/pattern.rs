@@ -11,11 +11,14 @@ use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 use std::fs::File;
 use std::io::{BufRead,BufReader};
 use std::ops::DerefMut;
+use std::mem::{size_of};
 
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
 use crate::topology::cartesian::CartesianData;//for CartesianTransform
 use crate::topology::{Topology,Location};
+use crate::routing::{Routing,new_routing,RoutingBuilderArgument};
+use crate::flow_model::split_flow;
 use crate::quantify::Quantifiable;
 use crate::{Plugs,match_object_panic};
 
@@ -34,6 +37,13 @@ pub trait Pattern : Quantifiable + std::fmt::Debug
 	fn initialize(&mut self, source_size:usize, target_size:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>);
 	///Obtain a destination of a source. This will be called repeteadly as the traffic requires destination for its messages.
 	fn get_destination(&self, origin:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)->usize;
+	///The factor by which the traffic should multiply the load offered by `origin`.
+	///Patterns whose sources have different weights, as `TrafficMatrix`, may override it. By default all sources offer the same load.
+	///The meta-patterns `Product`, `Composition`, `Pow`, and `RandomMix` forward it to the patterns seeing the origin. The rest, as `Components`, do not, so their sources always offer the same load.
+	fn load_scale(&self, _origin:usize) -> f32
+	{
+		1.0
+	}
 }
 
 ///The argument to a builder funtion of patterns.
@@ -125,6 +135,17 @@ FileMap{
 }
 ```
 
+### TrafficMatrix
+A weighted matrix of traffic read from file, such as one measured in a production system. Each source selects its destinations proportionally to the weights of its row, in constant time by alias tables. The file has lines `source destination weight` with the nonzero entries, or with `dense` the full row of each source in its own line. Empty lines and lines starting by `#` are ignored. Sources with an empty row send to themselves. With `scale_load` the load of each source in a `HomogeneousTraffic` is scaled by its row total relative to the largest one.
+```
+TrafficMatrix{
+	filename: "/path/to/matrix",
+	dense: false,//the default
+	scale_load: true,//defaults to false
+	legend_name: "traffic matrix of my cluster",
+}
+```
+
 ### CartesianTransform
 Sees the elments as a n-dimensional orthohedra. Then it applies several transformations. When mapping directly servers it may be useful to use as `sides[0]` the number of servers per router.
 ```
@@ -138,6 +159,61 @@ CartesianTransform{
 }
 ```
 
+### Bit permutations
+The classical permutations over the bits of the indices, with `b` bits. `BitComplement` complements every bit, `BitReverse` reverses their order, `PerfectShuffle` rotates them one position to the left, `Butterfly` swaps the most and least significant bits, `Transpose` swaps the lower and upper `b/2` bits, and `BitRotation` rotates them `amount` positions to the left, or to the right if negative.
+The number of elements must be a power of two. Otherwise the optional `offset` and `size` select a power of two range to permute, leaving the rest of the elements to map to themselves.
+```
+BitRotation{
+	amount: -2,
+	offset: 0,//default
+	size: 512,//defaults to all the elements
+	legend_name: "rotate the bits of the first 512 servers two positions to the right",
+}
+```
+
+### Stencil
+The servers hold the processes of a logical grid of `sides`, each sending to a random neighbour of its stencil of `points`. Stencils of `2n+1` points in `n` dimensions have the neighbours along each dimension, as the 5-point in 2D or the 7-point in 3D, and stencils of `3^n` points also have the diagonal ones, as the 9-point in 2D or the 27-point in 3D. With `periodic` the grid wraps around; otherwise the processes at the boundaries have fewer neighbours.
+The processes are placed into the servers by the `mapping`: `Linear` (the default), `Blocked{block}`, with each block of the grid in consecutive servers, or `Hilbert`, along a Hilbert curve. Coordinates are packed with the first one varying fastest. Servers without a process send to themselves. See the `HaloExchange` traffic to send to all the neighbours.
+```
+Stencil{
+	sides: [8,8],
+	points: 9,//defaults to 2n+1
+	periodic: false,//defaults to true
+	mapping: Blocked{block:[2,2]},
+	legend_name: "9-point stencil on a 8x8 grid of 2x2 blocks",
+}
+```
+
+### Tornado
+Each coordinate `x` of side `k` goes to `x+ceil(k/2)-1`, sending almost halfway around each ring in the same direction. Without `sides` it is applied to the routers of a topology with `CartesianData`, such as a `Mesh`, `Torus` or `Hamming`, and each server sends to the server in its same position at the destination router. With `sides` it is applied directly to the elements.
+```
+Tornado{
+	//sides: [8,8],//defaults to the routers of the topology
+	legend_name: "tornado",
+}
+```
+
+### GroupShift
+The groups of the topology are the components induced by the `component_classes`, by default `[0]`, the local links of a dragonfly. Each server sends to a random server of the group `shift` places after its own, by default the next one. This is the usual adversarial traffic of the dragonfly. It uses the topology, so it should not be used as a sub-pattern.
+```
+GroupShift{
+	shift: 1,
+	component_classes: [0],
+	legend_name: "adversarial to the next group",
+}
+```
+
+### WorstCasePermutation
+The permutation of routers that puts the most load on a single link for the given `routing`. The flows are split among the routing candidates as in the flow model, and for each link a maximum weight matching gives the permutation that loads it the most. Each server sends to the server in its same position at the destination router. It takes `O(L n^3)` time for `L` links and `n` routers. Routings with random decisions are averaged over `samples` routes of each flow.
+```
+WorstCasePermutation{
+	routing: DOR{order:[0,1]},
+	virtual_channels: 2,//offered to the routing, defaults to 1
+	samples: 10,//the default
+	legend_name: "worst permutation for DOR",
+}
+```
+
 ### Hotspots.
 A pool of hotspots is build from a given list of `destinations` plus some amount `extra_random_destinations` computed randomly on initialization.
 Destinations are randomly selected from such pool.
@@ -238,6 +314,12 @@ pub fn new_pattern(arg:PatternBuilderArgument) -> Box<dyn Pattern>
 			"GroupShufflingDestinations" => Box::new(GroupShufflingDestinations::new(arg)),
 			"UniformDistance" => Box::new(UniformDistance::new(arg)),
 			"FixedRandom" => Box::new(FixedRandom::new(arg)),
+			"Stencil" => Box::new(Stencil::new(arg)),
+			"BitComplement" | "BitReverse" | "PerfectShuffle" | "Butterfly" | "Transpose" | "BitRotation" => Box::new(BitPermutation::new(arg)),
+			"GroupShift" => Box::new(GroupShift::new(arg)),
+			"Tornado" => Box::new(Tornado::new(arg)),
+			"WorstCasePermutation" => Box::new(WorstCasePermutation::new(arg)),
+			"TrafficMatrix" => Box::new(TrafficMatrix::new(arg)),
 			_ => panic!("Unknown pattern {}",cv_name),
 		}
 	}
@@ -718,6 +800,10 @@ impl Pattern for ProductPattern
 		let global_dest=self.global_pattern.get_destination(global,topology,rng);
 		global_dest*self.block_size+local_dest
 	}
+	fn load_scale(&self, origin:usize) -> f32
+	{
+		self.block_pattern.load_scale(origin % self.block_size) * self.global_pattern.load_scale(origin / self.block_size)
+	}
 }
 
 impl ProductPattern
@@ -1125,6 +1211,11 @@ impl Pattern for Composition
 		}
 		destination
 	}
+	fn load_scale(&self, origin:usize) -> f32
+	{
+		//Only the first pattern sees the origin.
+		self.patterns.first().map(|pattern|pattern.load_scale(origin)).unwrap_or(1.0)
+	}
 }
 
 impl Composition
@@ -1190,6 +1281,10 @@ impl Pattern for Pow
 		}
 		destination
 	}
+	fn load_scale(&self, origin:usize) -> f32
+	{
+		self.pattern.load_scale(origin)
+	}
 }
 
 impl Pow
@@ -1450,6 +1545,12 @@ impl Pattern for RandomMix
 		}
 		self.patterns[index].get_destination(origin,topology,rng)
 	}
+	fn load_scale(&self, origin:usize) -> f32
+	{
+		//The expected scale over the selection of the pattern.
+		let total:f32 = self.patterns.iter().zip(self.weights.iter()).map(|(pattern,&weight)|pattern.load_scale(origin)*weight as f32).sum();
+		total / self.total_weight as f32
+	}
 }
 
 impl RandomMix
@@ -1815,3 +1916,1270 @@ impl FixedRandom
 	}
 }
 
+
+///How the processes of a logical grid are placed on the servers. Process coordinates are packed with the first coordinate varying fastest, as in `CartesianData`.
+#[derive(Debug,Clone)]
+pub enum GridMapping
+{
+	///The process with packed index `p` goes to the server `p`.
+	Linear,
+	///The grid is divided into blocks of sides `block`, each block placed in consecutive servers. For example, blocks of as many processes as servers per router keep a subgrid inside each router.
+	Blocked{block:Vec<usize>},
+	///The processes are placed along a Hilbert curve through the grid, which keeps near processes in near servers. It requires all the sides to be the same power of two.
+	Hilbert,
+}
+
+impl Quantifiable for GridMapping
+{
+	fn total_memory(&self) -> usize
+	{
+		match self
+		{
+			GridMapping::Blocked{block} => size_of::<GridMapping>() + block.len()*size_of::<usize>(),
+			_ => size_of::<GridMapping>(),
+		}
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl GridMapping
+{
+	pub fn new(cv:&ConfigurationValue) -> GridMapping
+	{
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			let mut block=None;
+			for &(ref name,ref value) in cv_pairs
+			{
+				match (cv_name.as_ref(),name.as_ref())
+				{
+					("Blocked","block") => match value
+					{
+						&ConfigurationValue::Array(ref a) => block=Some(a.iter().map(|v|match v{
+							&ConfigurationValue::Number(f) => f as usize,
+							_ => panic!("bad value in block"),
+						}).collect()),
+						_ => panic!("bad value for block"),
+					}
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			match cv_name.as_ref()
+			{
+				"Linear" => GridMapping::Linear,
+				"Blocked" => GridMapping::Blocked{block:block.expect("There were no block")},
+				"Hilbert" => GridMapping::Hilbert,
+				_ => panic!("Unknown grid mapping {}",cv_name),
+			}
+		}
+		else
+		{
+			panic!("Trying to create a GridMapping from a non-Object");
+		}
+	}
+	///The server of each process of the grid.
+	pub fn place(&self, grid:&CartesianData) -> Vec<usize>
+	{
+		match self
+		{
+			GridMapping::Linear => (0..grid.size).collect(),
+			GridMapping::Blocked{block} =>
+			{
+				if block.len()!=grid.sides.len() || block.iter().zip(grid.sides.iter()).any(|(&b,&side)|b==0 || side%b!=0)
+				{
+					panic!("The block {:?} does not divide the grid {:?}.",block,grid.sides);
+				}
+				let inner = CartesianData::new(block);
+				let outer = CartesianData::new(&grid.sides.iter().zip(block.iter()).map(|(side,b)|side/b).collect());
+				(0..grid.size).map(|process|{
+					let coordinates = grid.unpack(process);
+					let block_coordinates = coordinates.iter().zip(block.iter()).map(|(c,b)|c/b).collect();
+					let inner_coordinates = coordinates.iter().zip(block.iter()).map(|(c,b)|c%b).collect();
+					outer.pack(&block_coordinates)*inner.size + inner.pack(&inner_coordinates)
+				}).collect()
+			},
+			GridMapping::Hilbert =>
+			{
+				let side = grid.sides[0];
+				if !side.is_power_of_two() || grid.sides.iter().any(|&s|s!=side)
+				{
+					panic!("The Hilbert mapping requires all the sides to be the same power of two, but they are {:?}.",grid.sides);
+				}
+				let bits = side.trailing_zeros() as usize;
+				(0..grid.size).map(|process|hilbert_index(&grid.unpack(process),bits)).collect()
+			},
+		}
+	}
+}
+
+///The position along the Hilbert curve of the point with the given coordinates, each of `bits` bits.
+///Uses the transposition of Skilling, "Programming the Hilbert curve" (2004), valid for any dimension.
+fn hilbert_index(coordinates:&[usize], bits:usize) -> usize
+{
+	if bits==0
+	{
+		return 0;
+	}
+	let n = coordinates.len();
+	let mut x = coordinates.to_vec();
+	let m = 1usize << (bits-1);
+	//Inverse undo of the excess work.
+	let mut q = m;
+	while q>1
+	{
+		let p = q-1;
+		for i in 0..n
+		{
+			if x[i] & q != 0
+			{
+				x[0] ^= p;
+			}
+			else
+			{
+				let t = (x[0]^x[i]) & p;
+				x[0] ^= t;
+				x[i] ^= t;
+			}
+		}
+		q >>= 1;
+	}
+	//Gray encode.
+	for i in 1..n
+	{
+		x[i] ^= x[i-1];
+	}
+	let mut t = 0;
+	q = m;
+	while q>1
+	{
+		if x[n-1] & q != 0
+		{
+			t ^= q-1;
+		}
+		q >>= 1;
+	}
+	for value in x.iter_mut()
+	{
+		*value ^= t;
+	}
+	//Interleave the transposed bits, the most significant first.
+	let mut index = 0;
+	for bit in (0..bits).rev()
+	{
+		for value in x.iter()
+		{
+			index = (index<<1) | ((value>>bit)&1);
+		}
+	}
+	index
+}
+
+///A logical grid of processes, each communicating with the neighbours given by a stencil, and placed into the servers by a `GridMapping`.
+///A stencil of `2n+1` points has the neighbours along each of the `n` dimensions, as the 5-point stencil in 2D or the 7-point in 3D. A stencil of `3^n` points also has the diagonal neighbours, as the 9-point in 2D or the 27-point in 3D.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct StencilGrid
+{
+	///The sides of the grid of processes.
+	grid: CartesianData,
+	///Whether the grid wraps around its boundaries.
+	periodic: bool,
+	///The displacements to the neighbours.
+	offsets: Vec<Vec<i32>>,
+	///The server of each process.
+	process_to_server: Vec<usize>,
+	///The process of each server, if any.
+	server_to_process: Vec<Option<usize>>,
+}
+
+impl StencilGrid
+{
+	pub fn new(sides:&Vec<usize>, points:usize, periodic:bool, mapping:&GridMapping, servers:usize) -> StencilGrid
+	{
+		let grid = CartesianData::new(sides);
+		let n = sides.len();
+		let all_offsets = CartesianData::new(&vec![3;n]);
+		let offsets:Vec<Vec<i32>> = (0..all_offsets.size).map(|index|all_offsets.unpack(index).into_iter().map(|c|c as i32-1).collect::<Vec<i32>>()).filter(|offset|{
+			let nonzero = offset.iter().filter(|&&c|c!=0).count();
+			if points==2*n+1 { nonzero==1 } else if points==all_offsets.size { nonzero>0 } else { panic!("A stencil in {} dimensions has either {} or {} points, not {}.",n,2*n+1,all_offsets.size,points) }
+		}).collect();
+		let process_to_server = mapping.place(&grid);
+		let mut server_to_process = vec![None;servers];
+		for (process,&server) in process_to_server.iter().enumerate()
+		{
+			if server>=servers
+			{
+				panic!("The grid of {} processes does not fit into {} servers.",grid.size,servers);
+			}
+			if server_to_process[server].is_some()
+			{
+				panic!("The mapping places several processes in the server {}.",server);
+			}
+			server_to_process[server] = Some(process);
+		}
+		StencilGrid{
+			grid,
+			periodic,
+			offsets,
+			process_to_server,
+			server_to_process,
+		}
+	}
+	///The number of processes of the grid.
+	pub fn processes(&self) -> usize
+	{
+		self.grid.size
+	}
+	///The server in which the process has been placed.
+	pub fn server(&self, process:usize) -> usize
+	{
+		self.process_to_server[process]
+	}
+	///The process placed in the server, if any.
+	pub fn process(&self, server:usize) -> Option<usize>
+	{
+		self.server_to_process[server]
+	}
+	///The neighbours of a process, one for each point of the stencil out of itself. Without periodicity those outside the grid are missing.
+	///A neighbour may appear several times in small periodic grids.
+	pub fn neighbours(&self, process:usize) -> Vec<usize>
+	{
+		let coordinates = self.grid.unpack(process);
+		self.offsets.iter().filter_map(|offset|{
+			let mut neighbour = Vec::with_capacity(coordinates.len());
+			for ((&c,&o),&side) in coordinates.iter().zip(offset.iter()).zip(self.grid.sides.iter())
+			{
+				let x = c as i32 + o;
+				if x<0 || x>=side as i32
+				{
+					if !self.periodic
+					{
+						return None;
+					}
+				}
+				neighbour.push(x.rem_euclid(side as i32) as usize);
+			}
+			let neighbour = self.grid.pack(&neighbour);
+			if neighbour==process { None } else { Some(neighbour) }
+		}).collect()
+	}
+}
+
+///Each process of a logical grid sends to a random neighbour of its stencil. See `StencilGrid`.
+///The elements are servers, with those not holding a process sending to themselves, which generates no message.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Stencil
+{
+	sides: Vec<usize>,
+	points: usize,
+	periodic: bool,
+	mapping: GridMapping,
+	///The grid, built on initialization.
+	grid: Option<StencilGrid>,
+}
+
+impl Pattern for Stencil
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+	{
+		if source_size!=target_size
+		{
+			panic!("Stencil requires source and target sets to have same size.");
+		}
+		self.grid = Some(StencilGrid::new(&self.sides,self.points,self.periodic,&self.mapping,target_size));
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)->usize
+	{
+		let grid = self.grid.as_ref().expect("Stencil has not been initialized");
+		let process = match grid.process(origin)
+		{
+			Some(process) => process,
+			None => return origin,
+		};
+		let neighbours = grid.neighbours(process);
+		if neighbours.is_empty()
+		{
+			return origin;
+		}
+		let index = rng.borrow_mut().gen_range(0..neighbours.len());
+		grid.server(neighbours[index])
+	}
+}
+
+impl Stencil
+{
+	fn new(arg:PatternBuilderArgument) -> Stencil
+	{
+		let mut sides=None;
+		let mut points=None;
+		let mut periodic=true;
+		let mut mapping=GridMapping::Linear;
+		match_object_panic!(arg.cv,"Stencil",value,
+			"sides" => match value
+			{
+				&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
+					&ConfigurationValue::Number(f) => f as usize,
+					_ => panic!("bad value in sides"),
+				}).collect()),
+				_ => panic!("bad value for sides"),
+			},
+			"points" => match value
+			{
+				&ConfigurationValue::Number(f) => points=Some(f as usize),
+				_ => panic!("bad value for points"),
+			},
+			"periodic" => match value
+			{
+				&ConfigurationValue::True => periodic=true,
+				&ConfigurationValue::False => periodic=false,
+				_ => panic!("bad value for periodic"),
+			},
+			"mapping" => mapping=GridMapping::new(value),
+		);
+		let sides:Vec<usize>=sides.expect("There were no sides");
+		let points=points.unwrap_or(2*sides.len()+1);
+		Stencil{
+			sides,
+			points,
+			periodic,
+			mapping,
+			grid: None,
+		}
+	}
+}
+
+///The bit-level permutations of the indices, as in the interconnection networks literature. See `BitPermutation`.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum BitPermutationKind
+{
+	///Complements every bit.
+	Complement,
+	///Reverses the order of the bits.
+	Reverse,
+	///Rotates the bits one position to the left.
+	PerfectShuffle,
+	///Swaps the most and the least significant bits.
+	Butterfly,
+	///Swaps the lower and the upper halves of the bits. Requires an even number of bits.
+	Transpose,
+	///Rotates the bits the given amount of positions to the left, or to the right if negative.
+	Rotation(i32),
+}
+
+impl Quantifiable for BitPermutationKind
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<BitPermutationKind>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl BitPermutationKind
+{
+	///Applies the permutation to the `bits` lower bits of `x`.
+	pub fn apply(&self, x:usize, bits:usize) -> usize
+	{
+		if bits==0
+		{
+			return x;
+		}
+		let mask = if bits==usize::BITS as usize { usize::MAX } else { (1<<bits)-1 };
+		let rotate_left = |x:usize,amount:usize| { let amount = amount%bits; if amount==0 { x } else { ((x<<amount)|(x>>(bits-amount)))&mask } };
+		match self
+		{
+			BitPermutationKind::Complement => !x & mask,
+			BitPermutationKind::Reverse => x.reverse_bits()>>(usize::BITS as usize-bits),
+			BitPermutationKind::PerfectShuffle => rotate_left(x,1),
+			BitPermutationKind::Butterfly =>
+			{
+				let high = (x>>(bits-1))&1;
+				let low = x&1;
+				(x & !(1<<(bits-1)) & !1) | (low<<(bits-1)) | high
+			},
+			BitPermutationKind::Transpose => rotate_left(x,bits/2),
+			&BitPermutationKind::Rotation(amount) => rotate_left(x,amount.rem_euclid(bits as i32) as usize),
+		}
+	}
+}
+
+///A bit-level permutation of the indices of the elements in the range `[offset,offset+size)`, with `size` a power of two. The elements out of the range go to themselves.
+///By default the range is the whole set, whose size must then be a power of two.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct BitPermutation
+{
+	kind: BitPermutationKind,
+	///The first element permuted.
+	offset: usize,
+	///The number of elements permuted, if given. Otherwise all of them.
+	size: Option<usize>,
+	///The bits of the indices in the range, computed on initialization.
+	bits: usize,
+}
+
+impl Pattern for BitPermutation
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+	{
+		if source_size!=target_size
+		{
+			panic!("BitPermutation requires source and target sets to have same size.");
+		}
+		let size = self.size.unwrap_or(source_size-self.offset.min(source_size));
+		if !size.is_power_of_two()
+		{
+			panic!("The bit permutations require a power of two number of elements, but {} were given. Use the `size` field to permute a sub-range.",size);
+		}
+		if self.offset+size>source_size
+		{
+			panic!("The range [{},{}) of the bit permutation exceeds the {} elements.",self.offset,self.offset+size,source_size);
+		}
+		self.size = Some(size);
+		self.bits = size.trailing_zeros() as usize;
+		if self.kind==BitPermutationKind::Transpose && self.bits%2!=0
+		{
+			panic!("The Transpose permutation requires an even number of bits, but there are {}.",self.bits);
+		}
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)->usize
+	{
+		let size = self.size.expect("BitPermutation has not been initialized");
+		if origin<self.offset || origin>=self.offset+size
+		{
+			return origin;
+		}
+		self.offset + self.kind.apply(origin-self.offset,self.bits)
+	}
+}
+
+impl BitPermutation
+{
+	fn new(arg:PatternBuilderArgument) -> BitPermutation
+	{
+		let mut offset=0;
+		let mut size=None;
+		let mut amount=None;
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
+		{
+			for &(ref name,ref value) in cv_pairs
+			{
+				match (cv_name.as_ref(),name.as_ref())
+				{
+					(_,"offset") => match value
+					{
+						&ConfigurationValue::Number(f) => offset=f as usize,
+						_ => panic!("bad value for offset"),
+					}
+					(_,"size") => match value
+					{
+						&ConfigurationValue::Number(f) => size=Some(f as usize),
+						_ => panic!("bad value for size"),
+					}
+					("BitRotation","amount") => match value
+					{
+						&ConfigurationValue::Number(f) => amount=Some(f as i32),
+						_ => panic!("bad value for amount"),
+					}
+					(_,"legend_name") => (),
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			let kind = match cv_name.as_ref()
+			{
+				"BitComplement" => BitPermutationKind::Complement,
+				"BitReverse" => BitPermutationKind::Reverse,
+				"PerfectShuffle" => BitPermutationKind::PerfectShuffle,
+				"Butterfly" => BitPermutationKind::Butterfly,
+				"Transpose" => BitPermutationKind::Transpose,
+				"BitRotation" => BitPermutationKind::Rotation(amount.expect("There were no amount")),
+				_ => panic!("Unknown bit permutation {}",cv_name),
+			};
+			BitPermutation{
+				kind,
+				offset,
+				size,
+				bits: 0,
+			}
+		}
+		else
+		{
+			panic!("Trying to create a BitPermutation from a non-Object");
+		}
+	}
+}
+
+///The servers attached to each router, in the order of the router ports.
+fn servers_by_router(topology:&dyn Topology) -> Vec<Vec<usize>>
+{
+	(0..topology.num_routers()).map(|router|{
+		(0..topology.ports(router)).filter_map(|port|match topology.neighbour(router,port).0
+		{
+			Location::ServerPort(server) => Some(server),
+			_ => None,
+		}).collect()
+	}).collect()
+}
+
+///Builds a permutation of the servers from a map between routers. Each server goes to the server in the same position of the image of its router, wrapping around if the image has fewer servers.
+fn server_permutation_from_routers(topology:&dyn Topology, router_image:&[usize]) -> Vec<usize>
+{
+	let router_servers = servers_by_router(topology);
+	let mut destinations = vec![0;topology.num_servers()];
+	for (router,servers) in router_servers.iter().enumerate()
+	{
+		if servers.is_empty()
+		{
+			continue;
+		}
+		let target = &router_servers[router_image[router]];
+		if target.is_empty()
+		{
+			panic!("The router {} has servers but its image {} has none",router,router_image[router]);
+		}
+		for (index,&server) in servers.iter().enumerate()
+		{
+			destinations[server] = target[index % target.len()];
+		}
+	}
+	destinations
+}
+
+///The groups of the topology, taken as the components induced by some link classes, are shifted by `shift`. Each server sends to a random server of the group `shift` places after its own.
+///With the default local links of the dragonfly this is the usual adversarial traffic, that puts all the load of a group into a few global links.
+///Note that this uses the topology and will cause problems if used as a sub-pattern.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct GroupShift
+{
+	///The number of groups to advance. It can be negative.
+	shift: i32,
+	///The link classes inside each group.
+	component_classes: Vec<usize>,
+	///The servers in each group, computed on initialization.
+	group_servers: Vec<Vec<usize>>,
+	///The group of each server, computed on initialization.
+	server_group: Vec<usize>,
+}
+
+impl Pattern for GroupShift
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+	{
+		if source_size!=topology.num_servers() || target_size!=topology.num_servers()
+		{
+			panic!("GroupShift requires source and target sets to be the servers.");
+		}
+		let mut allowed_classes=vec![];
+		for &link_class in self.component_classes.iter()
+		{
+			if link_class>=allowed_classes.len()
+			{
+				allowed_classes.resize(link_class+1,false);
+			}
+			allowed_classes[link_class]=true;
+		}
+		let router_servers = servers_by_router(topology.as_ref());
+		let components = topology.components(&allowed_classes);
+		if components.len()<2
+		{
+			panic!("GroupShift requires several groups, but the link classes {:?} give {}.",self.component_classes,components.len());
+		}
+		self.server_group = vec![0;topology.num_servers()];
+		self.group_servers = components.iter().enumerate().map(|(group,routers)|{
+			let mut servers = vec![];
+			for &router in routers.iter()
+			{
+				for &server in router_servers[router].iter()
+				{
+					self.server_group[server] = group;
+					servers.push(server);
+				}
+			}
+			servers
+		}).collect();
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)->usize
+	{
+		let groups = self.group_servers.len() as i32;
+		let group = (self.server_group[origin] as i32 + self.shift).rem_euclid(groups) as usize;
+		let servers = &self.group_servers[group];
+		if servers.is_empty()
+		{
+			panic!("The group {} has no servers",group);
+		}
+		let r = rng.borrow_mut().gen_range(0..servers.len());
+		servers[r]
+	}
+}
+
+impl GroupShift
+{
+	fn new(arg:PatternBuilderArgument) -> GroupShift
+	{
+		let mut shift=1;
+		let mut component_classes=vec![0];
+		match_object_panic!(arg.cv,"GroupShift",value,
+			"shift" => match value
+			{
+				&ConfigurationValue::Number(f) => shift=f as i32,
+				_ => panic!("bad value for shift"),
+			},
+			"component_classes" => match value
+			{
+				&ConfigurationValue::Array(ref a) => component_classes=a.iter().map(|v|match v{
+					&ConfigurationValue::Number(f) => f as usize,
+					_ => panic!("bad value in component_classes"),
+				}).collect(),
+				_ => panic!("bad value for component_classes"),
+			},
+		);
+		GroupShift{
+			shift,
+			component_classes,
+			group_servers: vec![],
+			server_group: vec![],
+		}
+	}
+}
+
+///The tornado pattern generalized to any number of dimensions. Each coordinate `x` of side `k` goes to `x+ceil(k/2)-1`, so that the traffic of a ring goes almost halfway around it, always in the same direction.
+///If `sides` are not given it uses the routers of the topology, that must have `CartesianData`, and the servers keep their position inside the routers.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Tornado
+{
+	///The sides of the grid of elements, if given. Otherwise the grid of routers of the topology is used.
+	sides: Option<Vec<usize>>,
+	///The destination of each element, computed on initialization.
+	destinations: Vec<usize>,
+}
+
+impl Pattern for Tornado
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+	{
+		if source_size!=target_size
+		{
+			panic!("Tornado requires source and target sets to have same size.");
+		}
+		let tornado = |cartesian_data:&CartesianData, index:usize| -> usize {
+			let coordinates = cartesian_data.unpack(index).iter().zip(cartesian_data.sides.iter()).map(|(&x,&side)|(x+(side+1)/2+side-1)%side).collect();
+			cartesian_data.pack(&coordinates)
+		};
+		self.destinations = match self.sides
+		{
+			Some(ref sides) =>
+			{
+				let cartesian_data = CartesianData::new(sides);
+				if cartesian_data.size!=source_size
+				{
+					panic!("The sides {:?} of the Tornado give {} elements, but there are {}.",sides,cartesian_data.size,source_size);
+				}
+				(0..source_size).map(|index|tornado(&cartesian_data,index)).collect()
+			},
+			None =>
+			{
+				if source_size!=topology.num_servers()
+				{
+					panic!("A Tornado without sides requires source and target sets to be the servers.");
+				}
+				let cartesian_data = topology.cartesian_data().expect("The topology has no CartesianData for the Tornado. Give explicit sides.");
+				let router_image : Vec<usize> = (0..topology.num_routers()).map(|router|tornado(cartesian_data,router)).collect();
+				server_permutation_from_routers(topology.as_ref(),&router_image)
+			},
+		};
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)->usize
+	{
+		self.destinations[origin]
+	}
+}
+
+impl Tornado
+{
+	fn new(arg:PatternBuilderArgument) -> Tornado
+	{
+		let mut sides=None;
+		match_object_panic!(arg.cv,"Tornado",value,
+			"sides" => match value
+			{
+				&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
+					&ConfigurationValue::Number(f) => f as usize,
+					_ => panic!("bad value in sides"),
+				}).collect()),
+				_ => panic!("bad value for sides"),
+			},
+		);
+		Tornado{
+			sides,
+			destinations: vec![],
+		}
+	}
+}
+
+///Solves the assignment problem maximizing the total weight, by the Hungarian algorithm in `O(n^3)`.
+///Returns the column assigned to each row of the square matrix `weights`.
+fn maximum_weight_assignment(weights:&[Vec<f64>]) -> Vec<usize>
+{
+	let n = weights.len();
+	//Potentials and matching with 1-based indices, the 0 column being a sentinel.
+	let mut u = vec![0f64;n+1];
+	let mut v = vec![0f64;n+1];
+	let mut row_of_column = vec![0usize;n+1];
+	let mut way = vec![0usize;n+1];
+	for row in 1..=n
+	{
+		row_of_column[0] = row;
+		let mut column = 0;
+		let mut minimum = vec![f64::INFINITY;n+1];
+		let mut used = vec![false;n+1];
+		loop
+		{
+			used[column] = true;
+			let current_row = row_of_column[column];
+			let mut delta = f64::INFINITY;
+			let mut next_column = 0;
+			for j in 1..=n
+			{
+				if !used[j]
+				{
+					let reduced = -weights[current_row-1][j-1] - u[current_row] - v[j];
+					if reduced < minimum[j]
+					{
+						minimum[j] = reduced;
+						way[j] = column;
+					}
+					if minimum[j] < delta
+					{
+						delta = minimum[j];
+						next_column = j;
+					}
+				}
+			}
+			for j in 0..=n
+			{
+				if used[j]
+				{
+					u[row_of_column[j]] += delta;
+					v[j] -= delta;
+				}
+				else
+				{
+					minimum[j] -= delta;
+				}
+			}
+			column = next_column;
+			if row_of_column[column]==0
+			{
+				break;
+			}
+		}
+		loop
+		{
+			let previous = way[column];
+			row_of_column[column] = row_of_column[previous];
+			column = previous;
+			if column==0
+			{
+				break;
+			}
+		}
+	}
+	let mut assignment = vec![0;n];
+	for j in 1..=n
+	{
+		assignment[row_of_column[j]-1] = j-1;
+	}
+	assignment
+}
+
+///The permutation of routers that puts the most load into a single link for a given `routing`.
+///The load that a flow between each pair of routers puts on each link is computed as in the flow model, splitting it evenly among the candidates. Then, for each link, the permutation maximizing its load is found as a maximum weight matching, and the worst of them is kept.
+///Each server sends to the server in its same position at the image of its router.
+///This takes `O(L n^3)` time for `L` links and `n` routers, so it is only practical for moderate sizes.
+///Routings with random decisions, as the DOR choosing a direction in rings of even side, are evaluated by averaging `samples` routes of each flow.
+#[derive(Debug)]
+pub struct WorstCasePermutation
+{
+	///The routing to be attacked. It is only used during initialization.
+	routing: Option<Box<dyn Routing>>,
+	///The virtual channels offered to the routing when asking for candidates.
+	virtual_channels: usize,
+	///The number of times each flow is routed, averaging the loads, for routings that take random decisions.
+	samples: usize,
+	///The destination of each server, computed on initialization.
+	destinations: Vec<usize>,
+}
+
+impl Quantifiable for WorstCasePermutation
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<WorstCasePermutation>() + self.destinations.total_memory();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Pattern for WorstCasePermutation
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)
+	{
+		if source_size!=topology.num_servers() || target_size!=topology.num_servers()
+		{
+			panic!("WorstCasePermutation requires source and target sets to be the servers.");
+		}
+		let mut routing = self.routing.take().expect("WorstCasePermutation has already been initialized");
+		routing.initialize(topology,rng);
+		let router_servers = servers_by_router(topology.as_ref());
+		//Only the routers with servers take part in the permutation.
+		let server_routers : Vec<usize> = (0..topology.num_routers()).filter(|&router|!router_servers[router].is_empty()).collect();
+		let n = server_routers.len();
+		//Index the links between routers.
+		let mut link_index : Vec<Vec<Option<usize>>> = vec![];
+		let mut links = 0;
+		for router in 0..topology.num_routers()
+		{
+			link_index.push((0..topology.ports(router)).map(|port|match topology.neighbour(router,port).0
+			{
+				Location::RouterPort{..} => { links+=1; Some(links-1) },
+				_ => None,
+			}).collect());
+		}
+		//The load on each link of a flow between each pair of routers with servers, using the first server of each router as representative.
+		let mut link_load = vec![vec![vec![0f64;n];n];links];
+		for origin in 0..n
+		{
+			let origin_server = router_servers[server_routers[origin]][0];
+			for destination in 0..n
+			{
+				if destination==origin
+				{
+					continue;
+				}
+				let destination_server = router_servers[server_routers[destination]][0];
+				for _ in 0..self.samples
+				{
+					split_flow(routing.as_ref(),topology.as_ref(),origin_server,destination_server,1.0/self.samples as f64,self.virtual_channels,rng,&mut |router,port,weight|{
+						if let Some(link) = link_index[router][port]
+						{
+							link_load[link][origin][destination] += weight;
+						}
+					});
+				}
+			}
+		}
+		let mut best : Option<(f64,Vec<usize>)> = None;
+		for loads in link_load.iter()
+		{
+			let assignment = maximum_weight_assignment(loads);
+			let total : f64 = assignment.iter().enumerate().map(|(origin,&destination)|loads[origin][destination]).sum();
+			if best.as_ref().map(|(best_total,_)|total>*best_total).unwrap_or(true)
+			{
+				best = Some((total,assignment));
+			}
+		}
+		let (_total,assignment) = best.expect("There are no links between routers");
+		let mut router_image : Vec<usize> = (0..topology.num_routers()).collect();
+		for (origin,&destination) in assignment.iter().enumerate()
+		{
+			router_image[server_routers[origin]] = server_routers[destination];
+		}
+		self.destinations = server_permutation_from_routers(topology.as_ref(),&router_image);
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)->usize
+	{
+		self.destinations[origin]
+	}
+}
+
+impl WorstCasePermutation
+{
+	fn new(arg:PatternBuilderArgument) -> WorstCasePermutation
+	{
+		let mut routing=None;
+		let mut virtual_channels=1;
+		let mut samples=10;
+		match_object_panic!(arg.cv,"WorstCasePermutation",value,
+			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs:arg.plugs})),
+			"virtual_channels" => match value
+			{
+				&ConfigurationValue::Number(f) => virtual_channels=f as usize,
+				_ => panic!("bad value for virtual_channels"),
+			},
+			"samples" => match value
+			{
+				&ConfigurationValue::Number(f) => samples=f as usize,
+				_ => panic!("bad value for samples"),
+			},
+		);
+		if samples==0
+		{
+			panic!("WorstCasePermutation requires at least one sample.");
+		}
+		WorstCasePermutation{
+			routing: Some(routing.expect("There were no routing")),
+			virtual_channels,
+			samples,
+			destinations: vec![],
+		}
+	}
+}
+
+///Walker's alias table, to sample from a discrete distribution in constant time.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+struct AliasTable
+{
+	///The values to be sampled.
+	outcomes: Vec<usize>,
+	///The probability of keeping each column instead of taking its alias.
+	probability: Vec<f64>,
+	///The alternative column of each column.
+	alias: Vec<usize>,
+}
+
+impl AliasTable
+{
+	///Builds the table by Vose's method from the pairs `(outcome,weight)`, with positive weights.
+	fn new(entries:&[(usize,f64)]) -> AliasTable
+	{
+		let n = entries.len();
+		let total : f64 = entries.iter().map(|&(_,weight)|weight).sum();
+		let mut scaled : Vec<f64> = entries.iter().map(|&(_,weight)|weight*n as f64/total).collect();
+		let mut probability = vec![1.0;n];
+		let mut alias : Vec<usize> = (0..n).collect();
+		let (mut small, mut large) : (Vec<usize>,Vec<usize>) = (0..n).partition(|&index|scaled[index]<1.0);
+		while let (Some(&less),Some(&more)) = (small.last(),large.last())
+		{
+			small.pop();
+			probability[less] = scaled[less];
+			alias[less] = more;
+			scaled[more] += scaled[less] - 1.0;
+			if scaled[more]<1.0
+			{
+				large.pop();
+				small.push(more);
+			}
+		}
+		//The remaining columns are full, up to rounding errors.
+		AliasTable{
+			outcomes: entries.iter().map(|&(outcome,_)|outcome).collect(),
+			probability,
+			alias,
+		}
+	}
+	fn sample(&self, rng: &RefCell<StdRng>) -> usize
+	{
+		let mut rng = rng.borrow_mut();
+		let column = rng.gen_range(0..self.outcomes.len());
+		if rng.gen_range(0f64..1f64) < self.probability[column]
+		{
+			self.outcomes[column]
+		}
+		else
+		{
+			self.outcomes[self.alias[column]]
+		}
+	}
+}
+
+///Reads a weighted matrix of traffic between sources and destinations from a file and selects the destinations of each source proportionally to its row.
+///The file has lines `source destination weight`, listing only the nonzero entries, or with `dense` a line with the whole row of each source. Empty lines and those starting by `#` are ignored.
+///Sources with an empty row send to themselves. With `scale_load` each source also scales its load by its row total relative to the largest one.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct TrafficMatrix
+{
+	///The entries `(source,destination,weight)` read from the file.
+	entries: Vec<(usize,usize,f64)>,
+	///Whether to give the sources a load proportional to their row.
+	scale_load: bool,
+	///The table to sample the destinations of each source, computed on initialization.
+	tables: Vec<Option<AliasTable>>,
+	///The load scale of each source, computed on initialization.
+	load_scales: Vec<f32>,
+}
+
+impl Pattern for TrafficMatrix
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+	{
+		let mut rows : Vec<Vec<(usize,f64)>> = vec![vec![];source_size];
+		for &(source,destination,weight) in self.entries.iter()
+		{
+			if source>=source_size || destination>=target_size
+			{
+				panic!("The entry ({},{}) of the TrafficMatrix is out of the {}x{} matrix.",source,destination,source_size,target_size);
+			}
+			if weight>0.0
+			{
+				rows[source].push((destination,weight));
+			}
+		}
+		let totals : Vec<f64> = rows.iter().map(|row|row.iter().fold(0.0,|total,&(_,weight)|total+weight)).collect();
+		let maximum = totals.iter().cloned().fold(0.0,f64::max);
+		if maximum==0.0
+		{
+			panic!("The TrafficMatrix has no positive weights.");
+		}
+		self.load_scales = totals.iter().map(|&total|if self.scale_load { (total/maximum) as f32 } else { 1.0 }).collect();
+		self.tables = rows.iter().map(|row|if row.is_empty() { None } else { Some(AliasTable::new(row)) }).collect();
+	}
+	fn get_destination(&self, origin:usize, _topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)->usize
+	{
+		match self.tables[origin]
+		{
+			Some(ref table) => table.sample(rng),
+			None => origin,
+		}
+	}
+	fn load_scale(&self, origin:usize) -> f32
+	{
+		self.load_scales[origin]
+	}
+}
+
+impl TrafficMatrix
+{
+	fn new(arg:PatternBuilderArgument) -> TrafficMatrix
+	{
+		let mut filename=None;
+		let mut dense=false;
+		let mut scale_load=false;
+		match_object_panic!(arg.cv,"TrafficMatrix",value,
+			"filename" => match value
+			{
+				&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
+				_ => panic!("bad value for filename"),
+			},
+			"dense" => match value
+			{
+				&ConfigurationValue::True => dense=true,
+				&ConfigurationValue::False => dense=false,
+				_ => panic!("bad value for dense"),
+			},
+			"scale_load" => match value
+			{
+				&ConfigurationValue::True => scale_load=true,
+				&ConfigurationValue::False => scale_load=false,
+				_ => panic!("bad value for scale_load"),
+			},
+		);
+		let filename=filename.expect("There were no filename");
+		let file=File::open(&filename).unwrap_or_else(|e|panic!("could not open the traffic matrix file {}: {}",filename,e));
+		let mut entries=vec![];
+		let mut row=0;
+		for (line_index,line) in BufReader::new(file).lines().enumerate()
+		{
+			let line = line.unwrap_or_else(|e|panic!("could not read the traffic matrix file {}: {}",filename,e));
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#')
+			{
+				continue;
+			}
+			let fields:Vec<f64> = line.split(|c:char|c.is_whitespace()||c==',').filter(|s|!s.is_empty()).map(|s|s.parse::<f64>().unwrap_or_else(|_|panic!("bad number {} in line {} of {}",s,line_index+1,filename))).collect();
+			if fields.iter().any(|&x|x<0.0)
+			{
+				panic!("negative value in line {} of {}",line_index+1,filename);
+			}
+			if dense
+			{
+				entries.extend(fields.iter().enumerate().filter(|&(_,&weight)|weight>0.0).map(|(destination,&weight)|(row,destination,weight)));
+				row+=1;
+			}
+			else
+			{
+				if fields.len()!=3
+				{
+					panic!("line {} of {} should be `source destination weight`",line_index+1,filename);
+				}
+				entries.push((fields[0] as usize,fields[1] as usize,fields[2]));
+			}
+		}
+		TrafficMatrix{
+			entries,
+			scale_load,
+			tables: vec![],
+			load_scales: vec![],
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use std::collections::BTreeMap;
+
+	///The maximum total weight of an assignment, trying every permutation of the columns from `row` onward.
+	fn brute_force_assignment(weights:&[Vec<f64>], row:usize, used:&mut Vec<bool>) -> f64
+	{
+		if row==weights.len()
+		{
+			return 0.0;
+		}
+		let mut best = f64::NEG_INFINITY;
+		for column in 0..weights.len()
+		{
+			if !used[column]
+			{
+				used[column] = true;
+				best = best.max(weights[row][column]+brute_force_assignment(weights,row+1,used));
+				used[column] = false;
+			}
+		}
+		best
+	}
+
+	#[test]
+	fn assignment_matches_brute_force()
+	{
+		let mut rng = StdRng::seed_from_u64(10);
+		for n in 1..=6
+		{
+			for _ in 0..20
+			{
+				//Integer weights, some negative, so that ties are frequent.
+				let weights:Vec<Vec<f64>> = (0..n).map(|_|(0..n).map(|_|rng.gen_range(-5..10) as f64).collect()).collect();
+				let assignment = maximum_weight_assignment(&weights);
+				let mut columns = assignment.clone();
+				columns.sort();
+				assert_eq!(columns,(0..n).collect::<Vec<usize>>(),"the assignment {:?} is not a permutation",assignment);
+				let total:f64 = assignment.iter().enumerate().map(|(row,&column)|weights[row][column]).sum();
+				assert_eq!(total,brute_force_assignment(&weights,0,&mut vec![false;n]),"the assignment {:?} is not optimal for {:?}",assignment,weights);
+			}
+		}
+	}
+
+	#[test]
+	fn alias_table_follows_the_weights()
+	{
+		let entries = vec![(3,1.0),(7,2.0),(11,0.5),(13,4.5),(17,2.0)];
+		let total:f64 = entries.iter().map(|&(_,weight)|weight).sum();
+		let table = AliasTable::new(&entries);
+		let n = entries.len();
+		//The exact probability of each outcome given by the table.
+		for (index,&(outcome,weight)) in entries.iter().enumerate()
+		{
+			let probability:f64 = (0..n).map(|column|{
+				let kept = if column==index { table.probability[column] } else { 0.0 };
+				let aliased = if table.alias[column]==index { 1.0-table.probability[column] } else { 0.0 };
+				kept+aliased
+			}).sum::<f64>()/n as f64;
+			assert!((probability-weight/total).abs()<1e-9,"outcome {} has probability {} instead of {}",outcome,probability,weight/total);
+		}
+		//The frequencies of the samples.
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let samples = 100000;
+		let mut counts:BTreeMap<usize,usize> = BTreeMap::new();
+		for _ in 0..samples
+		{
+			*counts.entry(table.sample(&rng)).or_insert(0) += 1;
+		}
+		for &(outcome,weight) in entries.iter()
+		{
+			let frequency = *counts.get(&outcome).unwrap_or(&0) as f64/samples as f64;
+			assert!((frequency-weight/total).abs()<0.01,"outcome {} has frequency {} instead of {}",outcome,frequency,weight/total);
+		}
+		assert_eq!(counts.len(),n,"some sample is not an outcome: {:?}",counts);
+	}
+
+	#[test]
+	fn hilbert_curve_visits_adjacent_cells()
+	{
+		for dimensions in 1..=3
+		{
+			for bits in 1..=3
+			{
+				let side = 1<<bits;
+				let grid = CartesianData::new(&vec![side;dimensions]);
+				//The cell at each position of the curve.
+				let mut cells:Vec<Option<Vec<usize>>> = vec![None;grid.size];
+				for cell in 0..grid.size
+				{
+					let coordinates = grid.unpack(cell);
+					let index = hilbert_index(&coordinates,bits);
+					assert!(index<grid.size,"the Hilbert index of {:?} is out of range",coordinates);
+					assert!(cells[index].is_none(),"the Hilbert index {} is repeated with {} dimensions and {} bits",index,dimensions,bits);
+					cells[index] = Some(coordinates);
+				}
+				for index in 1..grid.size
+				{
+					let previous = cells[index-1].as_ref().unwrap();
+					let current = cells[index].as_ref().unwrap();
+					let distance:usize = previous.iter().zip(current.iter()).map(|(&a,&b)|if a>b { a-b } else { b-a }).sum();
+					assert_eq!(distance,1,"the cells {:?} and {:?} are consecutive in the Hilbert curve but not adjacent",previous,current);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn bit_permutations_are_bijections()
+	{
+		let kinds = vec![
+			BitPermutationKind::Complement,
+			BitPermutationKind::Reverse,
+			BitPermutationKind::PerfectShuffle,
+			BitPermutationKind::Butterfly,
+			BitPermutationKind::Transpose,
+			BitPermutationKind::Rotation(3),
+			BitPermutationKind::Rotation(-2),
+		];
+		for kind in kinds.iter()
+		{
+			for bits in 1..=8
+			{
+				if *kind==BitPermutationKind::Transpose && bits%2==1
+				{
+					continue;
+				}
+				let size = 1<<bits;
+				let mut images:Vec<usize> = (0..size).map(|x|kind.apply(x,bits)).collect();
+				assert!(images.iter().all(|&image|image<size),"{:?} with {} bits goes out of range",kind,bits);
+				images.sort();
+				images.dedup();
+				assert_eq!(images.len(),size,"{:?} with {} bits is not a bijection",kind,bits);
+			}
+		}
+	}
+
+	#[test]
+	fn bit_reverse_is_an_involution()
+	{
+		for bits in 1..=8
+		{
+			for x in 0..(1<<bits)
+			{
+				assert_eq!(BitPermutationKind::Reverse.apply(BitPermutationKind::Reverse.apply(x,bits),bits),x);
+			}
+		}
+		let x = 0b1011;
+		assert_eq!(BitPermutationKind::Reverse.apply(x,4),0b1101);
+		assert_eq!(BitPermutationKind::Reverse.apply(x,usize::BITS as usize),x.reverse_bits());
+	}
+}
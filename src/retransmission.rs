@@ -0,0 +1,517 @@
+/*!
+
+End-to-end retransmission of the packets dropped by a lossy network.
+
+When the routers use a lossy transmission mechanism, such as `transmission_mechanism: Lossy` in the Basic router, the packets that do not fit in the next buffer are dropped. The servers may then recover them with a retransmission protocol, configured in the `retransmission` field of the `Configuration`.
+
+```ignore
+retransmission: GoBackN{
+	window: 16,//Maximum number of packets sent towards a destination that have not been acknowledged yet.
+	timeout: 2000,//Cycles to wait for the acknowledgement of a packet, since it enters the network, before sending it again.
+	acknowledge_delay: 100,//Cycles that the acknowledgements take to reach the origin. Defaults to 0.
+}
+```
+
+Each pair of origin and destination servers forms a flow, whose packets are given consecutive sequence numbers. In `GoBackN` the destination only accepts the packets in order, discarding the rest, and the acknowledgements are cumulative. On a timeout the origin sends again all the unacknowledged packets of the flow. In `SelectiveRepeat`, with the same fields, the destination accepts the packets in any order, discarding only duplicates, and each packet is acknowledged and sent again on its own.
+
+The acknowledgements are not modelled as network traffic, they just reach the origin server after `acknowledge_delay` cycles. The phits of the discarded packets are counted in the `accepted_load`, but not in the `goodput` of the `loss_statistics` in the result.
+
+*/
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{VecDeque,BTreeMap,BTreeSet};
+
+use quantifiable_derive::Quantifiable;//the derive macro
+use crate::config_parser::ConfigurationValue;
+use crate::routing::RoutingInfo;
+use crate::{Phit,Packet,Message};
+
+///The policy of the destination to accept packets and of the origin to send them again.
+#[derive(Clone,Copy,Debug,PartialEq,Quantifiable)]
+pub enum RetransmissionProtocol
+{
+	///Packets are only accepted in order. A timeout sends again every unacknowledged packet of the flow.
+	GoBackN,
+	///Packets are accepted in any order. A timeout only sends again the expired packet.
+	SelectiveRepeat,
+}
+
+///The parameters of the retransmission protocol, shared by all the servers.
+#[derive(Clone,Debug,Quantifiable)]
+pub struct RetransmissionConfiguration
+{
+	///The protocol in use.
+	pub protocol: RetransmissionProtocol,
+	///Maximum number of unacknowledged packets in each flow.
+	pub window: usize,
+	///Cycles since the leading phit of the last copy of a packet entered the network after which it is sent again.
+	pub timeout: usize,
+	///Cycles from the acceptance of a packet until its acknowledgement reaches the origin.
+	pub acknowledge_delay: usize,
+}
+
+impl RetransmissionConfiguration
+{
+	pub fn new(cv:&ConfigurationValue) -> RetransmissionConfiguration
+	{
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			let protocol = match cv_name.as_ref()
+			{
+				"GoBackN" => RetransmissionProtocol::GoBackN,
+				"SelectiveRepeat" => RetransmissionProtocol::SelectiveRepeat,
+				_ => panic!("Unknown retransmission protocol {}",cv_name),
+			};
+			let mut window=None;
+			let mut timeout=None;
+			let mut acknowledge_delay=0;
+			for &(ref name,ref value) in cv_pairs
+			{
+				match name.as_ref()
+				{
+					"window" => match value
+					{
+						&ConfigurationValue::Number(f) => window=Some(f as usize),
+						_ => panic!("bad value for window"),
+					},
+					"timeout" => match value
+					{
+						&ConfigurationValue::Number(f) => timeout=Some(f as usize),
+						_ => panic!("bad value for timeout"),
+					},
+					"acknowledge_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => acknowledge_delay=f as usize,
+						_ => panic!("bad value for acknowledge_delay"),
+					},
+					"legend_name" => (),
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			let window=window.expect("There were no window");
+			let timeout=timeout.expect("There were no timeout");
+			if window==0 || timeout==0
+			{
+				panic!("The window and timeout of the retransmission protocol must be positive.");
+			}
+			RetransmissionConfiguration{
+				protocol,
+				window,
+				timeout,
+				acknowledge_delay,
+			}
+		}
+		else
+		{
+			panic!("Trying to create a retransmission protocol from a non-Object");
+		}
+	}
+}
+
+///A packet sent but still not acknowledged.
+#[derive(Clone,Quantifiable)]
+struct UnacknowledgedPacket
+{
+	///The sequence number of the packet in its flow.
+	sequence_number: usize,
+	///The last copy of the packet handed to the network.
+	packet: Rc<Packet>,
+	///The cycle in which the leading phit of the last copy entered the network. `None` while it waits in the server.
+	last_sent: Option<usize>,
+	///Whether a new copy is already waiting to be sent.
+	queued: bool,
+}
+
+///The state of the origin of a flow.
+#[derive(Clone,Default,Quantifiable)]
+struct SenderFlow
+{
+	///The sequence number to give to the next new packet.
+	next_sequence_number: usize,
+	///The unacknowledged packets, sorted by their sequence number.
+	unacknowledged: VecDeque<UnacknowledgedPacket>,
+}
+
+///The state of the destination of a flow.
+#[derive(Clone,Default,Quantifiable)]
+struct ReceiverFlow
+{
+	///The lowest sequence number not received yet.
+	expected: usize,
+	///The received sequence numbers greater than `expected`. Only used by `SelectiveRepeat`.
+	received: BTreeSet<usize>,
+}
+
+///The state of the retransmission protocol in a server, both as origin and as destination of flows.
+#[derive(Clone,Quantifiable)]
+pub struct ServerRetransmission
+{
+	///The parameters of the protocol.
+	configuration: RetransmissionConfiguration,
+	///The flows originated at this server, indexed by their destination.
+	senders: BTreeMap<usize,SenderFlow>,
+	///The flows towards this server, indexed by their origin.
+	receivers: BTreeMap<usize,ReceiverFlow>,
+	///The packets to send again, as `(destination,sequence_number)`.
+	retransmission_queue: VecDeque<(usize,usize)>,
+	///The acknowledgements generated by this server as `(cycle_to_deliver,origin,sequence_number)`.
+	outgoing_acknowledgements: VecDeque<(usize,usize,usize)>,
+	///The packets being received that have been discarded.
+	discarded_packets: BTreeSet<*const Packet>,
+}
+
+impl ServerRetransmission
+{
+	pub fn new(configuration:&RetransmissionConfiguration) -> ServerRetransmission
+	{
+		ServerRetransmission{
+			configuration: configuration.clone(),
+			senders: BTreeMap::new(),
+			receivers: BTreeMap::new(),
+			retransmission_queue: VecDeque::new(),
+			outgoing_acknowledgements: VecDeque::new(),
+			discarded_packets: BTreeSet::new(),
+		}
+	}
+	///Queues again the packets whose acknowledgement has expired.
+	///Returns the `(destination,size)` of each of them, to notify the congestion control of the loss.
+	pub fn check_timeouts(&mut self, cycle:usize) -> Vec<(usize,usize)>
+	{
+		let mut expired_packets = vec![];
+		let timeout = self.configuration.timeout;
+		for (&destination,flow) in self.senders.iter_mut()
+		{
+			match self.configuration.protocol
+			{
+				RetransmissionProtocol::GoBackN =>
+				{
+					let expired = match flow.unacknowledged.front()
+					{
+						Some(first) => !first.queued && first.last_sent.map_or(false,|sent|sent+timeout<=cycle),
+						None => false,
+					};
+					if expired
+					{
+						for entry in flow.unacknowledged.iter_mut().filter(|entry|!entry.queued)
+						{
+							entry.queued=true;
+							self.retransmission_queue.push_back((destination,entry.sequence_number));
+							expired_packets.push((destination,entry.packet.size));
+						}
+					}
+				},
+				RetransmissionProtocol::SelectiveRepeat =>
+				{
+					for entry in flow.unacknowledged.iter_mut().filter(|entry|!entry.queued && entry.last_sent.map_or(false,|sent|sent+timeout<=cycle))
+					{
+						entry.queued=true;
+						self.retransmission_queue.push_back((destination,entry.sequence_number));
+						expired_packets.push((destination,entry.packet.size));
+					}
+				},
+			}
+		}
+		expired_packets
+	}
+	///Gives a new copy of the next expired packet, if any. These go before the new packets.
+	pub fn next_retransmission(&mut self) -> Option<Rc<Packet>>
+	{
+		while let Some((destination,sequence_number)) = self.retransmission_queue.pop_front()
+		{
+			let flow = self.senders.get_mut(&destination).expect("the flow of a retransmission does not exist");
+			//The packet may have been acknowledged while it was waiting.
+			if let Some(entry) = flow.unacknowledged.iter_mut().find(|entry|entry.sequence_number==sequence_number)
+			{
+				let packet = sequenced_copy(&entry.packet,sequence_number);
+				entry.packet = packet.clone();
+				entry.last_sent = None;
+				entry.queued = false;
+				return Some(packet);
+			}
+		}
+		None
+	}
+	///Takes the first of the `stored_packets` and gives it a sequence number, unless the window of its flow is full.
+	pub fn next_new_packet(&mut self, stored_packets:&mut VecDeque<Rc<Packet>>) -> Option<Rc<Packet>>
+	{
+		let window = self.configuration.window;
+		let destination = match stored_packets.front()
+		{
+			Some(packet) => packet.message.destination,
+			None => return None,
+		};
+		let flow = self.senders.entry(destination).or_default();
+		if let Some(first) = flow.unacknowledged.front()
+		{
+			if flow.next_sequence_number >= first.sequence_number + window
+			{
+				return None;
+			}
+		}
+		let sequence_number = flow.next_sequence_number;
+		flow.next_sequence_number+=1;
+		let packet = sequenced_copy(&stored_packets.pop_front().expect("There are not packets in queue"),sequence_number);
+		flow.unacknowledged.push_back(UnacknowledgedPacket{
+			sequence_number,
+			packet: packet.clone(),
+			last_sent: None,
+			queued: false,
+		});
+		Some(packet)
+	}
+	///Starts the timeout of a packet when its leading phit enters the network.
+	pub fn notify_injection(&mut self, packet:&Rc<Packet>, cycle:usize)
+	{
+		let sequence_number = packet.sequence_number.expect("injected a packet without sequence number");
+		if let Some(flow) = self.senders.get_mut(&packet.message.destination)
+		{
+			//The packet may have been acknowledged meanwhile, or be an older copy.
+			if let Some(entry) = flow.unacknowledged.iter_mut().find(|entry|entry.sequence_number==sequence_number && Rc::ptr_eq(&entry.packet,packet))
+			{
+				entry.last_sent = Some(cycle);
+			}
+		}
+	}
+	///Decides whether a phit reaching this server is accepted, or discarded as a duplicate or out of order.
+	///The decision is taken at the leading phit and it is remembered until the last one, so that no phit of a discarded packet counts towards its message.
+	///When a packet is accepted an acknowledgement is scheduled towards its origin.
+	pub fn accept_phit(&mut self, phit:&Phit, cycle:usize) -> bool
+	{
+		let packet_ptr = phit.packet.as_ref() as *const Packet;
+		if !phit.is_begin()
+		{
+			return if phit.is_end()
+			{
+				!self.discarded_packets.remove(&packet_ptr)
+			}
+			else
+			{
+				!self.discarded_packets.contains(&packet_ptr)
+			};
+		}
+		let sequence_number = phit.packet.sequence_number.expect("received a packet without sequence number");
+		let origin = phit.packet.message.origin;
+		let flow = self.receivers.entry(origin).or_default();
+		let accept = match self.configuration.protocol
+		{
+			RetransmissionProtocol::GoBackN =>
+			{
+				if sequence_number==flow.expected
+				{
+					flow.expected+=1;
+					true
+				}
+				else
+				{
+					false
+				}
+			},
+			RetransmissionProtocol::SelectiveRepeat =>
+			{
+				if sequence_number<flow.expected || flow.received.contains(&sequence_number)
+				{
+					false
+				}
+				else
+				{
+					flow.received.insert(sequence_number);
+					while flow.received.remove(&flow.expected)
+					{
+						flow.expected+=1;
+					}
+					true
+				}
+			},
+		};
+		if accept
+		{
+			self.outgoing_acknowledgements.push_back((cycle+self.configuration.acknowledge_delay,origin,sequence_number));
+		}
+		else if !phit.is_end()
+		{
+			self.discarded_packets.insert(packet_ptr);
+		}
+		accept
+	}
+	///Takes the acknowledgements that reach their origin at the given cycle, as `(origin,sequence_number)`.
+	pub fn take_acknowledgements(&mut self, cycle:usize) -> Vec<(usize,usize)>
+	{
+		let mut acknowledgements = vec![];
+		while let Some(&(delivery,origin,sequence_number)) = self.outgoing_acknowledgements.front()
+		{
+			if delivery>cycle
+			{
+				break;
+			}
+			self.outgoing_acknowledgements.pop_front();
+			acknowledgements.push((origin,sequence_number));
+		}
+		acknowledgements
+	}
+	///Processes the acknowledgement of a packet sent to `destination`.
+	pub fn acknowledge(&mut self, destination:usize, sequence_number:usize)
+	{
+		let flow = self.senders.get_mut(&destination).expect("acknowledgement for a flow that does not exist");
+		match self.configuration.protocol
+		{
+			//Acknowledgements are cumulative.
+			RetransmissionProtocol::GoBackN => while let Some(first) = flow.unacknowledged.front()
+			{
+				if first.sequence_number>sequence_number
+				{
+					break;
+				}
+				flow.unacknowledged.pop_front();
+			},
+			RetransmissionProtocol::SelectiveRepeat => flow.unacknowledged.retain(|entry|entry.sequence_number!=sequence_number),
+		}
+	}
+}
+
+///Builds a new packet with the same content as the given one and the given sequence number, ready to be sent into the network.
+fn sequenced_copy(packet:&Packet, sequence_number:usize) -> Rc<Packet>
+{
+	let message:Rc<Message> = packet.message.clone();
+	Rc::new(Packet{
+		size: packet.size,
+		routing_info: RefCell::new(RoutingInfo::new()),
+		message,
+		index: packet.index,
+		cycle_into_network: RefCell::new(0),
+		extra: RefCell::new(None),
+		sequence_number: Some(sequence_number),
+		dropped: RefCell::new(false),
+		ecn_marked: RefCell::new(false),
+	})
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn configuration(protocol:RetransmissionProtocol) -> RetransmissionConfiguration
+	{
+		RetransmissionConfiguration{
+			protocol,
+			window: 4,
+			timeout: 100,
+			acknowledge_delay: 10,
+		}
+	}
+
+	///A packet of `size` phits from server 0 to server 1.
+	fn new_packet(size:usize, sequence_number:Option<usize>) -> Rc<Packet>
+	{
+		let message = Rc::new(Message{
+			origin: 0,
+			destination: 1,
+			size,
+			creation_cycle: 0,
+		});
+		Rc::new(Packet{
+			size,
+			routing_info: RefCell::new(RoutingInfo::new()),
+			message,
+			index: 0,
+			cycle_into_network: RefCell::new(0),
+			extra: RefCell::new(None),
+			sequence_number,
+			dropped: RefCell::new(false),
+			ecn_marked: RefCell::new(false),
+		})
+	}
+
+	///Whether the receiver accepts every phit of the packet.
+	fn receive(receiver:&mut ServerRetransmission, packet:&Rc<Packet>, cycle:usize) -> bool
+	{
+		let decisions:Vec<bool> = (0..packet.size).map(|index|receiver.accept_phit(&Phit{packet:packet.clone(),index,virtual_channel:RefCell::new(None)},cycle)).collect();
+		assert!(decisions.iter().all(|&decision|decision==decisions[0]),"the phits of a packet got different decisions");
+		decisions[0]
+	}
+
+	#[test]
+	fn duplicates_are_discarded()
+	{
+		for &protocol in [RetransmissionProtocol::GoBackN,RetransmissionProtocol::SelectiveRepeat].iter()
+		{
+			let mut receiver = ServerRetransmission::new(&configuration(protocol));
+			assert!(receive(&mut receiver,&new_packet(3,Some(0)),0));
+			assert!(!receive(&mut receiver,&new_packet(3,Some(0)),5),"{:?} accepted a duplicate",protocol);
+			assert!(receive(&mut receiver,&new_packet(3,Some(1)),5));
+			//Only the accepted copies are acknowledged.
+			assert_eq!(receiver.take_acknowledgements(15),vec![(0,0),(0,1)]);
+		}
+	}
+
+	#[test]
+	fn go_back_n_discards_out_of_order_and_rewinds()
+	{
+		let mut receiver = ServerRetransmission::new(&configuration(RetransmissionProtocol::GoBackN));
+		assert!(!receive(&mut receiver,&new_packet(2,Some(1)),0));
+		assert!(receive(&mut receiver,&new_packet(2,Some(0)),0));
+		assert!(receive(&mut receiver,&new_packet(2,Some(1)),0));
+
+		let mut sender = ServerRetransmission::new(&configuration(RetransmissionProtocol::GoBackN));
+		let mut stored:VecDeque<Rc<Packet>> = (0..3).map(|_|new_packet(2,None)).collect();
+		let sent:Vec<Rc<Packet>> = (0..3).map(|_|sender.next_new_packet(&mut stored).expect("the window is not full")).collect();
+		assert_eq!(sent.iter().map(|packet|packet.sequence_number.unwrap()).collect::<Vec<usize>>(),vec![0,1,2]);
+		//The timeout starts when the packets enter the network.
+		assert!(sender.check_timeouts(1000).is_empty());
+		for packet in sent.iter()
+		{
+			sender.notify_injection(packet,1000);
+		}
+		sender.acknowledge(1,0);
+		assert!(sender.check_timeouts(1099).is_empty());
+		assert_eq!(sender.check_timeouts(1100),vec![(1,2),(1,2)]);
+		let resent:Vec<usize> = std::iter::from_fn(||sender.next_retransmission()).map(|packet|packet.sequence_number.unwrap()).collect();
+		assert_eq!(resent,vec![1,2]);
+		//The copies wait to be injected again.
+		assert!(sender.check_timeouts(5000).is_empty());
+	}
+
+	#[test]
+	fn go_back_n_respects_the_window()
+	{
+		let mut sender = ServerRetransmission::new(&configuration(RetransmissionProtocol::GoBackN));
+		let mut stored:VecDeque<Rc<Packet>> = (0..6).map(|_|new_packet(1,None)).collect();
+		for _ in 0..4
+		{
+			assert!(sender.next_new_packet(&mut stored).is_some());
+		}
+		assert!(sender.next_new_packet(&mut stored).is_none());
+		//A cumulative acknowledgement opens the window.
+		sender.acknowledge(1,1);
+		assert!(sender.next_new_packet(&mut stored).is_some());
+		assert!(sender.next_new_packet(&mut stored).is_some());
+		assert!(stored.is_empty());
+	}
+
+	#[test]
+	fn selective_repeat_accepts_out_of_order()
+	{
+		let mut receiver = ServerRetransmission::new(&configuration(RetransmissionProtocol::SelectiveRepeat));
+		assert!(receive(&mut receiver,&new_packet(2,Some(1)),0));
+		assert!(receive(&mut receiver,&new_packet(2,Some(0)),3));
+		assert!(!receive(&mut receiver,&new_packet(2,Some(1)),4));
+		assert!(receive(&mut receiver,&new_packet(2,Some(2)),4));
+		assert_eq!(receiver.take_acknowledgements(12),vec![(0,1)]);
+		assert_eq!(receiver.take_acknowledgements(14),vec![(0,0),(0,2)]);
+
+		let mut sender = ServerRetransmission::new(&configuration(RetransmissionProtocol::SelectiveRepeat));
+		let mut stored:VecDeque<Rc<Packet>> = (0..3).map(|_|new_packet(2,None)).collect();
+		let sent:Vec<Rc<Packet>> = (0..3).map(|_|sender.next_new_packet(&mut stored).unwrap()).collect();
+		for packet in sent.iter()
+		{
+			sender.notify_injection(packet,0);
+		}
+		//Only the unacknowledged packet is sent again.
+		sender.acknowledge(1,0);
+		sender.acknowledge(1,2);
+		assert_eq!(sender.check_timeouts(100),vec![(1,2)]);
+		let resent:Vec<usize> = std::iter::from_fn(||sender.next_retransmission()).map(|packet|packet.sequence_number.unwrap()).collect();
+		assert_eq!(resent,vec![1]);
+	}
+}
@@ -0,0 +1,706 @@
+/*!
+
+A switch allocator decides which of the phits ready to advance inside a router actually traverse the crossbar in the current cycle.
+In a Basic router with output buffers it matches the input buffers with the output buffers; otherwise it matches the input buffers directly with the output links.
+
+see [`new_switch_allocator`](fn.new_switch_allocator.html) for documentation on the configuration syntax of predefined allocators.
+
+*/
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::ops::DerefMut;
+use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
+
+use crate::config_parser::ConfigurationValue;
+use crate::{Plugs,match_object_panic};
+
+///A phit that is ready to go from an entry port into an exit port.
+#[derive(Clone,Debug)]
+pub struct SwitchRequest
+{
+	///The port in which the phit is. When the router has output buffers it is the port of the output buffer, this is, the same as `exit_port`.
+	pub entry_port: usize,
+	///The virtual channel in which the phit is.
+	pub entry_virtual_channel: usize,
+	///The port the phit wants to go.
+	pub exit_port: usize,
+	///The virtual channel the phit is going to use in the exit port.
+	pub exit_virtual_channel: usize,
+	///Number of cycles since the packet of the phit entered the network.
+	pub age: usize,
+	///Whether the phit is the last one of its packet.
+	pub last_phit: bool,
+}
+
+///An allocator of the crossbar of a router.
+pub trait SwitchAllocator : Debug
+{
+	///Select the requests that advance this cycle. At most a request is granted for each exit port.
+	///When `input_exclusive` returns true, at most a request is granted for each entry port.
+	fn allocate(&mut self, requests:Vec<SwitchRequest>, rng:&RefCell<StdRng>) -> Vec<SwitchRequest>;
+	///Whether each entry port can get at most a grant each cycle. Used to compute the matching efficiency.
+	fn input_exclusive(&self) -> bool;
+}
+
+///The argument of a builder function for `SwitchAllocator`s.
+#[non_exhaustive]
+pub struct SwitchAllocatorBuilderArgument<'a>
+{
+	///A ConfigurationValue::Object defining the allocator.
+	pub cv: &'a ConfigurationValue,
+	///The user defined plugs. In case the allocator needs to create elements.
+	pub plugs: &'a Plugs,
+	///The number of ports of the router.
+	pub num_ports: usize,
+	///The number of virtual channels of each port.
+	pub num_virtual_channels: usize,
+}
+
+/**Build a new switch allocator. It is given to a router in its `switch_allocator` field.
+
+### Token
+The default allocator of the Basic router. Each exit port selects a virtual channel by a token, as in FSIN, advancing the token when a packet has been completely sent. There is no restriction on the number of phits leaving an entry port.
+```ignore
+Token
+```
+
+### Random
+Each exit port selects randomly among its requests. There is no restriction on the number of phits leaving an entry port.
+```ignore
+Random
+```
+
+### ISLIP
+A separable allocator with round-robin arbiters, whose pointers are only updated by the grants accepted in the first iteration. Each entry port and each exit port get at most one grant.
+By default it is output-first, as the original iSLIP: exit ports grant to entry ports and then entry ports accept one of the grants. With `input_first` entry ports select one of their requests and then exit ports grant one of those selections.
+```ignore
+ISLIP{
+	iterations: 2,
+	input_first: false,//optional, default to false.
+}
+```
+
+### Wavefront
+Serves the requests by diagonals of the matrix of entry ports and exit ports. The diagonal with the highest priority rotates each cycle. Each entry port and each exit port get at most one grant.
+```ignore
+Wavefront
+```
+
+### OldestFirst
+Serves the requests in order of age of their packets, ties broken randomly. Each entry port and each exit port get at most one grant.
+```ignore
+OldestFirst
+```
+
+*/
+pub fn new_switch_allocator(arg:SwitchAllocatorBuilderArgument) -> Box<dyn SwitchAllocator>
+{
+	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
+	{
+		match arg.plugs.switch_allocators.get(cv_name)
+		{
+			Some(builder) => return builder(arg),
+			_ => (),
+		};
+		match cv_name.as_ref()
+		{
+			"Token" => Box::new(TokenAllocator::new(arg)),
+			"Random" => Box::new(RandomAllocator::new(arg)),
+			"ISLIP" => Box::new(ISLIP::new(arg)),
+			"Wavefront" => Box::new(Wavefront::new(arg)),
+			"OldestFirst" => Box::new(OldestFirst::new(arg)),
+			_ => panic!("Unknown switch allocator {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a SwitchAllocator from a non-Object");
+	}
+}
+
+///Size of a maximum matching of the requests. It is the largest number of requests that can be granted simultaneously.
+///When `input_exclusive` is false it is just the number of requested exit ports.
+pub fn maximum_matching_size(requests:&[SwitchRequest], input_exclusive:bool) -> usize
+{
+	let num_exits = requests.iter().map(|r|r.exit_port+1).max().unwrap_or(0);
+	if !input_exclusive
+	{
+		let mut requested = vec![false;num_exits];
+		for r in requests
+		{
+			requested[r.exit_port]=true;
+		}
+		return requested.into_iter().filter(|&x|x).count();
+	}
+	let num_entries = requests.iter().map(|r|r.entry_port+1).max().unwrap_or(0);
+	let mut adjacency : Vec<Vec<usize>> = vec![vec![];num_entries];
+	for r in requests
+	{
+		if !adjacency[r.entry_port].contains(&r.exit_port)
+		{
+			adjacency[r.entry_port].push(r.exit_port);
+		}
+	}
+	//Kuhn's augmenting paths.
+	fn augment(entry:usize, adjacency:&[Vec<usize>], visited:&mut [bool], exit_match:&mut [Option<usize>]) -> bool
+	{
+		for &exit in adjacency[entry].iter()
+		{
+			if !visited[exit]
+			{
+				visited[exit]=true;
+				let free = match exit_match[exit]
+				{
+					None => true,
+					Some(other) => augment(other,adjacency,visited,exit_match),
+				};
+				if free
+				{
+					exit_match[exit]=Some(entry);
+					return true;
+				}
+			}
+		}
+		false
+	}
+	let mut exit_match = vec![None;num_exits];
+	let mut size = 0;
+	for entry in 0..num_entries
+	{
+		let mut visited = vec![false;num_exits];
+		if augment(entry,&adjacency,&mut visited,&mut exit_match)
+		{
+			size+=1;
+		}
+	}
+	size
+}
+
+///Remembers the last maximum matching computed, so that it is not computed again while the requested pairs of ports do not change.
+#[derive(Debug,Default)]
+pub struct MaximumMatchingCache
+{
+	///The pairs `(entry_port,exit_port)` of the last requests, sorted and without repetitions.
+	pairs: Vec<(usize,usize)>,
+	///The size of the maximum matching of `pairs`.
+	size: usize,
+}
+
+impl MaximumMatchingCache
+{
+	///Same as `maximum_matching_size`, reusing the previous result when the requests connect the same ports.
+	pub fn maximum_matching_size(&mut self, requests:&[SwitchRequest], input_exclusive:bool) -> usize
+	{
+		if !input_exclusive
+		{
+			//Just counting the exit ports is cheap enough.
+			return maximum_matching_size(requests,false);
+		}
+		let mut pairs : Vec<(usize,usize)> = requests.iter().map(|r|(r.entry_port,r.exit_port)).collect();
+		pairs.sort_unstable();
+		pairs.dedup();
+		if pairs!=self.pairs
+		{
+			self.size = maximum_matching_size(requests,true);
+			self.pairs = pairs;
+		}
+		self.size
+	}
+}
+
+///Index of the value in `values` that is first found going cyclically from `pointer`, over a range of size `modulo`.
+fn round_robin_select(values:&[usize], pointer:usize, modulo:usize) -> usize
+{
+	let mut best=0;
+	let mut best_distance=modulo;
+	for (index,&value) in values.iter().enumerate()
+	{
+		let distance = (value+modulo-pointer)%modulo;
+		if distance<best_distance
+		{
+			best=index;
+			best_distance=distance;
+		}
+	}
+	best
+}
+
+///Each exit port selects a virtual channel by a token, which advances when a packet finishes.
+#[derive(Debug)]
+pub struct TokenAllocator
+{
+	num_virtual_channels: usize,
+	///The token of each exit port.
+	port_token: Vec<usize>,
+}
+
+impl SwitchAllocator for TokenAllocator
+{
+	fn allocate(&mut self, requests:Vec<SwitchRequest>, _rng:&RefCell<StdRng>) -> Vec<SwitchRequest>
+	{
+		let mut per_exit : Vec<Vec<SwitchRequest>> = vec![vec![];self.port_token.len()];
+		for r in requests
+		{
+			per_exit[r.exit_port].push(r);
+		}
+		let mut granted = vec![];
+		for (exit_port,candidates) in per_exit.into_iter().enumerate()
+		{
+			if candidates.is_empty()
+			{
+				continue;
+			}
+			let vcs : Vec<usize> = candidates.iter().map(|r|r.exit_virtual_channel).collect();
+			let index = round_robin_select(&vcs,self.port_token[exit_port],self.num_virtual_channels);
+			let selected = candidates[index].clone();
+			self.port_token[exit_port] = if selected.last_phit
+			{
+				(selected.exit_virtual_channel+1)%self.num_virtual_channels
+			}
+			else
+			{
+				selected.exit_virtual_channel
+			};
+			granted.push(selected);
+		}
+		granted
+	}
+	fn input_exclusive(&self) -> bool
+	{
+		false
+	}
+}
+
+impl TokenAllocator
+{
+	pub fn new(arg:SwitchAllocatorBuilderArgument) -> TokenAllocator
+	{
+		match_object_panic!(arg.cv,"Token",_value,);
+		TokenAllocator{
+			num_virtual_channels: arg.num_virtual_channels,
+			port_token: vec![0;arg.num_ports],
+		}
+	}
+}
+
+///Each exit port selects randomly among its requests.
+#[derive(Debug)]
+pub struct RandomAllocator
+{
+	num_ports: usize,
+}
+
+impl SwitchAllocator for RandomAllocator
+{
+	fn allocate(&mut self, requests:Vec<SwitchRequest>, rng:&RefCell<StdRng>) -> Vec<SwitchRequest>
+	{
+		let mut per_exit : Vec<Vec<SwitchRequest>> = vec![vec![];self.num_ports];
+		for r in requests
+		{
+			per_exit[r.exit_port].push(r);
+		}
+		per_exit.into_iter().filter(|candidates|!candidates.is_empty()).map(|mut candidates|{
+			let index = rng.borrow_mut().gen_range(0..candidates.len());
+			candidates.swap_remove(index)
+		}).collect()
+	}
+	fn input_exclusive(&self) -> bool
+	{
+		false
+	}
+}
+
+impl RandomAllocator
+{
+	pub fn new(arg:SwitchAllocatorBuilderArgument) -> RandomAllocator
+	{
+		match_object_panic!(arg.cv,"Random",_value,);
+		RandomAllocator{
+			num_ports: arg.num_ports,
+		}
+	}
+}
+
+///Separable allocator with round-robin pointers updated only on first iteration grants.
+#[derive(Debug)]
+pub struct ISLIP
+{
+	num_ports: usize,
+	num_virtual_channels: usize,
+	///Number of iterations to perform each cycle.
+	iterations: usize,
+	///Whether entry ports make their selection before exit ports.
+	input_first: bool,
+	///For each exit port, the round-robin pointer over entry ports.
+	exit_pointer: Vec<usize>,
+	///For each entry port, the round-robin pointer over exit ports. Used when output first.
+	entry_pointer: Vec<usize>,
+	///For each entry port, the round-robin pointer over its virtual channels.
+	entry_virtual_channel_pointer: Vec<usize>,
+}
+
+impl SwitchAllocator for ISLIP
+{
+	fn allocate(&mut self, requests:Vec<SwitchRequest>, _rng:&RefCell<StdRng>) -> Vec<SwitchRequest>
+	{
+		let n=self.num_ports;
+		let nvc=self.num_virtual_channels;
+		let mut entry_matched = vec![false;n];
+		let mut exit_matched = vec![false;n];
+		let mut granted : Vec<SwitchRequest> = vec![];
+		for iteration in 0..self.iterations
+		{
+			let alive : Vec<&SwitchRequest> = requests.iter().filter(|r|!entry_matched[r.entry_port] && !exit_matched[r.exit_port]).collect();
+			if alive.is_empty()
+			{
+				break;
+			}
+			//The pairs accepted in this iteration.
+			let mut accepted : Vec<&SwitchRequest> = vec![];
+			if self.input_first
+			{
+				//Each entry port selects one of its virtual channels.
+				let mut selected : Vec<Option<&SwitchRequest>> = vec![None;n];
+				for entry_port in 0..n
+				{
+					let candidates : Vec<&SwitchRequest> = alive.iter().filter(|r|r.entry_port==entry_port).cloned().collect();
+					if !candidates.is_empty()
+					{
+						let vcs : Vec<usize> = candidates.iter().map(|r|r.entry_virtual_channel).collect();
+						selected[entry_port] = Some(candidates[round_robin_select(&vcs,self.entry_virtual_channel_pointer[entry_port],nvc)]);
+					}
+				}
+				//Each exit port grants one of the selections.
+				for exit_port in 0..n
+				{
+					let candidates : Vec<&SwitchRequest> = selected.iter().filter_map(|s|*s).filter(|r|r.exit_port==exit_port).collect();
+					if !candidates.is_empty()
+					{
+						let entries : Vec<usize> = candidates.iter().map(|r|r.entry_port).collect();
+						accepted.push(candidates[round_robin_select(&entries,self.exit_pointer[exit_port],n)]);
+					}
+				}
+			}
+			else
+			{
+				//Each exit port grants an entry port.
+				let mut grants : Vec<Vec<usize>> = vec![vec![];n];
+				for exit_port in 0..n
+				{
+					let entries : Vec<usize> = alive.iter().filter(|r|r.exit_port==exit_port).map(|r|r.entry_port).collect();
+					if !entries.is_empty()
+					{
+						let entry_port = entries[round_robin_select(&entries,self.exit_pointer[exit_port],n)];
+						grants[entry_port].push(exit_port);
+					}
+				}
+				//Each entry port accepts one grant, and selects one of its virtual channels requesting the accepted exit port.
+				for entry_port in 0..n
+				{
+					if !grants[entry_port].is_empty()
+					{
+						let exit_port = grants[entry_port][round_robin_select(&grants[entry_port],self.entry_pointer[entry_port],n)];
+						let candidates : Vec<&SwitchRequest> = alive.iter().filter(|r|r.entry_port==entry_port && r.exit_port==exit_port).cloned().collect();
+						let vcs : Vec<usize> = candidates.iter().map(|r|r.entry_virtual_channel).collect();
+						accepted.push(candidates[round_robin_select(&vcs,self.entry_virtual_channel_pointer[entry_port],nvc)]);
+					}
+				}
+			}
+			for r in accepted
+			{
+				entry_matched[r.entry_port]=true;
+				exit_matched[r.exit_port]=true;
+				if iteration==0
+				{
+					self.exit_pointer[r.exit_port]=(r.entry_port+1)%n;
+					self.entry_pointer[r.entry_port]=(r.exit_port+1)%n;
+					self.entry_virtual_channel_pointer[r.entry_port]=(r.entry_virtual_channel+1)%nvc;
+				}
+				granted.push(r.clone());
+			}
+		}
+		granted
+	}
+	fn input_exclusive(&self) -> bool
+	{
+		true
+	}
+}
+
+impl ISLIP
+{
+	pub fn new(arg:SwitchAllocatorBuilderArgument) -> ISLIP
+	{
+		let mut iterations=None;
+		let mut input_first=false;
+		match_object_panic!(arg.cv,"ISLIP",value,
+			"iterations" => match value
+			{
+				&ConfigurationValue::Number(f) => iterations=Some(f as usize),
+				_ => panic!("bad value for iterations"),
+			}
+			"input_first" => match value
+			{
+				&ConfigurationValue::True => input_first=true,
+				&ConfigurationValue::False => input_first=false,
+				_ => panic!("bad value for input_first"),
+			}
+		);
+		let iterations=iterations.expect("There were no iterations");
+		if iterations==0
+		{
+			panic!("ISLIP requires at least one iteration");
+		}
+		ISLIP{
+			num_ports: arg.num_ports,
+			num_virtual_channels: arg.num_virtual_channels,
+			iterations,
+			input_first,
+			exit_pointer: vec![0;arg.num_ports],
+			entry_pointer: vec![0;arg.num_ports],
+			entry_virtual_channel_pointer: vec![0;arg.num_ports],
+		}
+	}
+}
+
+///Wavefront allocator, with rotating priority diagonal.
+#[derive(Debug)]
+pub struct Wavefront
+{
+	num_ports: usize,
+	num_virtual_channels: usize,
+	///The diagonal with highest priority in the next allocation.
+	priority_diagonal: usize,
+	///For each entry port, the round-robin pointer over its virtual channels.
+	entry_virtual_channel_pointer: Vec<usize>,
+}
+
+impl SwitchAllocator for Wavefront
+{
+	fn allocate(&mut self, requests:Vec<SwitchRequest>, _rng:&RefCell<StdRng>) -> Vec<SwitchRequest>
+	{
+		let n=self.num_ports;
+		//requested[entry][exit] is the list of requests between those ports.
+		let mut requested : Vec<Vec<Vec<SwitchRequest>>> = vec![vec![vec![];n];n];
+		for r in requests
+		{
+			requested[r.entry_port][r.exit_port].push(r);
+		}
+		let mut entry_matched = vec![false;n];
+		let mut exit_matched = vec![false;n];
+		let mut granted = vec![];
+		for offset in 0..n
+		{
+			let diagonal = (self.priority_diagonal+offset)%n;
+			for entry_port in 0..n
+			{
+				let exit_port = (entry_port+diagonal)%n;
+				let candidates = &requested[entry_port][exit_port];
+				if !entry_matched[entry_port] && !exit_matched[exit_port] && !candidates.is_empty()
+				{
+					let vcs : Vec<usize> = candidates.iter().map(|r|r.entry_virtual_channel).collect();
+					let selected = candidates[round_robin_select(&vcs,self.entry_virtual_channel_pointer[entry_port],self.num_virtual_channels)].clone();
+					self.entry_virtual_channel_pointer[entry_port]=(selected.entry_virtual_channel+1)%self.num_virtual_channels;
+					entry_matched[entry_port]=true;
+					exit_matched[exit_port]=true;
+					granted.push(selected);
+				}
+			}
+		}
+		self.priority_diagonal=(self.priority_diagonal+1)%n;
+		granted
+	}
+	fn input_exclusive(&self) -> bool
+	{
+		true
+	}
+}
+
+impl Wavefront
+{
+	pub fn new(arg:SwitchAllocatorBuilderArgument) -> Wavefront
+	{
+		match_object_panic!(arg.cv,"Wavefront",_value,);
+		Wavefront{
+			num_ports: arg.num_ports,
+			num_virtual_channels: arg.num_virtual_channels,
+			priority_diagonal: 0,
+			entry_virtual_channel_pointer: vec![0;arg.num_ports],
+		}
+	}
+}
+
+///Grants the requests in order of age, oldest first.
+#[derive(Debug)]
+pub struct OldestFirst
+{
+	num_ports: usize,
+}
+
+impl SwitchAllocator for OldestFirst
+{
+	fn allocate(&mut self, mut requests:Vec<SwitchRequest>, rng:&RefCell<StdRng>) -> Vec<SwitchRequest>
+	{
+		//Shuffle before the stable sort to break ties randomly.
+		requests.shuffle(rng.borrow_mut().deref_mut());
+		requests.sort_by(|a,b|b.age.cmp(&a.age));
+		let mut entry_matched = vec![false;self.num_ports];
+		let mut exit_matched = vec![false;self.num_ports];
+		requests.into_iter().filter(|r|{
+			if entry_matched[r.entry_port] || exit_matched[r.exit_port]
+			{
+				false
+			}
+			else
+			{
+				entry_matched[r.entry_port]=true;
+				exit_matched[r.exit_port]=true;
+				true
+			}
+		}).collect()
+	}
+	fn input_exclusive(&self) -> bool
+	{
+		true
+	}
+}
+
+impl OldestFirst
+{
+	pub fn new(arg:SwitchAllocatorBuilderArgument) -> OldestFirst
+	{
+		match_object_panic!(arg.cv,"OldestFirst",_value,);
+		OldestFirst{
+			num_ports: arg.num_ports,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+
+	fn request(entry_port:usize, entry_virtual_channel:usize, exit_port:usize, exit_virtual_channel:usize) -> SwitchRequest
+	{
+		SwitchRequest{
+			entry_port,
+			entry_virtual_channel,
+			exit_port,
+			exit_virtual_channel,
+			age: 0,
+			last_phit: true,
+		}
+	}
+
+	fn allocator(name:&str, attributes:Vec<(String,ConfigurationValue)>, num_ports:usize, num_virtual_channels:usize) -> Box<dyn SwitchAllocator>
+	{
+		let plugs = Plugs::default();
+		let cv = ConfigurationValue::Object(String::from(name),attributes);
+		new_switch_allocator(SwitchAllocatorBuilderArgument{cv:&cv,plugs:&plugs,num_ports,num_virtual_channels})
+	}
+
+	///All the requests between every pair of ports and every virtual channel.
+	fn all_requests(num_ports:usize, num_virtual_channels:usize) -> Vec<SwitchRequest>
+	{
+		let mut requests = vec![];
+		for entry_port in 0..num_ports
+		{
+			for exit_port in 0..num_ports
+			{
+				for vc in 0..num_virtual_channels
+				{
+					requests.push(request(entry_port,vc,exit_port,vc));
+				}
+			}
+		}
+		requests
+	}
+
+	#[test]
+	fn grants_respect_the_ports()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let iterations = vec![(String::from("iterations"),ConfigurationValue::Number(2.0))];
+		let input_first = vec![(String::from("iterations"),ConfigurationValue::Number(1.0)),(String::from("input_first"),ConfigurationValue::True)];
+		for (name,attributes) in vec![("Token",vec![]),("Random",vec![]),("ISLIP",iterations),("ISLIP",input_first),("Wavefront",vec![]),("OldestFirst",vec![])]
+		{
+			let mut allocator = allocator(name,attributes,4,2);
+			for cycle in 0..20
+			{
+				//A different subset of the requests each cycle.
+				let requests : Vec<SwitchRequest> = all_requests(4,2).into_iter().enumerate().filter(|(index,_)|(index+cycle)%3!=0).map(|(_,r)|r).collect();
+				let grants = allocator.allocate(requests.clone(),&rng);
+				assert!(!grants.is_empty(),"{} granted nothing",name);
+				assert!(grants.len()<=maximum_matching_size(&requests,allocator.input_exclusive()),"{} granted more than possible",name);
+				for (index,grant) in grants.iter().enumerate()
+				{
+					assert!(requests.iter().any(|r|r.entry_port==grant.entry_port && r.entry_virtual_channel==grant.entry_virtual_channel && r.exit_port==grant.exit_port && r.exit_virtual_channel==grant.exit_virtual_channel),"{} granted a request not made",name);
+					for other in grants[index+1..].iter()
+					{
+						assert_ne!(grant.exit_port,other.exit_port,"{} granted an exit port twice",name);
+						if allocator.input_exclusive()
+						{
+							assert_ne!(grant.entry_port,other.entry_port,"{} granted an entry port twice",name);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn token_rotates_among_virtual_channels()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let mut allocator = allocator("Token",vec![],2,3);
+		let requests = vec![request(0,0,1,0),request(0,1,1,1),request(0,2,1,2)];
+		let granted : Vec<usize> = (0..6).map(|_|allocator.allocate(requests.clone(),&rng)[0].exit_virtual_channel).collect();
+		assert_eq!(granted,vec![0,1,2,0,1,2]);
+	}
+
+	#[test]
+	fn oldest_first_prefers_age()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let mut allocator = allocator("OldestFirst",vec![],2,2);
+		let mut young = request(0,0,1,0);
+		young.age = 5;
+		let mut old = request(1,0,1,1);
+		old.age = 50;
+		let grants = allocator.allocate(vec![young,old],&rng);
+		assert_eq!(grants.len(),1);
+		assert_eq!(grants[0].entry_port,1);
+	}
+
+	#[test]
+	fn wavefront_finds_full_matchings()
+	{
+		//With every pair of ports requested a wavefront serves a whole diagonal.
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let mut allocator = allocator("Wavefront",vec![],4,1);
+		for _ in 0..4
+		{
+			assert_eq!(allocator.allocate(all_requests(4,1),&rng).len(),4);
+		}
+	}
+
+	#[test]
+	fn maximum_matching()
+	{
+		//Entry 0 can go to exits 0 and 1, entry 1 only to exit 0.
+		let requests = vec![request(0,0,0,0),request(0,0,1,0),request(1,0,0,0)];
+		assert_eq!(maximum_matching_size(&requests,true),2);
+		assert_eq!(maximum_matching_size(&requests,false),2);
+		//Every entry wants the same exit.
+		let requests = vec![request(0,0,2,0),request(1,0,2,0),request(2,0,2,1)];
+		assert_eq!(maximum_matching_size(&requests,true),1);
+		assert_eq!(maximum_matching_size(&[],true),0);
+		let mut cache = MaximumMatchingCache::default();
+		assert_eq!(cache.maximum_matching_size(&all_requests(3,2),true),3);
+		assert_eq!(cache.maximum_matching_size(&all_requests(3,1),true),3);
+		assert_eq!(cache.maximum_matching_size(&requests,true),1);
+	}
+}
@@ -3,8 +3,8 @@ use std::cell::RefCell;
 use std::rc::{Rc,Weak};
 use std::ops::{Deref,DerefMut};
 use std::mem::{size_of};
-use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
-use super::{Router,TransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,TransmissionToServer,TransmissionFromServer,SimpleVirtualChannels,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument};
+use ::rand::{rngs::StdRng,prelude::SliceRandom};
+use super::{Router,TransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,TransmissionToServer,TransmissionFromServer,SimpleVirtualChannels,SharedBufferVirtualChannels,PauseFrameVirtualChannels,LossyVirtualChannels,EcnMarking,new_port_mechanisms,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument};
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Location};
 use crate::routing::CandidateEgress;
@@ -12,19 +12,10 @@ use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_polic
 use crate::event::{Event,Eventful,EventGeneration,CyclePosition};
 use crate::{Phit,Packet,Simulation};
 use crate::quantify::Quantifiable;
+use super::allocator::{SwitchAllocator,SwitchRequest,new_switch_allocator,SwitchAllocatorBuilderArgument,MaximumMatchingCache};
 //use crate::Plugs;
 
 
-///Strategy for the arbitration of the output port.
-enum OutputArbiter
-{
-	#[allow(dead_code)]
-	Random,
-	Token{
-		port_token: Vec<usize>,
-	},
-}
-
 ///The basic Router struct. Very similar to FSIN's router.
 pub struct Basic<TM:TransmissionMechanism>
 {
@@ -44,6 +35,8 @@ pub struct Basic<TM:TransmissionMechanism>
 	flit_size: usize,
 	///Size of each input buffer.
 	buffer_size: usize,
+	///The maximum space that a virtual channel may have in the next router. It is the `buffer_size` unless the space is shared among virtual channels.
+	virtual_channel_capacity: usize,
 	///Give priority to in-transit packets over packets in injection queues.
 	intransit_priority: bool,
 	///To allow to request a port even if some other packet is being transmitted throught it to a different virtual channel (as FSIN does).
@@ -58,8 +51,8 @@ pub struct Basic<TM:TransmissionMechanism>
 	///If 0 then there are no output buffer, if greater than 0 then the size of each of them.
 	output_buffer_size: usize,
 	///The outut buffers indexed as [output_port][output_vc].
-	///Phits are stored with their (entry_port,entry_vc).
-	output_buffers: Vec<Vec<AugmentedBuffer<(usize,usize)>>>,
+	///Phits are stored with their (entry_port,entry_vc,cycle), the cycle being the one in which they finish the switch traversal and may leave the buffer.
+	output_buffers: Vec<Vec<AugmentedBuffer<(usize,usize,usize)>>>,
 	///If not None then the input port+virtual_channel which is either sending by this port+virtual_channel or writing to this output buffer.
 	///We keep the packet for debugging/check considerations.
 	selected_input: Vec<Vec<Option<(Rc<Packet>,usize,usize)>>>,
@@ -68,10 +61,32 @@ pub struct Basic<TM:TransmissionMechanism>
 	selected_output: Vec<Vec<Option<(Rc<Packet>,usize,usize)>>>,
 	///Number of cycles that the current phit, if any, in the head of a given (port,virtual channel) input buffer the phit has been waiting.
 	time_at_input_head: Vec<Vec<usize>>,
-	///And arbiter of the physical output port.
-	output_arbiter: OutputArbiter,
+	///Number of cycles that the leading phit, if any, in the head of a given (port,virtual channel) input buffer has spent in the routing and allocation stages.
+	head_pipeline_cycles: Vec<Vec<usize>>,
+	///The allocator deciding which phits advance through the physical ports. Without output buffers it is the configured `switch_allocator`, allocating the whole crossbar.
+	///With output buffers it is a `Token` arbiter selecting in each port one of its output buffers.
+	switch_allocator: Box<dyn SwitchAllocator>,
+	///With output buffers, the configured `switch_allocator`, deciding which phits move from the input buffers into the output buffers.
+	///When it is None every selected input advances, limited only by `output_speedup`.
+	crossbar_allocator: Option<Box<dyn SwitchAllocator>>,
+	///The last maximum matching of the requests to the configured allocator, used to measure its efficiency.
+	matching_cache: MaximumMatchingCache,
 	///The maximum packet size that is allowed. Only for bubble consideration, that reserves space for a given packet plus maximum packet size.
 	maximum_packet_size: usize,
+	///Cycles of the routing computation stage. Only paid by the leading phit of each packet.
+	routing_delay: usize,
+	///Cycles of the virtual channel allocation stage. Only paid by the leading phit of each packet.
+	virtual_channel_allocation_delay: usize,
+	///Cycles of the switch allocation stage. Only paid by the leading phit of each packet.
+	switch_allocation_delay: usize,
+	///Cycles of the switch traversal stage. Paid by every phit, between the crossbar and the output buffer when there are output buffers, or before the link otherwise.
+	switch_traversal_delay: usize,
+	///How many times faster than the links the crossbar works. Each connection through the crossbar may move this many phits into its output buffer each cycle.
+	crossbar_speedup: usize,
+	///If not None, the maximum number of phits that each output port may receive from the crossbar each cycle.
+	output_speedup: Option<usize>,
+	///If not None, the packets leaving through congested ports towards other routers are marked for the congestion control of the servers.
+	ecn_marking: Option<EcnMarking>,
 
 	//statistics:
 	statistics_temporal_step: usize,
@@ -88,6 +103,14 @@ pub struct BasicRouterMeasurement
 	pub output_buffer_occupation_per_vc: Vec<f64>,
 	///Accumulated over time, averaged per port.
 	pub reception_space_occupation_per_vc: Vec<f64>,
+	///Accumulated over time. Number of requests granted by the switch allocator.
+	pub switch_allocation_grants: f64,
+	///Accumulated over time. Size of the maximum matching of the requests to the switch allocator.
+	pub switch_allocation_maximum_matching: f64,
+	///Accumulated over time. Number of phits that have traversed the crossbar.
+	pub crossbar_phits: f64,
+	///Accumulated over time. Number of pause messages sent to the previous routers by the on/off flow control.
+	pub pause_frames: f64,
 }
 
 impl BasicRouterMeasurement
@@ -99,6 +122,10 @@ impl BasicRouterMeasurement
 			begin_cycle: 0,
 			output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
 			reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+			switch_allocation_grants: 0f64,
+			switch_allocation_maximum_matching: 0f64,
+			crossbar_phits: 0f64,
+			pause_frames: 0f64,
 		}
 	}
 	pub fn into_mul(mut self, factor:f64 ) -> BasicRouterMeasurement
@@ -116,6 +143,10 @@ impl BasicRouterMeasurement
 		{
 			*elem *= factor;
 		}
+		self.switch_allocation_grants *= factor;
+		self.switch_allocation_maximum_matching *= factor;
+		self.crossbar_phits *= factor;
+		self.pause_frames *= factor;
 		self
 	}
 }
@@ -155,7 +186,7 @@ impl<TM:'static+TransmissionMechanism> Router for Basic<TM>
 	}
 	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
 	{
-		Some(self.buffer_size)
+		Some(self.virtual_channel_capacity)
 	}
 	fn get_index(&self)->Option<usize>
 	{
@@ -172,6 +203,13 @@ impl<TM:'static+TransmissionMechanism> Router for Basic<TM>
 		let cycle_span = cycle - self.principal_measurement.begin_cycle;
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
+		let mut switch_allocation_grants = self.principal_measurement.switch_allocation_grants/cycle_span as f64;
+		let mut switch_allocation_maximum_matching = self.principal_measurement.switch_allocation_maximum_matching/cycle_span as f64;
+		let mut crossbar_phits = self.principal_measurement.crossbar_phits/cycle_span as f64;
+		let mut pause_frames = self.principal_measurement.pause_frames/cycle_span as f64;
+		//The crossbar can deliver `crossbar_bandwidth` phits to each output port each cycle.
+		let crossbar_bandwidth = self.output_speedup.unwrap_or(self.crossbar_speedup);
+		let mut crossbar_utilization = crossbar_phits / (crossbar_bandwidth*self.transmission_port_status.len()) as f64;
 		let mut temporal_statistics: Vec<  BasicRouterMeasurement  > = self.temporal_statistics.iter().map(|m|m.clone().into_mul(1f64/self.statistics_temporal_step as f64)).collect();
 		if let Some(previous)=statistics
 		{
@@ -235,6 +273,33 @@ impl<TM:'static+TransmissionMechanism> Router for Basic<TM>
 							}
 							_ => panic!("bad value for average_reception_space_occupation_per_vc"),
 						},
+						"average_switch_allocation_grants" => match value
+						{
+							&ConfigurationValue::Number(x) => switch_allocation_grants += x,
+							_ => panic!("bad value for average_switch_allocation_grants"),
+						},
+						"average_switch_allocation_maximum_matching" => match value
+						{
+							&ConfigurationValue::Number(x) => switch_allocation_maximum_matching += x,
+							_ => panic!("bad value for average_switch_allocation_maximum_matching"),
+						},
+						//Computed at the last router from the other two values.
+						"switch_allocation_efficiency" => (),
+						"average_crossbar_phits" => match value
+						{
+							&ConfigurationValue::Number(x) => crossbar_phits += x,
+							_ => panic!("bad value for average_crossbar_phits"),
+						},
+						"crossbar_utilization" => match value
+						{
+							&ConfigurationValue::Number(x) => crossbar_utilization += x,
+							_ => panic!("bad value for crossbar_utilization"),
+						},
+						"average_pause_frames" => match value
+						{
+							&ConfigurationValue::Number(x) => pause_frames += x,
+							_ => panic!("bad value for average_pause_frames"),
+						},
 						"temporal_statistics" => match value
 						{
 							&ConfigurationValue::Object(_, ref prev_t_pairs) =>
@@ -335,6 +400,23 @@ impl<TM:'static+TransmissionMechanism> Router for Basic<TM>
 			}
 			result_content.push((String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(content.iter().map(|x|ConfigurationValue::Number(*x)).collect())));
 		}
+		if is_last
+		{
+			//The ratio of granted requests to the best possible matching. The averaging factor cancels out.
+			let switch_allocation_efficiency = if switch_allocation_maximum_matching>0f64 { switch_allocation_grants/switch_allocation_maximum_matching } else { 1f64 };
+			let factor=1f64 / total_routers as f64;
+			switch_allocation_grants *= factor;
+			switch_allocation_maximum_matching *= factor;
+			crossbar_phits *= factor;
+			crossbar_utilization *= factor;
+			pause_frames *= factor;
+			result_content.push((String::from("switch_allocation_efficiency"),ConfigurationValue::Number(switch_allocation_efficiency)));
+		}
+		result_content.push((String::from("average_switch_allocation_grants"),ConfigurationValue::Number(switch_allocation_grants)));
+		result_content.push((String::from("average_switch_allocation_maximum_matching"),ConfigurationValue::Number(switch_allocation_maximum_matching)));
+		result_content.push((String::from("average_crossbar_phits"),ConfigurationValue::Number(crossbar_phits)));
+		result_content.push((String::from("crossbar_utilization"),ConfigurationValue::Number(crossbar_utilization)));
+		result_content.push((String::from("average_pause_frames"),ConfigurationValue::Number(pause_frames)));
 		if !temporal_statistics.is_empty()
 		{
 			if is_last
@@ -364,6 +446,10 @@ impl<TM:'static+TransmissionMechanism> Router for Basic<TM>
 		{
 			*x=0f64;
 		}
+		self.principal_measurement.switch_allocation_grants=0f64;
+		self.principal_measurement.switch_allocation_maximum_matching=0f64;
+		self.principal_measurement.crossbar_phits=0f64;
+		self.principal_measurement.pause_frames=0f64;
 	}
 }
 
@@ -378,6 +464,7 @@ impl Basic<SimpleVirtualChannels>
 			plugs,
 			topology,
 			maximum_packet_size,
+			link_classes,
 			statistics_temporal_step,
 		} = arg;
 		//let mut servers=None;
@@ -392,6 +479,15 @@ impl Basic<SimpleVirtualChannels>
 		let mut allow_request_busy_port=None;
 		let mut output_priorize_lowest_label=None;
 		let mut output_buffer_size=None;
+		let mut routing_delay=0;
+		let mut virtual_channel_allocation_delay=0;
+		let mut switch_allocation_delay=0;
+		let mut switch_traversal_delay=0;
+		let mut crossbar_speedup=1;
+		let mut output_speedup=None;
+		let mut switch_allocator=None;
+		let mut ecn_marking=None;
+		let mut transmission_mechanism=None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="Basic"
@@ -419,7 +515,40 @@ impl Basic<SimpleVirtualChannels>
 						})).collect()),
 						_ => panic!("bad value for permute"),
 					}
-					"delay" => (),//FIXME: yet undecided if/how to implement this.
+					"delay" => (),//Deprecated. Use the delays of the pipeline stages instead.
+					"routing_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => routing_delay=f as usize,
+						_ => panic!("bad value for routing_delay"),
+					},
+					"virtual_channel_allocation_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => virtual_channel_allocation_delay=f as usize,
+						_ => panic!("bad value for virtual_channel_allocation_delay"),
+					},
+					"switch_allocation_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => switch_allocation_delay=f as usize,
+						_ => panic!("bad value for switch_allocation_delay"),
+					},
+					"switch_traversal_delay" => match value
+					{
+						&ConfigurationValue::Number(f) => switch_traversal_delay=f as usize,
+						_ => panic!("bad value for switch_traversal_delay"),
+					},
+					"crossbar_speedup" => match value
+					{
+						&ConfigurationValue::Number(f) => crossbar_speedup=f as usize,
+						_ => panic!("bad value for crossbar_speedup"),
+					},
+					"output_speedup" => match value
+					{
+						&ConfigurationValue::Number(f) => output_speedup=Some(f as usize),
+						_ => panic!("bad value for output_speedup"),
+					},
+					"switch_allocator" => switch_allocator=Some(value),
+					"ecn_marking" => ecn_marking=Some(EcnMarking::new(value)),
+					"transmission_mechanism" => transmission_mechanism=Some(value),
 					"buffer_size" => match value
 					{
 						&ConfigurationValue::Number(f) => buffer_size=Some(f as usize),
@@ -478,7 +607,32 @@ impl Basic<SimpleVirtualChannels>
 		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
 		let allow_request_busy_port=allow_request_busy_port.expect("There were no allow_request_busy_port");
 		let output_priorize_lowest_label=output_priorize_lowest_label.expect("There were no output_priorize_lowest_label");
+		if output_buffer_size==0 && (crossbar_speedup!=1 || output_speedup.is_some())
+		{
+			panic!("The crossbar_speedup and output_speedup of the Basic router require output buffers.");
+		}
+		if crossbar_speedup==0 || output_speedup==Some(0)
+		{
+			panic!("The crossbar_speedup and output_speedup of the Basic router must be positive.");
+		}
 		let input_ports=topology.ports(router_index);
+		//By default use the token arbitration of previous versions.
+		let default_switch_allocator=ConfigurationValue::Object(String::from("Token"),vec![]);
+		let build_allocator = |cv| new_switch_allocator(SwitchAllocatorBuilderArgument{
+			cv,
+			plugs,
+			num_ports:input_ports,
+			num_virtual_channels:virtual_channels,
+		});
+		//With output buffers the configured allocator acts between the input and output buffers, and the links keep the token arbitration.
+		let (switch_allocator,crossbar_allocator) = if output_buffer_size>0
+		{
+			(build_allocator(&default_switch_allocator),switch_allocator.map(build_allocator))
+		}
+		else
+		{
+			(build_allocator(switch_allocator.unwrap_or(&default_switch_allocator)),None)
+		};
 		let selected_input=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|None).collect()
 		).collect();
@@ -488,31 +642,108 @@ impl Basic<SimpleVirtualChannels>
 		let time_at_input_head=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|0).collect()
 		).collect();
-		let transmission_mechanism = SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
+		let head_pipeline_cycles=(0..input_ports).map(|_|
+			(0..virtual_channels).map(|_|0).collect()
+		).collect();
 		let to_server_mechanism = TransmissionToServer();
 		let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
-		let transmission_port_status:Vec<Box<dyn StatusAtEmissor>> = (0..input_ports).map(|p|
-			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
-			{
-				let b:Box<dyn StatusAtEmissor> = Box::new(to_server_mechanism.new_status_at_emissor());
-				b
-			}
-			else
-			{
-				Box::new(transmission_mechanism.new_status_at_emissor())
-			}
-		).collect();
-		let reception_port_space = (0..input_ports).map(|p|
-			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
-			{
-				let b:Box<dyn SpaceAtReceptor> = Box::new(from_server_mechanism.new_space_at_receptor());
-				b
-			}
-			else
+		//The transmission mechanism is used in the ports towards other routers. Each port gets virtual_channels*buffer_size phits of space in any case.
+		let default_transmission_mechanism=ConfigurationValue::Object(String::from("SimpleVirtualChannels"),vec![]);
+		let (transmission_port_status,reception_port_space,virtual_channel_capacity) = match transmission_mechanism.unwrap_or(&default_transmission_mechanism)
+		{
+			&ConfigurationValue::Object(ref cv_name, ref cv_pairs) => match cv_name.as_ref()
 			{
-				Box::new(transmission_mechanism.new_space_at_receptor())
-			}
-		).collect();
+				"SimpleVirtualChannels" =>
+				{
+					if let Some((name,_)) = cv_pairs.first()
+					{
+						panic!("Nothing to do with field {} in SimpleVirtualChannels",name);
+					}
+					let transmission_mechanism = SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
+					let (status,space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+					(status,space,buffer_size)
+				},
+				"SharedBuffer" =>
+				{
+					let mut reserved_size=None;
+					for &(ref name,ref value) in cv_pairs
+					{
+						match name.as_ref()
+						{
+							"reserved_size" => match value
+							{
+								&ConfigurationValue::Number(f) => reserved_size=Some(f as usize),
+								_ => panic!("bad value for reserved_size"),
+							},
+							_ => panic!("Nothing to do with field {} in SharedBuffer",name),
+						}
+					}
+					let reserved_size=reserved_size.expect("There were no reserved_size");
+					let transmission_mechanism = SharedBufferVirtualChannels::new(virtual_channels,buffer_size,reserved_size,flit_size);
+					let (status,space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+					(status,space,transmission_mechanism.maximum_space_per_virtual_channel())
+				},
+				"PauseFrames" =>
+				{
+					let mut headroom=None;
+					let mut xon_threshold=None;
+					let mut reaction_delay=0;
+					for &(ref name,ref value) in cv_pairs
+					{
+						match name.as_ref()
+						{
+							"headroom" => match value
+							{
+								&ConfigurationValue::Number(f) => headroom=Some(f as usize),
+								_ => panic!("bad value for headroom"),
+							},
+							"xon_threshold" => match value
+							{
+								&ConfigurationValue::Number(f) => xon_threshold=Some(f as usize),
+								_ => panic!("bad value for xon_threshold"),
+							},
+							"reaction_delay" => match value
+							{
+								&ConfigurationValue::Number(f) => reaction_delay=f as usize,
+								_ => panic!("bad value for reaction_delay"),
+							},
+							_ => panic!("Nothing to do with field {} in PauseFrames",name),
+						}
+					}
+					let headroom=headroom.expect("There were no headroom");
+					let xon_threshold=xon_threshold.expect("There were no xon_threshold");
+					//The headroom must absorb what the emissor sends during the round trip of the pause, plus the rest of a packet in course.
+					for port in 0..topology.ports(router_index)
+					{
+						if let (Location::RouterPort{..},link_class)=topology.neighbour(router_index,port)
+						{
+							let link=&link_classes[link_class];
+							let round_trip = 2*link.delay + reaction_delay + switch_traversal_delay + 1;
+							let required = (round_trip as f64*link.capacity()).ceil() as usize + maximum_packet_size;
+							if headroom<required
+							{
+								panic!("The headroom ({}) of PauseFrames cannot absorb the phits in flight through the port {} of router {}. It requires at least {}: {} cycles of round trip at {} phits per cycle plus a packet of {} phits.",headroom,port,router_index,required,round_trip,link.capacity(),maximum_packet_size);
+							}
+						}
+					}
+					let transmission_mechanism = PauseFrameVirtualChannels::new(virtual_channels,buffer_size,headroom,xon_threshold,reaction_delay);
+					let (status,space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+					(status,space,buffer_size)
+				},
+				"Lossy" =>
+				{
+					if let Some((name,_)) = cv_pairs.first()
+					{
+						panic!("Nothing to do with field {} in Lossy",name);
+					}
+					let transmission_mechanism = LossyVirtualChannels::new(virtual_channels,buffer_size);
+					let (status,space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+					(status,space,buffer_size)
+				},
+				_ => panic!("Unknown transmission_mechanism {}",cv_name),
+			},
+			_ => panic!("bad value for transmission_mechanism"),
+		};
 		let output_buffers= if output_buffer_size==0 {vec![]} else{
 			(0..input_ports).map(|_|
 				(0..virtual_channels).map(|_|AugmentedBuffer::new()).collect()
@@ -531,6 +762,7 @@ impl Basic<SimpleVirtualChannels>
 			allow_request_busy_port,
 			output_priorize_lowest_label,
 			buffer_size,
+			virtual_channel_capacity,
 			transmission_port_status,
 			reception_port_space,
 			output_buffer_size,
@@ -538,8 +770,18 @@ impl Basic<SimpleVirtualChannels>
 			selected_input,
 			selected_output,
 			time_at_input_head,
-			output_arbiter: OutputArbiter::Token{port_token: vec![0;input_ports]},
+			head_pipeline_cycles,
+			switch_allocator,
+			crossbar_allocator,
+			matching_cache: MaximumMatchingCache::default(),
 			maximum_packet_size,
+			routing_delay,
+			virtual_channel_allocation_delay,
+			switch_allocation_delay,
+			switch_traversal_delay,
+			crossbar_speedup,
+			output_speedup,
+			ecn_marking,
 			//statistics_begin_cycle: 0,
 			//statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
 			//statistics_reception_space_occupation_per_vc: vec![0f64;virtual_channels],
@@ -600,6 +842,12 @@ impl<TM:'static+TransmissionMechanism> Basic<TM>
 			available_internal_space >= necessary_credits
 		}
 	}
+	///Number of cycles that the leading phit of a packet must be at the head of its input buffer before making requests.
+	///It is the sum of the routing computation, virtual channel allocation and switch allocation stages.
+	fn head_pipeline_delay(&self) -> usize
+	{
+		self.routing_delay + self.virtual_channel_allocation_delay + self.switch_allocation_delay
+	}
 	fn get_current_temporal_measurement(&mut self, cycle:usize) -> Option<usize>
 	{
 		if self.statistics_temporal_step>0
@@ -663,6 +911,19 @@ struct PortRequest
 	label: i32,
 }
 
+///Builds the request to the switch allocator for the `phit` at the given entry to go through the given exit.
+fn switch_request(phit:&Phit, entry_port:usize, entry_virtual_channel:usize, exit_port:usize, exit_virtual_channel:usize, cycle:usize) -> SwitchRequest
+{
+	SwitchRequest{
+		entry_port,
+		entry_virtual_channel,
+		exit_port,
+		exit_virtual_channel,
+		age: cycle - *phit.packet.cycle_into_network.borrow(),
+		last_phit: phit.is_end(),
+	}
+}
+
 impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 {
 	///main routine of the router. Do all things that must be done in a cycle, if any.
@@ -746,7 +1007,7 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 				let total=(0..amount_virtual_channels).map(|vc|{
 					//self.buffer_size-p.known_available_space_for_virtual_channel(vc).expect("needs to know available space")
 					let available = p.known_available_space_for_virtual_channel(vc).expect("needs to know available space");
-					if available>self.buffer_size
+					if available>self.virtual_channel_capacity
 					{
 						//panic!("We should never have more available space than the buffer size.");
 						//Actually when the neighbour is a server it may have longer queue.
@@ -754,7 +1015,7 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 					}
 					else
 					{
-						self.buffer_size - available
+						self.virtual_channel_capacity - available
 					}
 				}).sum::<usize>();
 				(total as f32) / (amount_virtual_channels as f32)
@@ -829,6 +1090,13 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 					None =>
 					{
 						undecided_channels+=1;
+						if phit.is_begin() && self.head_pipeline_cycles[entry_port][entry_vc] < self.head_pipeline_delay()
+						{
+							//The leading phit is still in the routing computation or allocation stages.
+							self.head_pipeline_cycles[entry_port][entry_vc]+=1;
+							self.time_at_input_head[entry_port][entry_vc]+=1;
+							continue;
+						}
 						let target_server=phit.packet.message.destination;
 						let (target_location,_link_class)=topology.server_neighbour(target_server);
 						let target_router=match target_location
@@ -856,7 +1124,7 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 								Some(_) => None,
 								None =>
 								{
-									let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,f_port);
+									let bubble_in_use= self.bubble && phit.is_begin() && simulation.routing.is_direction_change(simulation.network.topology.as_ref(),self.router_index,entry_port,f_port);
 									//if self.transmission_port_status[f_port].can_transmit(&phit,f_virtual_channel,transmit_auxiliar_info)
 									let allowed = if self.can_phit_advance(&phit,f_port,f_virtual_channel,bubble_in_use)
 									{
@@ -1020,197 +1288,321 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 			};
 		}
 
-		//-- For each output port decide which input actually use it this cycle.
 		let mut events=vec![];
-		for exit_port in 0..self.transmission_port_status.len()
+		let num_ports=self.transmission_port_status.len();
+		//-- Move phits through the crossbar into the internal output space.
+		//The crossbar performs `crossbar_speedup` steps each cycle, moving a phit through each connection in each step, but no more than `output_speedup` into each output port.
+		//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
+		if self.output_buffer_size>0
 		{
-			let nvc=amount_virtual_channels;
-			//Gather the list of all vc that can advance
-			let mut cand=Vec::with_capacity(nvc);
-			let mut cand_in_transit=false;
-			let mut undo_selected_input=Vec::with_capacity(nvc);
-			for exit_vc in 0..nvc
+			let mut received_per_port=vec![0;num_ports];
+			for step in 0..self.crossbar_speedup
 			{
-				if let Some((ref entry_packet,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
+				//When there is a configured allocator, it decides which of the connections able to advance actually do.
+				let granted:Option<Vec<(usize,usize)>> = if self.crossbar_allocator.is_some()
 				{
-					if self.output_buffer_size>0
+					let mut crossbar_requests=vec![];
+					for exit_port in 0..num_ports
 					{
-						//-- Move phits into the internal output space
-						//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
-						if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+						if let Some(limit)=self.output_speedup
 						{
-							if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+							if received_per_port[exit_port]>=limit
 							{
-								panic!("Trying to move into a full output buffer.");
+								continue;
 							}
-							moved_phits+=1;
-							self.time_at_input_head[entry_port][entry_vc]=0;
-							*phit.virtual_channel.borrow_mut()=Some(exit_vc);
-							if let Some(message)=ack_message
-							{
-								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
-								events.push(EventGeneration{
-									delay: simulation.link_classes[previous_link_class].delay,
-									position:CyclePosition::Begin,
-									//event:Event::Acknowledge{location:previous_location,message:AcknowledgeMessage::ack_phit_clear_from_virtual_channel(entry_vc)},
-									event:Event::Acknowledge{location:previous_location,message},
-								});
-							}
-							if let Some((ref s_exit_packet,s_exit_port,s_exit_vc))=self.selected_output[entry_port][entry_vc]
-							{
-								let entry_packet_ptr = entry_packet.as_ref() as *const Packet;
-								let s_exit_packet_ptr = s_exit_packet.as_ref() as *const Packet;
-								if s_exit_packet_ptr!=entry_packet_ptr || s_exit_port!=exit_port || s_exit_vc!=exit_vc
-								{
-									panic!("Mismatch between selected input and selected output: selected_input[{}][{}]=({:?},{},{}) selected_output[{}][{}]=({:?},{},{}).",exit_port,exit_vc,entry_packet_ptr,entry_port,entry_vc,  entry_port,entry_vc,s_exit_packet_ptr,s_exit_port,s_exit_vc);
-								}
-							}
-							if phit.is_end()
-							{
-								self.selected_input[exit_port][exit_vc]=None;
-								self.selected_output[entry_port][entry_vc]=None;
-							}
-							else
-							{
-								self.selected_output[entry_port][entry_vc]=Some((entry_packet.clone(),exit_port,exit_vc));
-							}
-							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
 						}
-						else
+						for exit_vc in 0..amount_virtual_channels
 						{
-							if self.flit_size>1
+							if let Some((_,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 							{
-								//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
-								println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
+								if self.output_buffers[exit_port][exit_vc].len()<self.output_buffer_size
+								{
+									if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+									{
+										crossbar_requests.push(switch_request(&phit,entry_port,entry_vc,exit_port,exit_vc,simulation.cycle));
+									}
+								}
 							}
 						}
 					}
-					else if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+					let allocator=self.crossbar_allocator.as_mut().unwrap();
+					let maximum_matching = self.matching_cache.maximum_matching_size(&crossbar_requests,allocator.input_exclusive());
+					let grants = allocator.allocate(crossbar_requests,&simulation.rng);
+					self.principal_measurement.switch_allocation_grants += grants.len() as f64;
+					self.principal_measurement.switch_allocation_maximum_matching += maximum_matching as f64;
+					Some(grants.into_iter().map(|grant|(grant.exit_port,grant.exit_virtual_channel)).collect())
+				}
+				else
+				{
+					None
+				};
+				for exit_port in 0..num_ports
+				{
+					//Rotate the first virtual channel considered, so that none is favoured when the output port is limited.
+					let offset = if self.output_speedup.is_some() { (simulation.cycle+step)%amount_virtual_channels } else { 0 };
+					for exit_vc in (0..amount_virtual_channels).map(|vc|(vc+offset)%amount_virtual_channels)
 					{
-						if phit.is_begin()
+						let port_exhausted = match self.output_speedup
 						{
-							undo_selected_input.push(exit_vc);
-						}
-						let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
-						//if self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc,transmit_auxiliar_info)
-						if self.can_phit_advance(&phit,exit_port,exit_vc,bubble_in_use)
+							Some(limit) => received_per_port[exit_port]>=limit,
+							None => false,
+						};
+						if let Some((ref entry_packet,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 						{
-							//cand.push(exit_vc);
-							if cand_in_transit
+							if port_exhausted
 							{
-								if !phit.is_begin()
+								if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
 								{
-									cand.push(exit_vc);
+									if phit.is_begin()
+									{
+										//Packets that have not started to move can change their decision at the next cycle
+										self.selected_input[exit_port][exit_vc]=None;
+									}
 								}
+								continue;
 							}
-							else
+							if let Some(ref granted)=granted
 							{
-								if phit.is_begin()
+								if !granted.contains(&(exit_port,exit_vc))
 								{
-									cand.push(exit_vc);
+									if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+									{
+										if phit.is_begin()
+										{
+											//Packets that have not started to move can change their decision at the next cycle
+											self.selected_input[exit_port][exit_vc]=None;
+										}
+									}
+									continue;
+								}
+							}
+							if step>0 && self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+							{
+								//The space for the leading phit is checked when requesting. Later steps just stop at a full buffer.
+								continue;
+							}
+							if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+							{
+								if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+								{
+									panic!("Trying to move into a full output buffer.");
+								}
+								moved_phits+=1;
+								received_per_port[exit_port]+=1;
+								self.principal_measurement.crossbar_phits+=1f64;
+								self.time_at_input_head[entry_port][entry_vc]=0;
+								self.head_pipeline_cycles[entry_port][entry_vc]=0;
+								*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+								if let Some(message)=ack_message
+								{
+									let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+									events.push(EventGeneration{
+										delay: simulation.link_classes[previous_link_class].delay,
+										position:CyclePosition::Begin,
+										//event:Event::Acknowledge{location:previous_location,message:AcknowledgeMessage::ack_phit_clear_from_virtual_channel(entry_vc)},
+										event:Event::Acknowledge{location:previous_location,message},
+									});
+								}
+								if let Some((ref s_exit_packet,s_exit_port,s_exit_vc))=self.selected_output[entry_port][entry_vc]
+								{
+									let entry_packet_ptr = entry_packet.as_ref() as *const Packet;
+									let s_exit_packet_ptr = s_exit_packet.as_ref() as *const Packet;
+									if s_exit_packet_ptr!=entry_packet_ptr || s_exit_port!=exit_port || s_exit_vc!=exit_vc
+									{
+										panic!("Mismatch between selected input and selected output: selected_input[{}][{}]=({:?},{},{}) selected_output[{}][{}]=({:?},{},{}).",exit_port,exit_vc,entry_packet_ptr,entry_port,entry_vc,  entry_port,entry_vc,s_exit_packet_ptr,s_exit_port,s_exit_vc);
+									}
+								}
+								if phit.is_end()
+								{
+									self.selected_input[exit_port][exit_vc]=None;
+									self.selected_output[entry_port][entry_vc]=None;
 								}
 								else
 								{
-									cand=vec![exit_vc];
-									cand_in_transit=true;
+									self.selected_output[entry_port][entry_vc]=Some((entry_packet.clone(),exit_port,exit_vc));
+								}
+								self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc,simulation.cycle+self.switch_traversal_delay));
+							}
+							else
+							{
+								if step==0 && self.flit_size>1
+								{
+									//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
+									println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
 								}
 							}
 						}
 					}
 				}
-				if self.output_buffer_size>0
+			}
+		}
+
+		//-- For each output port gather the virtual channels that could use it this cycle.
+		//Links faster than a phit per cycle require several rounds of allocation.
+		//The number of phits that each link can carry this cycle. Each port is considered in that many rounds.
+		let link_phits:Vec<usize> = (0..num_ports).map(|port|{
+			let (_location,link_class)=simulation.network.topology.neighbour(self.router_index,port);
+			simulation.link_classes[link_class].phits_at_cycle(simulation.cycle)
+		}).collect();
+		//The number of phits that each port may still send this cycle.
+		let mut port_budget=link_phits.clone();
+		let mut undo_selected_input_per_port=vec![vec![];num_ports];
+		let mut granted_per_port=vec![vec![];num_ports];
+		let mut waiting_link=false;
+		let mut round=0;
+		loop
+		{
+			let mut switch_requests=vec![];
+			for exit_port in 0..num_ports
+			{
+				if round>0 && (port_budget[exit_port]==0 || link_phits[exit_port]<=round)
+				{
+					continue;
+				}
+				let nvc=amount_virtual_channels;
+				//Gather the list of all vc that can advance
+				let mut cand=Vec::with_capacity(nvc);
+				let mut cand_in_transit=false;
+				let mut undo_selected_input=Vec::with_capacity(nvc);
+				for exit_vc in 0..nvc
 				{
-					//Candidates when using output ports.
-					if let Some( (phit,(entry_port,_entry_vc))) = self.output_buffers[exit_port][exit_vc].front()
+					if self.output_buffer_size>0
 					{
-						let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
-						let status=&self.transmission_port_status[exit_port];
-						let can_transmit = if bubble_in_use
+						//The phits have already been moved through the crossbar into the output buffers.
+					}
+					else if let Some((_,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
+					{
+						if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
 						{
-							//self.transmission_port_status[exit_port].can_transmit_whole_packet(&phit,exit_vc)
-							if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
+							if phit.is_begin()
 							{
-								status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + self.maximum_packet_size
+								undo_selected_input.push(exit_vc);
 							}
-							else
+							let bubble_in_use= self.bubble && phit.is_begin() && simulation.routing.is_direction_change(simulation.network.topology.as_ref(),self.router_index,entry_port,exit_port);
+							//if self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc,transmit_auxiliar_info)
+							if self.can_phit_advance(&phit,exit_port,exit_vc,bubble_in_use)
 							{
-								panic!("Basic router requires knowledge of available space to apply bubble.");
+								//cand.push(exit_vc);
+								if cand_in_transit
+								{
+									if !phit.is_begin()
+									{
+										cand.push(switch_request(&phit,entry_port,entry_vc,exit_port,exit_vc,simulation.cycle));
+									}
+								}
+								else
+								{
+									if phit.is_begin()
+									{
+										cand.push(switch_request(&phit,entry_port,entry_vc,exit_port,exit_vc,simulation.cycle));
+									}
+									else
+									{
+										cand=vec![switch_request(&phit,entry_port,entry_vc,exit_port,exit_vc,simulation.cycle)];
+										cand_in_transit=true;
+									}
+								}
 							}
 						}
-						else
+					}
+					if self.output_buffer_size>0
+					{
+						//Candidates when using output ports.
+						let front = self.output_buffers[exit_port][exit_vc].front();
+						if let Some((_,(_,_,ready_cycle))) = front
 						{
-							status.can_transmit(&phit,exit_vc)
-						};
-						if can_transmit
+							if ready_cycle>simulation.cycle
+							{
+								//The phit is still traversing the switch.
+								waiting_link=true;
+							}
+						}
+						if let Some( (phit,(entry_port,_entry_vc,_ready_cycle))) = front.filter(|&(_,(_,_,ready_cycle))|ready_cycle<=simulation.cycle)
 						{
-							if cand_in_transit
+							let bubble_in_use= self.bubble && phit.is_begin() && simulation.routing.is_direction_change(simulation.network.topology.as_ref(),self.router_index,entry_port,exit_port);
+							let status=&self.transmission_port_status[exit_port];
+							let can_transmit = if bubble_in_use
 							{
-								if !phit.is_begin()
+								//self.transmission_port_status[exit_port].can_transmit_whole_packet(&phit,exit_vc)
+								if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
 								{
-									cand.push(exit_vc);
+									status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + self.maximum_packet_size
+								}
+								else
+								{
+									panic!("Basic router requires knowledge of available space to apply bubble.");
 								}
 							}
 							else
 							{
-								if phit.is_begin()
+								status.can_transmit(&phit,exit_vc)
+							};
+							if can_transmit
+							{
+								if cand_in_transit
 								{
-									cand.push(exit_vc);
+									if !phit.is_begin()
+									{
+										cand.push(switch_request(&phit,exit_port,exit_vc,exit_port,exit_vc,simulation.cycle));
+									}
 								}
 								else
 								{
-									cand=vec![exit_vc];
-									cand_in_transit=true;
+									if phit.is_begin()
+									{
+										cand.push(switch_request(&phit,exit_port,exit_vc,exit_port,exit_vc,simulation.cycle));
+									}
+									else
+									{
+										cand=vec![switch_request(&phit,exit_port,exit_vc,exit_port,exit_vc,simulation.cycle)];
+										cand_in_transit=true;
+									}
 								}
 							}
-						}
-						else
-						{
-							if 0<phit.index && phit.index<self.flit_size
+							else
 							{
-								panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
+								if 0<phit.index && phit.index<self.flit_size
+								{
+									panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
+								}
 							}
 						}
 					}
 				}
+				if port_budget[exit_port]>0
+				{
+					switch_requests.extend(cand);
+				}
+				else if !cand.is_empty()
+				{
+					//The link is not available this cycle, but it will be in a later one.
+					waiting_link=true;
+				}
+				undo_selected_input_per_port[exit_port].extend(undo_selected_input);
 			}
-			//for selected_virtual_channel in 0..nvc
-			let selected_virtual_channel = if cand.len()>0
+			//-- Let the allocator decide which virtual channels actually use the ports this cycle.
+			//The measurements are for the configured allocator, which is the one in the crossbar when there is any.
+			let measured = self.crossbar_allocator.is_none();
+			let maximum_matching = if measured { self.matching_cache.maximum_matching_size(&switch_requests,self.switch_allocator.input_exclusive()) } else { 0 };
+			let grants = self.switch_allocator.allocate(switch_requests,&simulation.rng);
+			if measured
 			{
-				//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
-				let selected_virtual_channel = match self.output_arbiter
-				{
-					//OutputArbiter::Random=> cand[simulation.rng.borrow_mut().gen_range(0,cand.len())],//rand-0.4
-					OutputArbiter::Random=> cand[simulation.rng.borrow_mut().gen_range(0..cand.len())],//rand-0.8
-					OutputArbiter::Token{ref mut port_token}=>
-					{
-						//Or by tokens as in fsin
-						//let nvc=self.virtual_ports[exit_port].len() as i64;
-						let nvc= amount_virtual_channels as i64;
-						let token= port_token[exit_port] as i64;
-						let mut best=0;
-						let mut bestd=nvc;
-						for vc in cand
-						{
-							let mut d:i64 = vc as i64 - token;
-							if d<0
-							{
-								d+=nvc;
-							}
-							if d<bestd
-							{
-								best=vc;
-								bestd=d;
-							}
-						}
-						port_token[exit_port]=best;
-						best
-					},
-				};
+				self.principal_measurement.switch_allocation_grants += grants.len() as f64;
+				self.principal_measurement.switch_allocation_maximum_matching += maximum_matching as f64;
+			}
+			if grants.is_empty()
+			{
+				break;
+			}
+			for grant in grants
+			{
+				let exit_port = grant.exit_port;
+				let selected_virtual_channel = grant.exit_virtual_channel;
 				//move phits around.
 				let (phit,original_port) = if self.output_buffer_size>0
 				{
 					//If we get the phit from an output buffer there is little to do.
-					let (phit,(entry_port,_entry_vc))=self.output_buffers[exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
+					let (phit,(entry_port,_entry_vc,_ready_cycle))=self.output_buffers[exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
 					(phit,entry_port)
 				}
 				else
@@ -1221,7 +1613,9 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 						if let Ok((phit,ack_message)) = self.reception_port_space[iport].extract(entry_vc)
 						{
 							moved_phits+=1;
+							self.principal_measurement.crossbar_phits+=1f64;
 							self.time_at_input_head[iport][entry_vc]=0;
+							self.head_pipeline_cycles[iport][entry_vc]=0;
 							//phit.virtual_channel.replace(Some(selected_virtual_channel));
 							*phit.virtual_channel.borrow_mut()=Some(selected_virtual_channel);
 							if let Some(message)=ack_message
@@ -1256,9 +1650,25 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 					}
 				};
 				let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
-				//Send the phit to the other link endpoint.
+				if let (Some(ref ecn_marking),true,Location::RouterPort{..}) = (&self.ecn_marking,phit.is_begin(),&new_location)
+				{
+					//The queue of the packet is made of the phits in the output buffer and those in the next router.
+					let status = &self.transmission_port_status[exit_port];
+					let next_occupancy = match status.known_occupied_space_for_virtual_channel(selected_virtual_channel)
+					{
+						Some(occupied) => occupied,
+						None => self.virtual_channel_capacity.saturating_sub(status.known_available_space_for_virtual_channel(selected_virtual_channel).unwrap_or(self.virtual_channel_capacity)),
+					};
+					let occupancy = if self.output_buffer_size>0 { self.output_buffers[exit_port][selected_virtual_channel].len() } else { 0 } + next_occupancy;
+					if ecn_marking.should_mark(occupancy,&simulation.rng)
+					{
+						*phit.packet.ecn_marked.borrow_mut()=true;
+					}
+				}
+				//Send the phit to the other link endpoint. Without output buffers it is still traversing the switch.
+				let traversal_delay = if self.output_buffer_size>0 { 0 } else { self.switch_traversal_delay };
 				events.push(EventGeneration{
-					delay: simulation.link_classes[link_class].delay,
+					delay: traversal_delay + simulation.link_classes[link_class].delay,
 					position:CyclePosition::Begin,
 					event:Event::PhitToLocation{
 						phit: phit.clone(),
@@ -1270,26 +1680,45 @@ impl<TM:'static+TransmissionMechanism> Eventful for Basic<TM>
 					},
 				});
 				self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
-				if phit.is_end()
-				{
-					if let OutputArbiter::Token{ref mut port_token}=self.output_arbiter
-					{
-						port_token[exit_port]=(port_token[exit_port]+1)%amount_virtual_channels;
-					}
-				}
-				Some(selected_virtual_channel)
-			} else {None};
+				port_budget[exit_port]-=1;
+				granted_per_port[exit_port].push(selected_virtual_channel);
+			}
+			round+=1;
+			if link_phits.iter().all(|&phits|phits<=round)
+			{
+				break;
+			}
+		}
+		for (exit_port,undo_selected_input) in undo_selected_input_per_port.into_iter().enumerate()
+		{
 			for other_virtual_channel in undo_selected_input
 			{
-				if Some(other_virtual_channel) != selected_virtual_channel
+				if !granted_per_port[exit_port].contains(&other_virtual_channel)
 				{
 					//Packets that have not started to move can change their decision at the next cycle
 					self.selected_input[exit_port][other_virtual_channel]=None;
 				}
 			}
 		}
+		//-- Send the messages that the receptors generate on their own, such as pause frames.
+		for (entry_port,space) in self.reception_port_space.iter_mut().enumerate()
+		{
+			for (message,extra_delay) in space.take_spontaneous_messages()
+			{
+				if message.is_pause()
+				{
+					self.principal_measurement.pause_frames+=1f64;
+				}
+				let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+				events.push(EventGeneration{
+					delay: simulation.link_classes[previous_link_class].delay + extra_delay,
+					position:CyclePosition::Begin,
+					event:Event::Acknowledge{location:previous_location,message},
+				});
+			}
+		}
 		//TODO: what to do with probabilistic requests???
-		if undecided_channels>0 || moved_phits>0 || events.len()>0 || request_len>0
+		if undecided_channels>0 || moved_phits>0 || events.len()>0 || request_len>0 || waiting_link
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0
 		//if true
 		{
@@ -1342,3 +1771,104 @@ impl<TM:TransmissionMechanism> Quantifiable for Basic<TM>
 	}
 }
 
+
+#[cfg(test)]
+mod tests
+{
+	use crate::tests::{test_plugs,new_simulation,send_message,advance_until,last_consumption};
+	use crate::config_parser::ConfigurationValue;
+
+	///The cycle in which a lone packet of 16 phits from the server 0 is consumed by the server 1, going through both routers.
+	fn zero_load_arrival(pipeline:&str, output_buffer_size:usize) -> usize
+	{
+		let plugs = test_plugs();
+		let mut simulation = new_simulation(&format!("Configuration
+		{{
+			random_seed: 1,
+			warmup: 0,
+			measured: 1000,
+			topology: Hamming {{ sides: [2], servers_per_router: 1 }},
+			traffic: Scripted,
+			maximum_packet_size: 16,
+			router: Basic {{ virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], delay: 0, {} buffer_size: 64, bubble: false, flit_size: 16, intransit_priority: false, allow_request_busy_port: true, output_priorize_lowest_label: false, output_buffer_size: {} }},
+			routing: Shortest,
+			link_classes: [ LinkClass {{ delay: 1 }}, LinkClass {{ delay: 1 }} ],
+			launch_configurations: [],
+		}}",pipeline,output_buffer_size),&plugs);
+		send_message(&mut simulation,0,1,16);
+		advance_until(&mut simulation,200);
+		assert_eq!(simulation.network.servers[1].statistics.consumed_messages,1);
+		last_consumption(&simulation,1)
+	}
+
+	#[test]
+	fn pipeline_adds_its_delays_at_zero_load()
+	{
+		let pipeline = "routing_delay: 2, virtual_channel_allocation_delay: 1, switch_allocation_delay: 3, switch_traversal_delay: 4,";
+		for output_buffer_size in [0,32]
+		{
+			let base = zero_load_arrival("",output_buffer_size);
+			//Each of the two routers adds the stages of the leading phit once, and the rest of phits follow it in the pipeline.
+			assert_eq!(zero_load_arrival(pipeline,output_buffer_size),base+2*(2+1+3+4),"with output_buffer_size={}",output_buffer_size);
+		}
+	}
+
+	///Sends 320 phits from the server 0 to the server 1, both in the router 0, through server links of 2 phits per cycle.
+	///Returns the consumption cycle together with the `average_crossbar_phits` and `crossbar_utilization` of the router 0.
+	fn crossbar_transfer(speedup:&str) -> (usize,f64,f64)
+	{
+		let plugs = test_plugs();
+		let mut simulation = new_simulation(&format!("Configuration
+		{{
+			random_seed: 1,
+			warmup: 0,
+			measured: 1000,
+			topology: Hamming {{ sides: [2], servers_per_router: 2 }},
+			traffic: Scripted,
+			maximum_packet_size: 16,
+			router: Basic {{ virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], delay: 0, {} buffer_size: 64, bubble: false, flit_size: 16, intransit_priority: false, allow_request_busy_port: true, output_priorize_lowest_label: false, output_buffer_size: 32 }},
+			routing: Shortest,
+			link_classes: [ LinkClass {{ delay: 1 }}, LinkClass {{ delay: 1, transference_speed: 2 }} ],
+			launch_configurations: [],
+		}}",speedup),&plugs);
+		send_message(&mut simulation,0,1,320);
+		advance_until(&mut simulation,1000);
+		assert_eq!(simulation.network.servers[1].statistics.consumed_phits,320);
+		let statistics = simulation.network.routers[0].borrow().aggregate_statistics(None,0,2,simulation.cycle).expect("the Basic router has statistics");
+		let mut crossbar_phits = None;
+		let mut crossbar_utilization = None;
+		if let ConfigurationValue::Object(_,ref content) = statistics
+		{
+			for (name,value) in content
+			{
+				match (name.as_ref(),value)
+				{
+					("average_crossbar_phits",&ConfigurationValue::Number(x)) => crossbar_phits=Some(x),
+					("crossbar_utilization",&ConfigurationValue::Number(x)) => crossbar_utilization=Some(x),
+					_ => (),
+				}
+			}
+		}
+		(last_consumption(&simulation,1),crossbar_phits.unwrap(),crossbar_utilization.unwrap())
+	}
+
+	#[test]
+	fn crossbar_speedup_moves_more_phits_per_cycle()
+	{
+		let (slow_arrival,slow_phits,slow_utilization) = crossbar_transfer("");
+		let (fast_arrival,fast_phits,fast_utilization) = crossbar_transfer("crossbar_speedup: 2,");
+		let (limited_arrival,limited_phits,limited_utilization) = crossbar_transfer("crossbar_speedup: 2, output_speedup: 1,");
+		//The same 320 phits cross the crossbar of the router 0 in the 1000 measured cycles.
+		for phits in [slow_phits,fast_phits,limited_phits]
+		{
+			assert!((phits-0.32).abs()<1e-9,"average_crossbar_phits={}",phits);
+		}
+		//The 3 output ports of the router 0 receive up to 1 phit per cycle each, or 2 with the speedup.
+		assert!((slow_utilization-0.32/3.0).abs()<1e-9,"crossbar_utilization={}",slow_utilization);
+		assert!((fast_utilization-0.32/6.0).abs()<1e-9,"crossbar_utilization={}",fast_utilization);
+		assert!((limited_utilization-0.32/3.0).abs()<1e-9,"crossbar_utilization={}",limited_utilization);
+		//Only the speedup into the output port keeps up with the faster server links.
+		assert!(2*fast_arrival<slow_arrival+10,"fast={} slow={}",fast_arrival,slow_arrival);
+		assert_eq!(limited_arrival,slow_arrival);
+	}
+}
@@ -1,20 +1,25 @@
 
 pub mod basic;
+pub mod allocator;
+pub mod output_queued;
+pub mod virtual_output_queued;
 
 use std::rc::{Rc};
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::mem::{size_of};
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap,BTreeSet};
 use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 use self::basic::Basic;
+use self::output_queued::OutputQueued;
+use self::virtual_output_queued::VirtualOutputQueued;
 use crate::config_parser::ConfigurationValue;
-use crate::topology::{Topology};
-use crate::{Phit,Packet};
+use crate::topology::{Topology,Location};
+use crate::{Phit,Packet,LinkClass};
 use crate::event::{Eventful};
 use crate::quantify::Quantifiable;
-use crate::Plugs;
+use crate::{Plugs,match_object_panic};
 
 ///The interface that a router type must follow.
 pub trait Router: Eventful + Quantifiable
@@ -60,6 +65,8 @@ pub struct RouterBuilderArgument<'a>
 	pub topology: &'a dyn Topology,
 	///The maximum number of phits that packet gonna have.
 	pub maximum_packet_size: usize,
+	///The classes of the links of the topology. For the routers that need to know their delays or speeds.
+	pub link_classes: &'a [LinkClass],
 	///The corresponding value of the `Statistics` struct.
 	///Available to the router for the case it want to use the same period.
 	pub statistics_temporal_step: usize,
@@ -80,6 +87,8 @@ pub fn new_router(arg:RouterBuilderArgument) -> Rc<RefCell<dyn Router>>
 		{
 			//"Basic" => Basic::<SimpleVirtualChannels>::new(arg.router_index, arg.cv, arg.plugs, arg.topology, arg.maximum_packet_size),
 			"Basic" => Basic::<SimpleVirtualChannels>::new(arg),
+			"OutputQueued" => OutputQueued::new(arg),
+			"VirtualOutputQueued" => VirtualOutputQueued::new(arg),
 			_ => panic!("Unknown router {}",cv_name),
 		}
 	}
@@ -223,6 +232,12 @@ pub trait StatusAtEmissor
 	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool;
 	///Consult available space. This includes dedicated and shared space.
 	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>;
+	///The phits known to be stored at the receptor in a virtual channel, for mechanisms that track them without using them to stop the transmission.
+	///By default `None`, meaning that the occupation has to be deduced from the available space.
+	fn known_occupied_space_for_virtual_channel(&self,_virtual_channel:usize)->Option<usize>
+	{
+		None
+	}
 	///Get timestamp of last transmission.
 	fn get_last_transmission(&self)->usize;
 }
@@ -244,6 +259,12 @@ pub trait SpaceAtReceptor
 	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
 	///Consult current number of phits in space dedicated to a virtual channel.
 	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
+	///Take the messages that the receptor sends on its own initiative instead of as a response to an extraction, such as pause frames.
+	///Each message comes with the delay, in addition to the one of the link, until it takes effect at the emissor.
+	fn take_spontaneous_messages(&mut self) -> Vec<(AcknowledgeMessage,usize)>
+	{
+		vec![]
+	}
 }
 
 ///A message send from the receptor to the emissor when the receptor state changes.
@@ -253,6 +274,8 @@ pub struct AcknowledgeMessage
 {
 	virtual_channel: Option<usize>,
 	set_available_size: Option<usize>,
+	///For on/off flow control. Whether the virtual channel is being paused (true) or resumed (false).
+	pause: Option<bool>,
 }
 
 impl AcknowledgeMessage
@@ -262,6 +285,7 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: None,
 			set_available_size: None,
+			pause: None,
 		}
 	}
 	pub fn ack_phit_clear_from_virtual_channel(virtual_channel:usize)->AcknowledgeMessage
@@ -269,6 +293,7 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: Some(virtual_channel),
 			set_available_size: None,
+			pause: None,
 		}
 	}
 	pub fn ack_fix_available_size(amount:usize)->AcknowledgeMessage
@@ -277,8 +302,32 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: None,
 			set_available_size: Some(amount),
+			pause: None,
+		}
+	}
+	///Stop the emissor from beginning new packets in the virtual channel.
+	pub fn ack_pause_virtual_channel(virtual_channel:usize)->AcknowledgeMessage
+	{
+		AcknowledgeMessage{
+			virtual_channel: Some(virtual_channel),
+			set_available_size: None,
+			pause: Some(true),
+		}
+	}
+	///Allow again the emissor to use the virtual channel.
+	pub fn ack_resume_virtual_channel(virtual_channel:usize)->AcknowledgeMessage
+	{
+		AcknowledgeMessage{
+			virtual_channel: Some(virtual_channel),
+			set_available_size: None,
+			pause: Some(false),
 		}
 	}
+	///Whether the message pauses a virtual channel.
+	pub fn is_pause(&self)->bool
+	{
+		self.pause==Some(true)
+	}
 }
 
 
@@ -509,6 +558,623 @@ impl TransmissionMechanism for SimpleVirtualChannels
 }
 
 
+///Credit counters for a receptor with a pool of space shared among the virtual channels, in addition to some space reserved for each virtual channel.
+///Phits consume the reserved space of their virtual channel before taking space from the shared pool.
+struct SharedCreditCounters
+{
+	///The known available space reserved to each virtual channel in the next router.
+	dedicated_credits: Vec<usize>,
+	///The known available space in the shared pool of the next router.
+	shared_credits: usize,
+	///The number of phits of each virtual channel that are occupying space from the shared pool.
+	///Acknowledges release the shared space first, so that the reserved space remains available for the virtual channels that only use it.
+	shared_in_use: Vec<usize>,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: usize,
+	///Credits required in the next router's virtual port to begin the transmission
+	flit_size: usize,
+}
+
+impl SharedCreditCounters
+{
+	///The space that a phit in the virtual channel could use, both dedicated and shared.
+	fn available(&self, virtual_channel:usize) -> usize
+	{
+		self.dedicated_credits[virtual_channel] + self.shared_credits
+	}
+}
+
+impl StatusAtEmissor for SharedCreditCounters
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.dedicated_credits.len()
+	}
+
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		let virtual_channel = message.virtual_channel.expect("there is no virtual channel in the message");
+		if self.shared_in_use[virtual_channel]>0
+		{
+			self.shared_in_use[virtual_channel]-=1;
+			self.shared_credits+=1;
+		}
+		else
+		{
+			self.dedicated_credits[virtual_channel]+=1;
+		}
+	}
+
+	fn notify_outcoming_phit(&mut self, virtual_channel: usize, cycle:usize)
+	{
+		if self.dedicated_credits[virtual_channel]>0
+		{
+			self.dedicated_credits[virtual_channel]-=1;
+		}
+		else
+		{
+			self.shared_credits-=1;
+			self.shared_in_use[virtual_channel]+=1;
+		}
+		self.last_transmission=cycle;
+	}
+
+	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		let mut necessary_credits=1;
+		if phit.is_begin()
+		{
+			necessary_credits=self.flit_size;
+		}
+		self.available(virtual_channel)>=necessary_credits
+	}
+
+	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		let necessary_credits=phit.packet.size - phit.index;
+		self.available(virtual_channel)>=necessary_credits
+	}
+
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(self.available(virtual_channel))
+	}
+
+	fn get_last_transmission(&self)->usize
+	{
+		self.last_transmission
+	}
+}
+
+///A dynamically allocated multi-queue (DAMQ). There is a queue for each virtual channel, all of them storing their phits in a common space.
+///Each virtual channel has `reserved_size` phits of that space reserved, the rest being shared by all of them.
+pub struct SharedBuffers
+{
+	///The queues of each virtual channel. They are not limited by themselves.
+	queues: ParallelBuffers,
+	///The space reserved to each virtual channel.
+	reserved_size: usize,
+	///The total space of the port, including the reserved and the shared spaces.
+	total_size: usize,
+}
+
+impl SpaceAtReceptor for SharedBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, rng: &RefCell<StdRng>) -> Result<(),()>
+	{
+		let occupied:usize = self.queues.buffers.iter().map(|buffer|buffer.len()).sum();
+		if occupied>=self.total_size
+		{
+			return Err(());
+		}
+		self.queues.insert(phit,rng)
+	}
+
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.front_iter()
+	}
+
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.queues.front_virtual_channel(virtual_channel)
+	}
+
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),()>
+	{
+		//The emissor decides by itself which kind of credit is being returned.
+		self.queues.extract(virtual_channel)
+	}
+
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.iter_phits()
+	}
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.reserved_size - self.queues.buffers[virtual_channel].len().min(self.reserved_size))
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.queues.buffers[virtual_channel].len().min(self.reserved_size))
+	}
+}
+
+///A virtual channel mechanism with the buffers of the port being shared, as a dynamically allocated multi-queue (DAMQ).
+///Each port has space for `virtual_channels*buffer_size` phits, as in `SimpleVirtualChannels`, but only `reserved_size` of them are reserved to each virtual channel.
+///The rest of the space is shared among all the virtual channels. The `reserved_size` must hold a flit and the reserved space of all the virtual channels must fit in the port.
+///It keeps track of the space of the neighbour using credit counters that distinguish the reserved and the shared space.
+struct SharedBufferVirtualChannels
+{
+	///The number of virtual channels = number of queues.
+	virtual_channels: usize,
+	///The space reserved to each virtual channel.
+	reserved_size: usize,
+	///The space shared by all the virtual channels.
+	shared_size: usize,
+	///Credits required in the next router's virtual port to begin the transmission
+	flit_size: usize,
+}
+
+impl SharedBufferVirtualChannels
+{
+	fn new(virtual_channels: usize, buffer_size: usize, reserved_size: usize, flit_size:usize) -> SharedBufferVirtualChannels
+	{
+		//Each virtual channel must be able to receive a whole flit in its reserved space, so that it never depends on the shared space to advance.
+		if reserved_size<flit_size
+		{
+			panic!("The reserved_size ({}) cannot be lower than the flit_size ({}).",reserved_size,flit_size);
+		}
+		//The space of the port is `buffer_size` for each virtual channel, from which all of them reserve theirs.
+		let port_size = virtual_channels*buffer_size;
+		if reserved_size*virtual_channels>port_size
+		{
+			panic!("The reserved_size ({}) of the {} virtual channels does not fit in the {} phits of each port (buffer_size {} per virtual channel).",reserved_size,virtual_channels,port_size,buffer_size);
+		}
+		SharedBufferVirtualChannels{
+			virtual_channels,
+			reserved_size,
+			shared_size: virtual_channels*(buffer_size-reserved_size),
+			flit_size,
+		}
+	}
+	///The greatest space that a virtual channel can get.
+	fn maximum_space_per_virtual_channel(&self) -> usize
+	{
+		self.reserved_size + self.shared_size
+	}
+}
+
+impl TransmissionMechanism for SharedBufferVirtualChannels
+{
+	type StatusAtEmissor = SharedCreditCounters;
+	type SpaceAtReceptor = SharedBuffers;
+
+	fn new_status_at_emissor(&self)-> SharedCreditCounters
+	{
+		SharedCreditCounters{
+			dedicated_credits: vec![self.reserved_size;self.virtual_channels],
+			shared_credits: self.shared_size,
+			shared_in_use: vec![0;self.virtual_channels],
+			last_transmission: 0,
+			flit_size: self.flit_size,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> SharedBuffers
+	{
+		SharedBuffers{
+			queues: ParallelBuffers{
+				buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+				input_virtual_channel_choices: BTreeMap::new(),
+			},
+			reserved_size: self.reserved_size,
+			total_size: self.virtual_channels*self.reserved_size + self.shared_size,
+		}
+	}
+}
+
+///The status of an on/off flow control, as the priority flow control (PFC) of Ethernet.
+///The emissor does not count the space of the receptor, it only knows which virtual channels have been paused.
+///A paused virtual channel completes the packet in course, but it does not begin new ones.
+struct PauseStatus
+{
+	///Whether each virtual channel has been paused by the receptor.
+	paused: Vec<bool>,
+	///The space of each buffer of the receptor. It is reported as available while the virtual channel is not paused.
+	buffer_size: usize,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: usize,
+}
+
+impl StatusAtEmissor for PauseStatus
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.paused.len()
+	}
+
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		let virtual_channel = message.virtual_channel.expect("there is no virtual channel in the message");
+		match message.pause
+		{
+			Some(pause) => self.paused[virtual_channel]=pause,
+			None => panic!("The on/off flow control only receives pause and resume messages."),
+		}
+	}
+
+	fn notify_outcoming_phit(&mut self, _virtual_channel: usize, cycle:usize)
+	{
+		self.last_transmission=cycle;
+	}
+
+	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		!self.paused[virtual_channel] || !phit.is_begin()
+	}
+
+	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		//The headroom of the receptor is expected to absorb the packet.
+		self.can_transmit(phit,virtual_channel)
+	}
+
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some( if self.paused[virtual_channel] { 0 } else { self.buffer_size } )
+	}
+
+	fn get_last_transmission(&self)->usize
+	{
+		self.last_transmission
+	}
+}
+
+///Buffers for an on/off flow control, one for each virtual channel.
+///A pause message is sent when the occupation of a buffer reaches `xoff_threshold` and a resume message when it drops to `xon_threshold`.
+pub struct PauseBuffers
+{
+	///The queues of each virtual channel.
+	queues: ParallelBuffers,
+	///The size of each buffer.
+	buffer_size: usize,
+	///Occupation at which the virtual channel is paused.
+	xoff_threshold: usize,
+	///Occupation at which the virtual channel is resumed.
+	xon_threshold: usize,
+	///Cycles that the emissor takes to react to the messages.
+	reaction_delay: usize,
+	///Whether each virtual channel is paused.
+	paused: Vec<bool>,
+	///The pause and resume messages not yet sent.
+	pending_messages: Vec<AcknowledgeMessage>,
+}
+
+impl SpaceAtReceptor for PauseBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, rng: &RefCell<StdRng>) -> Result<(),()>
+	{
+		self.queues.insert(phit.clone(),rng)?;
+		let virtual_channel = phit.virtual_channel.borrow().expect("the phit has no virtual channel");
+		let occupation = self.queues.buffers[virtual_channel].len();
+		if occupation>self.buffer_size
+		{
+			panic!("Overflow of a buffer with on/off flow control. The headroom is not enough to absorb the phits in flight.");
+		}
+		if !self.paused[virtual_channel] && occupation>=self.xoff_threshold
+		{
+			self.paused[virtual_channel]=true;
+			self.pending_messages.push(AcknowledgeMessage::ack_pause_virtual_channel(virtual_channel));
+		}
+		Ok(())
+	}
+
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.front_iter()
+	}
+
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.queues.front_virtual_channel(virtual_channel)
+	}
+
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),()>
+	{
+		//There are no credits to return.
+		let (phit,_credit) = self.queues.extract(virtual_channel)?;
+		if self.paused[virtual_channel] && self.queues.buffers[virtual_channel].len()<=self.xon_threshold
+		{
+			self.paused[virtual_channel]=false;
+			self.pending_messages.push(AcknowledgeMessage::ack_resume_virtual_channel(virtual_channel));
+		}
+		Ok((phit,None))
+	}
+
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.iter_phits()
+	}
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size - self.queues.buffers[virtual_channel].len())
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.queues.buffers[virtual_channel].len())
+	}
+	fn take_spontaneous_messages(&mut self) -> Vec<(AcknowledgeMessage,usize)>
+	{
+		let reaction_delay = self.reaction_delay;
+		self.pending_messages.drain(..).map(|message|(message,reaction_delay)).collect()
+	}
+}
+
+///An on/off flow control, as the priority flow control (PFC) of Ethernet, with a buffer of `buffer_size` phits for each virtual channel.
+///The receptor pauses a virtual channel when its occupation reaches `buffer_size-headroom` and resumes it when it drops to `xon_threshold`.
+///The `headroom` must absorb the phits sent until the pause takes effect, which includes the delays of the links, the `reaction_delay`, and a packet in course.
+///The Basic router checks it when built, requiring `(2*delay+reaction_delay+switch_traversal_delay+1)*capacity+maximum_packet_size` for each link between routers.
+struct PauseFrameVirtualChannels
+{
+	///The number of virtual channels = number of buffers.
+	virtual_channels: usize,
+	///The size of each buffer.
+	buffer_size: usize,
+	///Occupation at which the virtual channel is paused.
+	xoff_threshold: usize,
+	///Occupation at which the virtual channel is resumed.
+	xon_threshold: usize,
+	///Cycles that the emissor takes to react to the messages.
+	reaction_delay: usize,
+}
+
+impl PauseFrameVirtualChannels
+{
+	fn new(virtual_channels: usize, buffer_size: usize, headroom: usize, xon_threshold: usize, reaction_delay: usize) -> PauseFrameVirtualChannels
+	{
+		if headroom>=buffer_size
+		{
+			panic!("The headroom ({}) must be lower than the buffer_size ({}).",headroom,buffer_size);
+		}
+		let xoff_threshold = buffer_size - headroom;
+		if xon_threshold>=xoff_threshold
+		{
+			panic!("The xon_threshold ({}) must be lower than the pause threshold buffer_size-headroom ({}).",xon_threshold,xoff_threshold);
+		}
+		PauseFrameVirtualChannels{
+			virtual_channels,
+			buffer_size,
+			xoff_threshold,
+			xon_threshold,
+			reaction_delay,
+		}
+	}
+}
+
+impl TransmissionMechanism for PauseFrameVirtualChannels
+{
+	type StatusAtEmissor = PauseStatus;
+	type SpaceAtReceptor = PauseBuffers;
+
+	fn new_status_at_emissor(&self)-> PauseStatus
+	{
+		PauseStatus{
+			paused: vec![false;self.virtual_channels],
+			buffer_size: self.buffer_size,
+			last_transmission: 0,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> PauseBuffers
+	{
+		PauseBuffers{
+			queues: ParallelBuffers{
+				buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+				input_virtual_channel_choices: BTreeMap::new(),
+			},
+			buffer_size: self.buffer_size,
+			xoff_threshold: self.xoff_threshold,
+			xon_threshold: self.xon_threshold,
+			reaction_delay: self.reaction_delay,
+			paused: vec![false;self.virtual_channels],
+			pending_messages: vec![],
+		}
+	}
+}
+
+///The status of a lossy link. The emissor never waits for the receptor, which drops the packets for which it has no space.
+///The receptor still acknowledges each phit it extracts or discards, so that the emissor knows how many phits are queued there, as needed to mark congested packets.
+struct LossyStatus
+{
+	///The number of virtual channels of the receptor.
+	virtual_channels: usize,
+	///The space of each buffer of the receptor. It is always reported as available.
+	buffer_size: usize,
+	///The phits sent through each virtual channel and not acknowledged yet.
+	occupied: Vec<usize>,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: usize,
+}
+
+impl StatusAtEmissor for LossyStatus
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.virtual_channels
+	}
+
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		let virtual_channel = message.virtual_channel.expect("A lossy receptor only acknowledges phits.");
+		self.occupied[virtual_channel] = self.occupied[virtual_channel].checked_sub(1).expect("Acknowledgement of a phit that was not sent.");
+	}
+
+	fn notify_outcoming_phit(&mut self, virtual_channel: usize, cycle:usize)
+	{
+		self.occupied[virtual_channel]+=1;
+		self.last_transmission=cycle;
+	}
+
+	fn can_transmit(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
+	{
+		true
+	}
+
+	fn can_transmit_whole_packet(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
+	{
+		true
+	}
+
+	fn known_available_space_for_virtual_channel(&self,_virtual_channel:usize)->Option<usize>
+	{
+		Some(self.buffer_size)
+	}
+
+	fn known_occupied_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(self.occupied[virtual_channel])
+	}
+
+	fn get_last_transmission(&self)->usize
+	{
+		self.last_transmission
+	}
+}
+
+///Buffers that drop the packets that do not fit in them, instead of making the emissor wait.
+///The decision is taken at the leading phit. The remaining phits of a packet dropped by these buffers are discarded as they arrive.
+pub struct LossyBuffers
+{
+	///The queues of each virtual channel.
+	queues: ParallelBuffers,
+	///The size of each buffer.
+	buffer_size: usize,
+	///For each virtual channel, the phits of the accepted packets that have not arrived yet.
+	incoming: Vec<usize>,
+	///The acknowledgements of the discarded phits, to be sent to the emissor.
+	pending_messages: Vec<(AcknowledgeMessage,usize)>,
+	///The packets dropped by these buffers whose last phit has not arrived yet.
+	///A packet dropped further in the network still has its remaining phits stored here, to keep the count of `incoming` right.
+	dropped_packets: BTreeSet<*const Packet>,
+}
+
+impl SpaceAtReceptor for LossyBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, rng: &RefCell<StdRng>) -> Result<(),()>
+	{
+		let virtual_channel = phit.virtual_channel.borrow().expect("the phit has no virtual channel");
+		let packet_ptr = phit.packet.as_ref() as *const Packet;
+		let discarded = if phit.is_end() { self.dropped_packets.remove(&packet_ptr) } else { self.dropped_packets.contains(&packet_ptr) };
+		if discarded
+		{
+			self.pending_messages.push((AcknowledgeMessage::ack_phit_clear_from_virtual_channel(virtual_channel),0));
+			return Ok(());
+		}
+		if phit.is_begin()
+		{
+			if self.queues.buffers[virtual_channel].len() + self.incoming[virtual_channel] + phit.packet.size > self.buffer_size
+			{
+				*phit.packet.dropped.borrow_mut()=true;
+				if !phit.is_end()
+				{
+					self.dropped_packets.insert(packet_ptr);
+				}
+				self.pending_messages.push((AcknowledgeMessage::ack_phit_clear_from_virtual_channel(virtual_channel),0));
+				return Ok(());
+			}
+			self.incoming[virtual_channel]+=phit.packet.size;
+		}
+		self.incoming[virtual_channel]-=1;
+		self.queues.insert(phit,rng)
+	}
+
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.front_iter()
+	}
+
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.queues.front_virtual_channel(virtual_channel)
+	}
+
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),()>
+	{
+		//The acknowledgement only informs of the occupation. The emissor does not wait for it.
+		self.queues.extract(virtual_channel)
+	}
+
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		self.queues.iter_phits()
+	}
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size - self.queues.buffers[virtual_channel].len() - self.incoming[virtual_channel])
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.queues.buffers[virtual_channel].len())
+	}
+	fn take_spontaneous_messages(&mut self) -> Vec<(AcknowledgeMessage,usize)>
+	{
+		std::mem::take(&mut self.pending_messages)
+	}
+}
+
+///A lossy link with a buffer of `buffer_size` phits for each virtual channel.
+///The emissor transmits without any flow control and the receptor drops the packets that do not fit in the buffer.
+///The lost packets must be recovered by the retransmission protocol of the servers, if any.
+struct LossyVirtualChannels
+{
+	///The number of virtual channels = number of buffers.
+	virtual_channels: usize,
+	///The size of each buffer.
+	buffer_size: usize,
+}
+
+impl LossyVirtualChannels
+{
+	fn new(virtual_channels: usize, buffer_size: usize) -> LossyVirtualChannels
+	{
+		LossyVirtualChannels{
+			virtual_channels,
+			buffer_size,
+		}
+	}
+}
+
+impl TransmissionMechanism for LossyVirtualChannels
+{
+	type StatusAtEmissor = LossyStatus;
+	type SpaceAtReceptor = LossyBuffers;
+
+	fn new_status_at_emissor(&self)-> LossyStatus
+	{
+		LossyStatus{
+			virtual_channels: self.virtual_channels,
+			buffer_size: self.buffer_size,
+			occupied: vec![0;self.virtual_channels],
+			last_transmission: 0,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> LossyBuffers
+	{
+		LossyBuffers{
+			queues: ParallelBuffers{
+				buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+				input_virtual_channel_choices: BTreeMap::new(),
+			},
+			buffer_size: self.buffer_size,
+			incoming: vec![0;self.virtual_channels],
+			pending_messages: vec![],
+			dropped_packets: BTreeSet::new(),
+		}
+	}
+}
+
 ///For senders that not care about the receptor or phantom senders that do not actually send anything.
 struct EmptyStatus();
 
@@ -618,6 +1284,121 @@ impl TransmissionMechanism for TransmissionToServer
 	}
 }
 
+///Builds the status and space of each port of a router. The given `transmission_mechanism` is used for the ports connected to other routers.
+fn new_port_mechanisms<TM:TransmissionMechanism>(transmission_mechanism:&TM, to_server_mechanism:&TransmissionToServer, from_server_mechanism:&TransmissionFromServer, topology:&dyn Topology, router_index:usize) -> (Vec<Box<dyn StatusAtEmissor>>,Vec<Box<dyn SpaceAtReceptor>>)
+	where TM::StatusAtEmissor:'static, TM::SpaceAtReceptor:'static
+{
+	let ports=topology.ports(router_index);
+	let transmission_port_status:Vec<Box<dyn StatusAtEmissor>> = (0..ports).map(|p|
+		if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
+		{
+			let b:Box<dyn StatusAtEmissor> = Box::new(to_server_mechanism.new_status_at_emissor());
+			b
+		}
+		else
+		{
+			Box::new(transmission_mechanism.new_status_at_emissor())
+		}
+	).collect();
+	let reception_port_space:Vec<Box<dyn SpaceAtReceptor>> = (0..ports).map(|p|
+		if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
+		{
+			let b:Box<dyn SpaceAtReceptor> = Box::new(from_server_mechanism.new_space_at_receptor());
+			b
+		}
+		else
+		{
+			Box::new(transmission_mechanism.new_space_at_receptor())
+		}
+	).collect();
+	(transmission_port_status,reception_port_space)
+}
+
+///Explicit congestion notification (ECN) marking, with a probability function as in random early detection (RED).
+///A packet leaving through a port with `occupancy` phits queued towards the next buffer is marked with probability 0 below `minimum_threshold`, growing linearly up to `maximum_probability` at `maximum_threshold`, and 1 from there.
+///When both thresholds are equal it marks every packet above the threshold, as DCTCP expects.
+///```ignore
+///ecn_marking: EcnMarking{
+///	minimum_threshold: 16,
+///	maximum_threshold: 48,//defaults to minimum_threshold.
+///	maximum_probability: 0.2,//defaults to 1.
+///}
+///```
+#[derive(Debug,Quantifiable)]
+pub struct EcnMarking
+{
+	///Occupation from which packets may be marked.
+	minimum_threshold: usize,
+	///Occupation from which all packets are marked.
+	maximum_threshold: usize,
+	///Probability of marking just below `maximum_threshold`.
+	maximum_probability: f64,
+}
+
+impl EcnMarking
+{
+	pub fn new(cv:&ConfigurationValue) -> EcnMarking
+	{
+		let mut minimum_threshold=None;
+		let mut maximum_threshold=None;
+		let mut maximum_probability=1.0;
+		match_object_panic!(cv,"EcnMarking",value,
+			"minimum_threshold" => match value
+			{
+				&ConfigurationValue::Number(f) => minimum_threshold=Some(f as usize),
+				_ => panic!("bad value for minimum_threshold"),
+			},
+			"maximum_threshold" => match value
+			{
+				&ConfigurationValue::Number(f) => maximum_threshold=Some(f as usize),
+				_ => panic!("bad value for maximum_threshold"),
+			},
+			"maximum_probability" => match value
+			{
+				&ConfigurationValue::Number(f) => maximum_probability=f,
+				_ => panic!("bad value for maximum_probability"),
+			},
+		);
+		let minimum_threshold=minimum_threshold.expect("There were no minimum_threshold");
+		let maximum_threshold=maximum_threshold.unwrap_or(minimum_threshold);
+		if maximum_probability<0.0 || maximum_probability>1.0
+		{
+			panic!("The maximum_probability ({}) of EcnMarking must be in [0,1].",maximum_probability);
+		}
+		if maximum_threshold<minimum_threshold
+		{
+			panic!("The maximum_threshold ({}) of EcnMarking cannot be lower than its minimum_threshold ({}).",maximum_threshold,minimum_threshold);
+		}
+		EcnMarking{
+			minimum_threshold,
+			maximum_threshold,
+			maximum_probability,
+		}
+	}
+	///The probability of marking a packet leaving through a port with `occupancy` phits queued.
+	pub fn probability(&self, occupancy:usize) -> f64
+	{
+		if occupancy<self.minimum_threshold
+		{
+			0.0
+		}
+		else if occupancy>=self.maximum_threshold
+		{
+			1.0
+		}
+		else
+		{
+			self.maximum_probability * (occupancy-self.minimum_threshold) as f64 / (self.maximum_threshold-self.minimum_threshold) as f64
+		}
+	}
+	///Randomly decides whether to mark a packet leaving through a port with `occupancy` phits queued.
+	pub fn should_mark(&self, occupancy:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		let probability = self.probability(occupancy);
+		probability>=1.0 || (probability>0.0 && rng.borrow_mut().gen_range(0.0..1.0)<probability)
+	}
+}
+
 ///What a server needs to know of a router to send it packets.
 #[derive(Clone,Quantifiable)]
 pub struct StatusAtServer
@@ -826,3 +1607,184 @@ impl TransmissionMechanism for TransmissionFromServer
 }
 
 
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::routing::RoutingInfo;
+	use crate::Message;
+
+	///The phits of a new packet of `size` phits, going through the given virtual channel.
+	fn new_phits(size:usize, virtual_channel:usize) -> Vec<Rc<Phit>>
+	{
+		let message = Rc::new(Message{
+			origin: 0,
+			destination: 1,
+			size,
+			creation_cycle: 0,
+		});
+		let packet = Rc::new(Packet{
+			size,
+			routing_info: RefCell::new(RoutingInfo::new()),
+			message,
+			index: 0,
+			cycle_into_network: RefCell::new(0),
+			extra: RefCell::new(None),
+			sequence_number: None,
+			dropped: RefCell::new(false),
+			ecn_marked: RefCell::new(false),
+		});
+		(0..size).map(|index|Rc::new(Phit{
+			packet: packet.clone(),
+			index,
+			virtual_channel: RefCell::new(Some(virtual_channel)),
+		})).collect()
+	}
+
+	#[test]
+	fn shared_buffers_count_reserved_and_shared_credits()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		//Two virtual channels of 8 phits, reserving 4 each and sharing the other 8.
+		let mechanism = SharedBufferVirtualChannels::new(2,8,4,2);
+		let mut status = mechanism.new_status_at_emissor();
+		let mut buffers = mechanism.new_space_at_receptor();
+		//The first virtual channel fills its reserved space and then the whole shared pool.
+		let long = new_phits(12,0);
+		for phit in long.iter()
+		{
+			assert!(status.can_transmit(phit,0));
+			status.notify_outcoming_phit(0,0);
+			buffers.insert(phit.clone(),&rng).unwrap();
+		}
+		assert_eq!(status.known_available_space_for_virtual_channel(0),Some(0));
+		//The other virtual channel keeps its reserved space.
+		assert_eq!(status.known_available_space_for_virtual_channel(1),Some(4));
+		let short = new_phits(4,1);
+		assert!(status.can_transmit(&short[0],1));
+		for phit in short.iter()
+		{
+			status.notify_outcoming_phit(1,0);
+			buffers.insert(phit.clone(),&rng).unwrap();
+		}
+		assert_eq!(buffers.occupied_dedicated_space(1),Some(4));
+		assert!(buffers.insert(new_phits(1,1)[0].clone(),&rng).is_err(),"the port should be full");
+		//Extracting from the first virtual channel returns the shared space before its reserved space.
+		for returned in 1..=12
+		{
+			let (_phit,message) = buffers.extract(0).unwrap();
+			status.acknowledge(message.expect("the extraction should be acknowledged"));
+			//The second virtual channel has used up its reserved space, so it only sees the shared one.
+			assert_eq!(status.known_available_space_for_virtual_channel(1),Some(returned.min(8)));
+			assert_eq!(status.known_available_space_for_virtual_channel(0),Some(returned));
+		}
+	}
+
+	#[test]
+	fn pause_frames_stop_and_resume_at_the_thresholds()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		//Buffers of 16 phits with a headroom of 6 pause at 10 phits and resume at 4.
+		let mechanism = PauseFrameVirtualChannels::new(2,16,6,4,3);
+		let mut status = mechanism.new_status_at_emissor();
+		let mut buffers = mechanism.new_space_at_receptor();
+		let phits = new_phits(12,0);
+		for (index,phit) in phits.iter().take(10).enumerate()
+		{
+			buffers.insert(phit.clone(),&rng).unwrap();
+			let messages = buffers.take_spontaneous_messages();
+			if index<9
+			{
+				assert!(messages.is_empty(),"paused with {} phits",index+1);
+			}
+			else
+			{
+				assert_eq!(messages.len(),1);
+				let (message,delay) = messages.into_iter().next().unwrap();
+				assert_eq!(message.pause,Some(true));
+				assert_eq!(delay,3);
+				status.acknowledge(message);
+			}
+		}
+		//The paused emissor completes the packet in course, but it does not begin another.
+		let next = new_phits(1,0);
+		assert!(!status.can_transmit(&next[0],0));
+		assert!(status.can_transmit(&phits[10],0));
+		assert_eq!(status.known_available_space_for_virtual_channel(0),Some(0));
+		assert_eq!(status.known_available_space_for_virtual_channel(1),Some(16));
+		//The headroom absorbs the phits in flight without pausing again.
+		for phit in phits.iter().skip(10)
+		{
+			buffers.insert(phit.clone(),&rng).unwrap();
+		}
+		assert!(buffers.take_spontaneous_messages().is_empty());
+		//Extractions do not return credits. The resume is sent when the occupation drops to 4.
+		for remaining in (4..12).rev()
+		{
+			let (_phit,message) = buffers.extract(0).unwrap();
+			assert!(message.is_none());
+			let messages = buffers.take_spontaneous_messages();
+			if remaining>4
+			{
+				assert!(messages.is_empty(),"resumed with {} phits",remaining);
+			}
+			else
+			{
+				assert_eq!(messages.len(),1);
+				let (message,_delay) = messages.into_iter().next().unwrap();
+				assert_eq!(message.pause,Some(false));
+				status.acknowledge(message);
+			}
+		}
+		assert!(status.can_transmit(&next[0],0));
+		assert_eq!(status.known_available_space_for_virtual_channel(0),Some(16));
+	}
+
+	#[test]
+	fn lossy_buffers_drop_packets_that_do_not_fit()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let mut buffers = LossyVirtualChannels::new(2,4).new_space_at_receptor();
+		let accepted = new_phits(3,0);
+		let dropped = new_phits(3,0);
+		buffers.insert(accepted[0].clone(),&rng).unwrap();
+		//The whole accepted packet is reserved since its leading phit.
+		assert_eq!(buffers.available_dedicated_space(0),Some(1));
+		buffers.insert(dropped[0].clone(),&rng).unwrap();
+		assert!(*dropped[0].packet.dropped.borrow());
+		for index in 1..3
+		{
+			buffers.insert(accepted[index].clone(),&rng).unwrap();
+			buffers.insert(dropped[index].clone(),&rng).unwrap();
+		}
+		assert_eq!(buffers.occupied_dedicated_space(0),Some(3));
+		assert_eq!(buffers.available_dedicated_space(0),Some(1));
+		assert!(buffers.iter_phits().all(|phit|Rc::ptr_eq(&phit.packet,&accepted[0].packet)));
+		//Each discarded phit is acknowledged to the emissor.
+		assert_eq!(buffers.take_spontaneous_messages().len(),3);
+		//The other virtual channel is unaffected.
+		assert_eq!(buffers.available_dedicated_space(1),Some(4));
+	}
+
+	#[test]
+	fn lossy_buffers_keep_packets_dropped_downstream()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(10));
+		let mut buffers = LossyVirtualChannels::new(1,4).new_space_at_receptor();
+		let phits = new_phits(3,0);
+		buffers.insert(phits[0].clone(),&rng).unwrap();
+		//A later router drops the packet while its tail is still arriving here.
+		*phits[0].packet.dropped.borrow_mut()=true;
+		buffers.insert(phits[1].clone(),&rng).unwrap();
+		buffers.insert(phits[2].clone(),&rng).unwrap();
+		assert_eq!(buffers.occupied_dedicated_space(0),Some(3));
+		assert_eq!(buffers.available_dedicated_space(0),Some(1));
+		assert!(buffers.take_spontaneous_messages().is_empty());
+		for _ in 0..3
+		{
+			buffers.extract(0).unwrap();
+		}
+		assert_eq!(buffers.available_dedicated_space(0),Some(4));
+	}
+}
@@ -0,0 +1,572 @@
+
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+use std::collections::VecDeque;
+use std::ops::DerefMut;
+use std::mem::{size_of};
+use ::rand::{rngs::StdRng,prelude::SliceRandom};
+use super::{Router,StatusAtEmissor,SpaceAtReceptor,TransmissionToServer,TransmissionFromServer,SimpleVirtualChannels,AcknowledgeMessage,RouterBuilderArgument,new_port_mechanisms};
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Location};
+use crate::routing::CandidateEgress;
+use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::event::{Event,Eventful,EventGeneration,CyclePosition};
+use crate::{Phit,Packet,Simulation,match_object_panic};
+use crate::quantify::Quantifiable;
+
+/**
+An ideal output-queued router, to be used as a baseline. Its crossbar has infinite speedup, so each phit moves into the buffer of its output port as soon as it has been routed and there is space in that buffer. There is no head-of-line blocking other than the one caused by full output buffers.
+
+Several inputs may write packets into the same output buffer at once. The leading phit of a packet reserves space in the output buffer for the whole packet, so the `output_buffer_size` must be at least the `maximum_packet_size`. Each output buffer sends its packets in the order in which their leading phits arrived, each one as its phits become available.
+
+The input buffers are kept only to apply flow control towards the previous router.
+
+```ignore
+OutputQueued{
+	virtual_channels: 4,
+	virtual_channel_policies: [EnforceFlowControl,Random],
+	buffer_size: 64,//phits of each input buffer.
+	output_buffer_size: 64,//phits of each output buffer, there being one for each port and virtual channel. At least the maximum_packet_size.
+	flit_size: 16,//phits that must fit in the next buffer to begin the transmission of a packet.
+}
+```
+**/
+pub struct OutputQueued
+{
+	///Weak pointer to itself, see https://users.rust-lang.org/t/making-a-rc-refcell-trait2-from-rc-refcell-trait1/16086/3
+	self_rc: Weak<RefCell<OutputQueued>>,
+	///If there is an event pending
+	event_pending: bool,
+	///The cycle number of the last time OutputQueued::process was called. Only for debugging/assertion purposes.
+	last_process_at_cycle: Option<usize>,
+	///Its index in the topology
+	router_index: usize,
+	///The mechanism to select virtual channels
+	virtual_channel_policies: Vec<Box<dyn VirtualChannelPolicy>>,
+	///Credits required in the next buffer to begin the transmission of a packet.
+	flit_size: usize,
+	///Size of each input buffer.
+	buffer_size: usize,
+	///Size of each output buffer.
+	output_buffer_size: usize,
+	///transmission_port_status[port] = status
+	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	///reception_port_space[port] = space
+	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
+	///The output buffers indexed as [output_port][output_vc]. The packets are in the order their leading phits arrived.
+	output_buffers: Vec<Vec<VecDeque<OutputPacket>>>,
+	///The phits reserved in each output buffer by the packets in it, indexed as [output_port][output_vc]. It includes the phits still at the inputs.
+	reserved_output_space: Vec<Vec<usize>>,
+	///If not None then the output port+virtual_channel into which the packet in this input buffer is being written.
+	selected_output: Vec<Vec<Option<(usize,usize)>>>,
+	///The virtual channel with priority in the next transmission through each output port.
+	port_token: Vec<usize>,
+
+	//statistics:
+	///The first cycle included in the statistics.
+	statistics_begin_cycle: usize,
+	///Accumulated over time, averaged per port.
+	statistics_output_buffer_occupation_per_vc: Vec<f64>,
+}
+
+///A packet in an output buffer of an `OutputQueued` router. Its phits are appended as they leave the input buffer.
+struct OutputPacket
+{
+	///The packet being stored.
+	packet: Rc<Packet>,
+	///The port through which the packet entered the router.
+	entry_port: usize,
+	///The phits that have arrived and have not been sent yet.
+	phits: VecDeque<Rc<Phit>>,
+}
+
+impl Router for OutputQueued
+{
+	fn insert(&mut self, phit:Rc<Phit>, port:usize, rng: &RefCell<StdRng>)
+	{
+		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+	}
+	fn acknowledge(&mut self, port:usize, ack_message:AcknowledgeMessage)
+	{
+		self.transmission_port_status[port].acknowledge(ack_message);
+	}
+	fn num_virtual_channels(&self) -> usize
+	{
+		self.transmission_port_status[0].num_virtual_channels()
+	}
+	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
+	{
+		self.buffer_size
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		let input_phits = self.reception_port_space.iter().flat_map(|space|space.iter_phits());
+		let output_phits = self.output_buffers.iter().flat_map(|port|port.iter().flat_map(|buffer|buffer.iter().flat_map(|packet|packet.phits.iter().cloned())));
+		Box::new(input_phits.chain(output_phits).collect::<Vec<_>>().into_iter())
+	}
+	fn get_status_at_emisor(&self, port:usize) -> Option<&dyn StatusAtEmissor>
+	{
+		Some(&*self.transmission_port_status[port])
+	}
+	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size)
+	}
+	fn get_index(&self)->Option<usize>
+	{
+		Some(self.router_index)
+	}
+	fn aggregate_statistics(&self, statistics:Option<ConfigurationValue>, router_index:usize, total_routers:usize, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let cycle_span = cycle - self.statistics_begin_cycle;
+		let mut output_buffer_occupation_per_vc:Vec<f64> = self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect();
+		if let Some(previous)=statistics
+		{
+			match_object_panic!(&previous,"OutputQueued",value,
+				"average_output_buffer_occupation_per_vc" => match value
+				{
+					&ConfigurationValue::Array(ref prev_a) =>
+					{
+						for (c,p) in output_buffer_occupation_per_vc.iter_mut().zip(prev_a.iter())
+						{
+							if let &ConfigurationValue::Number(x)=p
+							{
+								*c += x;
+							}
+							else
+							{
+								panic!("The non-number {:?} cannot be added",p);
+							}
+						}
+					}
+					_ => panic!("bad value for average_output_buffer_occupation_per_vc"),
+				},
+			);
+		}
+		if router_index+1==total_routers
+		{
+			let factor=1f64 / total_routers as f64;
+			for x in output_buffer_occupation_per_vc.iter_mut()
+			{
+				*x *= factor;
+			}
+		}
+		let result_content = vec![
+			(String::from("average_output_buffer_occupation_per_vc"),ConfigurationValue::Array(output_buffer_occupation_per_vc.into_iter().map(ConfigurationValue::Number).collect())),
+		];
+		Some(ConfigurationValue::Object(String::from("OutputQueued"),result_content))
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.statistics_begin_cycle=next_cycle;
+		for x in self.statistics_output_buffer_occupation_per_vc.iter_mut()
+		{
+			*x=0f64;
+		}
+	}
+}
+
+impl OutputQueued
+{
+	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<OutputQueued>>
+	{
+		let RouterBuilderArgument{
+			router_index,
+			cv,
+			plugs,
+			topology,
+			maximum_packet_size,
+			..
+		} = arg;
+		let mut virtual_channels=None;
+		let mut virtual_channel_policies=None;
+		let mut buffer_size=None;
+		let mut output_buffer_size=None;
+		let mut flit_size=None;
+		match_object_panic!(cv,"OutputQueued",value,
+			"virtual_channels" => match value
+			{
+				&ConfigurationValue::Number(f) => virtual_channels=Some(f as usize),
+				_ => panic!("bad value for virtual_channels"),
+			},
+			"virtual_channel_policies" => match value
+			{
+				&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(
+					|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{cv,plugs})
+				).collect()),
+				_ => panic!("bad value for virtual_channel_policies"),
+			},
+			"buffer_size" => match value
+			{
+				&ConfigurationValue::Number(f) => buffer_size=Some(f as usize),
+				_ => panic!("bad value for buffer_size"),
+			},
+			"output_buffer_size" => match value
+			{
+				&ConfigurationValue::Number(f) => output_buffer_size=Some(f as usize),
+				_ => panic!("bad value for output_buffer_size"),
+			},
+			"flit_size" => match value
+			{
+				&ConfigurationValue::Number(f) => flit_size=Some(f as usize),
+				_ => panic!("bad value for flit_size"),
+			},
+		);
+		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
+		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let buffer_size=buffer_size.expect("There were no buffer_size");
+		let output_buffer_size=output_buffer_size.expect("There were no output_buffer_size");
+		let flit_size=flit_size.expect("There were no flit_size");
+		if output_buffer_size<flit_size.max(maximum_packet_size)
+		{
+			panic!("The output_buffer_size ({}) must be at least the flit_size ({}) and the maximum_packet_size ({}).",output_buffer_size,flit_size,maximum_packet_size);
+		}
+		let ports=topology.ports(router_index);
+		let transmission_mechanism = SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
+		let to_server_mechanism = TransmissionToServer();
+		let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
+		let (transmission_port_status,reception_port_space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+		let r=Rc::new(RefCell::new(OutputQueued{
+			self_rc: Weak::new(),
+			event_pending: false,
+			last_process_at_cycle: None,
+			router_index,
+			virtual_channel_policies,
+			flit_size,
+			buffer_size,
+			output_buffer_size,
+			transmission_port_status,
+			reception_port_space,
+			output_buffers: (0..ports).map(|_|(0..virtual_channels).map(|_|VecDeque::new()).collect()).collect(),
+			reserved_output_space: vec![vec![0;virtual_channels];ports],
+			selected_output: vec![vec![None;virtual_channels];ports],
+			port_token: vec![0;ports],
+			statistics_begin_cycle: 0,
+			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
+		}));
+		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
+		r
+	}
+	///Whether the output buffer at (`exit_port`,`exit_vc`) has space to reserve for the packet whose leading phit is `phit`.
+	fn output_space_for(&self, phit:&Phit, exit_port:usize, exit_vc:usize) -> bool
+	{
+		let necessary_space = phit.packet.size.max(self.flit_size);
+		self.output_buffer_size - self.reserved_output_space[exit_port][exit_vc] >= necessary_space
+	}
+	///The number of phits stored in the output buffer at (`exit_port`,`exit_vc`).
+	fn output_occupation(&self, exit_port:usize, exit_vc:usize) -> usize
+	{
+		self.output_buffers[exit_port][exit_vc].iter().map(|packet|packet.phits.len()).sum()
+	}
+	///Selects the output port and virtual channel for the packet whose leading phit is at the input (`entry_port`,`entry_vc`).
+	///Returns None when there is no output buffer available.
+	fn route(&self, phit:&Rc<Phit>, entry_port:usize, entry_vc:usize, simulation:&Simulation) -> Option<(usize,usize)>
+	{
+		let topology = simulation.network.topology.as_ref();
+		let amount_virtual_channels=self.num_virtual_channels();
+		let target_server=phit.packet.message.destination;
+		let target_router=match topology.server_neighbour(target_server).0
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		let routing_candidates=simulation.routing.next(&phit.packet.routing_info.borrow(),topology,self.router_index,target_server,amount_virtual_channels,&simulation.rng);
+		if routing_candidates.len()==0
+		{
+			if routing_candidates.idempotent
+			{
+				panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
+			}
+			return None;
+		}
+		let mut good_ports=routing_candidates.into_iter().filter_map(|candidate|{
+			let CandidateEgress{port:f_port,virtual_channel:f_virtual_channel,..} = candidate;
+			let allowed = self.output_space_for(phit,f_port,f_virtual_channel);
+			Some(CandidateEgress{router_allows:Some(allowed), ..candidate})
+		}).collect::<Vec<_>>();
+		let server_ports : Vec<usize> = (0..topology.ports(self.router_index)).filter(|&p|
+			matches!(topology.neighbour(self.router_index,p).0,Location::ServerPort(_))
+		).collect();
+		let port_last_transmission : Vec<usize> = self.transmission_port_status.iter().map(|p|p.get_last_transmission()).collect();
+		let virtual_channel_occupied_output_space : Vec<Vec<usize>> = self.reserved_output_space.clone();
+		let virtual_channel_available_output_space : Vec<Vec<usize>> = self.reserved_output_space.iter().map(|p|
+			p.iter().map(|reserved|self.output_buffer_size-reserved).collect()
+		).collect();
+		let port_occupied_output_space : Vec<usize> = virtual_channel_occupied_output_space.iter().map(|p|p.iter().sum()).collect();
+		let port_available_output_space : Vec<usize> = virtual_channel_available_output_space.iter().map(|p|p.iter().sum()).collect();
+		let request_info=RequestInfo{
+			target_router_index: target_router,
+			entry_port,
+			entry_virtual_channel: entry_vc,
+			performed_hops: phit.packet.routing_info.borrow().hops,
+			server_ports: Some(&server_ports),
+			port_average_neighbour_queue_length: None,
+			port_last_transmission: Some(&port_last_transmission),
+			port_occupied_output_space: Some(&port_occupied_output_space),
+			port_available_output_space: Some(&port_available_output_space),
+			virtual_channel_occupied_output_space: Some(&virtual_channel_occupied_output_space),
+			virtual_channel_available_output_space: Some(&virtual_channel_available_output_space),
+			time_at_front: None,
+			current_cycle: simulation.cycle,
+			phit: phit.clone(),
+		};
+		for vcp in self.virtual_channel_policies.iter()
+		{
+			good_ports=vcp.filter(good_ports,self,&request_info,topology,&simulation.rng);
+			if good_ports.len()==0
+			{
+				return None;
+			}
+		}
+		if good_ports.len()>=2
+		{
+			panic!("You need a VirtualChannelPolicy able to select a single (port,vc).");
+		}
+		simulation.routing.performed_request(&good_ports[0],&phit.packet.routing_info,topology,self.router_index,target_server,amount_virtual_channels,&simulation.rng);
+		Some((good_ports[0].port,good_ports[0].virtual_channel))
+	}
+}
+
+impl Eventful for OutputQueued
+{
+	///main routine of the router. Do all things that must be done in a cycle, if any.
+	fn process(&mut self, simulation:&Simulation) -> Vec<EventGeneration>
+	{
+		let mut cycles_span = 1;//cycles since last checked
+		if let Some(ref last)=self.last_process_at_cycle
+		{
+			cycles_span = simulation.cycle - *last;
+			if *last >= simulation.cycle
+			{
+				panic!("Trying to process at cycle {} a router::OutputQueued already processed at {}",simulation.cycle,last);
+			}
+		}
+		self.last_process_at_cycle = Some(simulation.cycle);
+		let amount_virtual_channels=self.num_virtual_channels();
+		let num_ports=self.transmission_port_status.len();
+		//-- gather cycle statistics
+		for port in 0..num_ports
+		{
+			for vc in 0..amount_virtual_channels
+			{
+				self.statistics_output_buffer_occupation_per_vc[vc] += (self.output_occupation(port,vc)*cycles_span) as f64 / num_ports as f64;
+			}
+		}
+		let mut events=vec![];
+
+		//-- Move every phit that can into its output buffer. The order of the inputs is random, to be fair when they compete for the space of the same output buffer.
+		let mut inputs:Vec<(usize,usize)> = (0..num_ports).flat_map(|port|(0..amount_virtual_channels).map(move |vc|(port,vc))).collect();
+		inputs.shuffle(simulation.rng.borrow_mut().deref_mut());
+		for (entry_port,entry_vc) in inputs
+		{
+			while let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+			{
+				let (exit_port,exit_vc) = match self.selected_output[entry_port][entry_vc]
+				{
+					Some(selection) => selection,
+					None =>
+					{
+						if !phit.is_begin()
+						{
+							panic!("The phit {} of a packet at port {} virtual channel {} of router {} has no output selected.",phit.index,entry_port,entry_vc,self.router_index);
+						}
+						match self.route(&phit,entry_port,entry_vc,simulation)
+						{
+							Some((exit_port,exit_vc)) =>
+							{
+								self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
+								(exit_port,exit_vc)
+							},
+							None => break,
+						}
+					},
+				};
+				if phit.is_begin()
+				{
+					//Reserve the space of the whole packet, so that the rest of its phits never wait for the output.
+					if !self.output_space_for(&phit,exit_port,exit_vc)
+					{
+						break;
+					}
+					self.reserved_output_space[exit_port][exit_vc]+=phit.packet.size;
+					self.output_buffers[exit_port][exit_vc].push_back(OutputPacket{
+						packet: phit.packet.clone(),
+						entry_port,
+						phits: VecDeque::new(),
+					});
+				}
+				let (phit,ack_message) = self.reception_port_space[entry_port].extract(entry_vc).expect("there was no phit to extract");
+				*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+				if let Some(message)=ack_message
+				{
+					let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+					events.push(EventGeneration{
+						delay: simulation.link_classes[previous_link_class].delay,
+						position:CyclePosition::Begin,
+						event:Event::Acknowledge{location:previous_location,message},
+					});
+				}
+				if phit.is_end()
+				{
+					self.selected_output[entry_port][entry_vc]=None;
+				}
+				let output_packet = self.output_buffers[exit_port][exit_vc].iter_mut().rev().find(|output_packet|Rc::ptr_eq(&output_packet.packet,&phit.packet)).expect("the packet has no space reserved in its output buffer");
+				output_packet.phits.push_back(phit);
+			}
+		}
+
+		//-- Send phits through the links. Packets that have begun their transmission have priority, so that they do not get spread.
+		for exit_port in 0..num_ports
+		{
+			let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
+			for _ in 0..simulation.link_classes[link_class].phits_at_cycle(simulation.cycle)
+			{
+				let candidates:Vec<(usize,bool)> = (0..amount_virtual_channels).filter_map(|vc|{
+					//Only the oldest packet of the buffer may be sent.
+					let phit = self.output_buffers[exit_port][vc].front()?.phits.front()?.clone();
+					if self.transmission_port_status[exit_port].can_transmit(&phit,vc)
+					{
+						Some((vc,phit.is_begin()))
+					}
+					else
+					{
+						None
+					}
+				}).collect();
+				let in_transit = candidates.iter().any(|&(_vc,is_begin)|!is_begin);
+				let token = self.port_token[exit_port];
+				//Choose in round-robin among the candidates with most priority.
+				let selected = candidates.iter().filter(|&&(_vc,is_begin)|!in_transit || !is_begin).map(|&(vc,_)|vc).min_by_key(|&vc|(vc+amount_virtual_channels-token)%amount_virtual_channels);
+				let selected_virtual_channel = match selected
+				{
+					Some(vc) => vc,
+					None => break,
+				};
+				let buffer = &mut self.output_buffers[exit_port][selected_virtual_channel];
+				let output_packet = buffer.front_mut().expect("the selected output buffer is empty");
+				let entry_port = output_packet.entry_port;
+				let phit = output_packet.phits.pop_front().expect("the selected output buffer is empty");
+				self.reserved_output_space[exit_port][selected_virtual_channel]-=1;
+				if phit.is_end()
+				{
+					buffer.pop_front();
+					self.port_token[exit_port]=(selected_virtual_channel+1)%amount_virtual_channels;
+				}
+				events.push(EventGeneration{
+					delay: simulation.link_classes[link_class].delay,
+					position:CyclePosition::Begin,
+					event:Event::PhitToLocation{
+						phit,
+						previous: Location::RouterPort{
+							router_index: self.router_index,
+							router_port: entry_port,
+						},
+						new: new_location.clone(),
+					},
+				});
+				self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+			}
+		}
+
+		//Keep working while there are phits inside.
+		let stored_phits = self.reception_port_space.iter().any(|space|space.front_iter().next().is_some())
+			|| self.output_buffers.iter().any(|port|port.iter().any(|buffer|!buffer.is_empty()));
+		if stored_phits || events.len()>0
+		{
+			events.push(EventGeneration{
+				delay:1,
+				position:CyclePosition::End,
+				event:Event::Generic(self.as_eventful().upgrade().expect("missing router")),
+			});
+		}
+		else
+		{
+			self.clear_pending_events();
+		}
+		events
+	}
+	fn pending_events(&self)->usize
+	{
+		if self.event_pending { 1 } else { 0 }
+	}
+	fn add_pending_event(&mut self)
+	{
+		self.event_pending=true;
+	}
+	fn clear_pending_events(&mut self)
+	{
+		self.event_pending=false;
+	}
+	fn as_eventful(&self)->Weak<RefCell<dyn Eventful>>
+	{
+		self.self_rc.clone()
+	}
+}
+
+impl Quantifiable for OutputQueued
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<OutputQueued>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::tests::{test_plugs,new_simulation,send_message,advance_until,last_consumption};
+
+	///Two routers with servers 0,1,2 and 3,4,5, joined by a link that carries a phit every 4 cycles.
+	const CONFIGURATION: &str = "Configuration
+	{
+		random_seed: 1,
+		warmup: 0,
+		measured: 1000,
+		topology: Hamming { sides: [2], servers_per_router: 3 },
+		traffic: Scripted,
+		maximum_packet_size: 16,
+		router: OutputQueued{ virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], buffer_size: 64, output_buffer_size: 64, flit_size: 16 },
+		routing: Shortest,
+		link_classes: [ LinkClass { delay: 1, transference_speed: [1,4] }, LinkClass { delay: 1 } ],
+		launch_configurations: [],
+	}";
+
+	///The cycle in which the server 2 consumes the second packet of the server 1, whose first packet goes to the server 0.
+	///With `contention` the servers 3 and 2 are also writing packets into the output towards the server 0 when the first packet arrives, the one from the server 3 slowly.
+	fn second_packet_arrival(contention:bool) -> usize
+	{
+		let plugs = test_plugs();
+		let mut simulation = new_simulation(CONFIGURATION,&plugs);
+		if contention
+		{
+			send_message(&mut simulation,3,0,16);
+		}
+		advance_until(&mut simulation,10);
+		if contention
+		{
+			send_message(&mut simulation,2,0,16);
+		}
+		send_message(&mut simulation,1,0,16);
+		send_message(&mut simulation,1,2,16);
+		advance_until(&mut simulation,300);
+		let senders = if contention { 3 } else { 1 };
+		assert_eq!(simulation.network.servers[0].statistics.consumed_messages,senders);
+		assert_eq!(simulation.network.servers[2].statistics.consumed_messages,1);
+		last_consumption(&simulation,2)
+	}
+
+	#[test]
+	fn inputs_write_into_the_same_output_at_once()
+	{
+		let alone = second_packet_arrival(false);
+		assert_eq!(second_packet_arrival(true),alone,"the packet towards a free output has been delayed by the inputs writing into another output");
+	}
+}
@@ -0,0 +1,605 @@
+
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+use std::ops::DerefMut;
+use std::mem::{size_of};
+use ::rand::{rngs::StdRng,prelude::SliceRandom};
+use super::{Router,StatusAtEmissor,SpaceAtReceptor,TransmissionToServer,TransmissionFromServer,SimpleVirtualChannels,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_port_mechanisms};
+use super::allocator::{SwitchAllocator,SwitchRequest,new_switch_allocator,SwitchAllocatorBuilderArgument,maximum_matching_size};
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Location};
+use crate::routing::CandidateEgress;
+use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::event::{Event,Eventful,EventGeneration,CyclePosition};
+use crate::{Phit,Simulation,match_object_panic};
+use crate::quantify::Quantifiable;
+
+/**
+A router with virtual output queues (VOQ) at its inputs. The space of each virtual channel of each input port is divided into a queue for each output port, so a packet waiting for an output does not block the packets behind it going to other outputs.
+
+Packets are routed as soon as their leading phit reaches the input, and they are moved into the queue of the selected output port. The switch allocator sees the heads of all the queues. The space of the input is released, and notified to the previous router, when the phits leave the router, so the credits keep counting all the phits of the virtual channel.
+
+```ignore
+VirtualOutputQueued{
+	virtual_channels: 4,
+	virtual_channel_policies: [EnforceFlowControl,Random],
+	buffer_size: 64,//phits of each virtual channel of each input port, shared by its queues.
+	flit_size: 16,//phits that must fit in the next buffer to begin the transmission of a packet.
+	switch_allocator: Token,//optional, this is the default, as in the Basic router.
+}
+```
+**/
+pub struct VirtualOutputQueued
+{
+	///Weak pointer to itself, see https://users.rust-lang.org/t/making-a-rc-refcell-trait2-from-rc-refcell-trait1/16086/3
+	self_rc: Weak<RefCell<VirtualOutputQueued>>,
+	///If there is an event pending
+	event_pending: bool,
+	///The cycle number of the last time VirtualOutputQueued::process was called. Only for debugging/assertion purposes.
+	last_process_at_cycle: Option<usize>,
+	///Its index in the topology
+	router_index: usize,
+	///The mechanism to select virtual channels
+	virtual_channel_policies: Vec<Box<dyn VirtualChannelPolicy>>,
+	///Size of each input buffer.
+	buffer_size: usize,
+	///transmission_port_status[port] = status
+	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	///reception_port_space[port] = space. Phits stay here until they are routed.
+	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
+	///The queues indexed as [entry_port][entry_vc][exit_port].
+	///Phits are stored with their exit virtual channel and the acknowledge to send when they leave the router.
+	virtual_output_queues: Vec<Vec<Vec<AugmentedBuffer<(usize,Option<AcknowledgeMessage>)>>>>,
+	///If not None then the exit port+virtual_channel selected for the packet being moved from the reception space into its queue.
+	classification: Vec<Vec<Option<(usize,usize)>>>,
+	///If not None then the input port+virtual_channel whose packet is being sent through this exit port+virtual_channel.
+	selected_input: Vec<Vec<Option<(usize,usize)>>>,
+	///The allocator of the crossbar.
+	switch_allocator: Box<dyn SwitchAllocator>,
+
+	//statistics:
+	///The first cycle included in the statistics.
+	statistics_begin_cycle: usize,
+	///Accumulated over time. Phits stored in the queues of each virtual channel, averaged per port.
+	statistics_queue_occupation_per_vc: Vec<f64>,
+	///Accumulated over time. Number of requests granted by the switch allocator.
+	statistics_switch_allocation_grants: f64,
+	///Accumulated over time. Size of the maximum matching of the requests to the switch allocator.
+	statistics_switch_allocation_maximum_matching: f64,
+}
+
+impl Router for VirtualOutputQueued
+{
+	fn insert(&mut self, phit:Rc<Phit>, port:usize, rng: &RefCell<StdRng>)
+	{
+		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+	}
+	fn acknowledge(&mut self, port:usize, ack_message:AcknowledgeMessage)
+	{
+		self.transmission_port_status[port].acknowledge(ack_message);
+	}
+	fn num_virtual_channels(&self) -> usize
+	{
+		self.transmission_port_status[0].num_virtual_channels()
+	}
+	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
+	{
+		self.buffer_size
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		let input_phits = self.reception_port_space.iter().flat_map(|space|space.iter_phits());
+		let queued_phits = self.virtual_output_queues.iter().flat_map(|port|port.iter().flat_map(|vc|vc.iter().flat_map(|queue|queue.iter_phits())));
+		Box::new(input_phits.chain(queued_phits).collect::<Vec<_>>().into_iter())
+	}
+	fn get_status_at_emisor(&self, port:usize) -> Option<&dyn StatusAtEmissor>
+	{
+		Some(&*self.transmission_port_status[port])
+	}
+	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size)
+	}
+	fn get_index(&self)->Option<usize>
+	{
+		Some(self.router_index)
+	}
+	fn aggregate_statistics(&self, statistics:Option<ConfigurationValue>, router_index:usize, total_routers:usize, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let cycle_span = (cycle - self.statistics_begin_cycle) as f64;
+		let mut queue_occupation_per_vc:Vec<f64> = self.statistics_queue_occupation_per_vc.iter().map(|x|x/cycle_span).collect();
+		let mut switch_allocation_grants = self.statistics_switch_allocation_grants/cycle_span;
+		let mut switch_allocation_maximum_matching = self.statistics_switch_allocation_maximum_matching/cycle_span;
+		if let Some(previous)=statistics
+		{
+			match_object_panic!(&previous,"VirtualOutputQueued",value,
+				"average_queue_occupation_per_vc" => match value
+				{
+					&ConfigurationValue::Array(ref prev_a) =>
+					{
+						for (c,p) in queue_occupation_per_vc.iter_mut().zip(prev_a.iter())
+						{
+							if let &ConfigurationValue::Number(x)=p
+							{
+								*c += x;
+							}
+							else
+							{
+								panic!("The non-number {:?} cannot be added",p);
+							}
+						}
+					}
+					_ => panic!("bad value for average_queue_occupation_per_vc"),
+				},
+				"average_switch_allocation_grants" => match value
+				{
+					&ConfigurationValue::Number(x) => switch_allocation_grants += x,
+					_ => panic!("bad value for average_switch_allocation_grants"),
+				},
+				"average_switch_allocation_maximum_matching" => match value
+				{
+					&ConfigurationValue::Number(x) => switch_allocation_maximum_matching += x,
+					_ => panic!("bad value for average_switch_allocation_maximum_matching"),
+				},
+			);
+		}
+		let mut result_content = vec![];
+		if router_index+1==total_routers
+		{
+			//The ratio of granted requests to the best possible matching. The averaging factor cancels out.
+			let switch_allocation_efficiency = if switch_allocation_maximum_matching>0f64 { switch_allocation_grants/switch_allocation_maximum_matching } else { 1f64 };
+			let factor=1f64 / total_routers as f64;
+			for x in queue_occupation_per_vc.iter_mut()
+			{
+				*x *= factor;
+			}
+			switch_allocation_grants *= factor;
+			switch_allocation_maximum_matching *= factor;
+			result_content.push((String::from("switch_allocation_efficiency"),ConfigurationValue::Number(switch_allocation_efficiency)));
+		}
+		result_content.push((String::from("average_queue_occupation_per_vc"),ConfigurationValue::Array(queue_occupation_per_vc.into_iter().map(ConfigurationValue::Number).collect())));
+		result_content.push((String::from("average_switch_allocation_grants"),ConfigurationValue::Number(switch_allocation_grants)));
+		result_content.push((String::from("average_switch_allocation_maximum_matching"),ConfigurationValue::Number(switch_allocation_maximum_matching)));
+		Some(ConfigurationValue::Object(String::from("VirtualOutputQueued"),result_content))
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.statistics_begin_cycle=next_cycle;
+		for x in self.statistics_queue_occupation_per_vc.iter_mut()
+		{
+			*x=0f64;
+		}
+		self.statistics_switch_allocation_grants=0f64;
+		self.statistics_switch_allocation_maximum_matching=0f64;
+	}
+}
+
+impl VirtualOutputQueued
+{
+	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<VirtualOutputQueued>>
+	{
+		let RouterBuilderArgument{
+			router_index,
+			cv,
+			plugs,
+			topology,
+			..
+		} = arg;
+		let mut virtual_channels=None;
+		let mut virtual_channel_policies=None;
+		let mut buffer_size=None;
+		let mut flit_size=None;
+		let mut switch_allocator=None;
+		match_object_panic!(cv,"VirtualOutputQueued",value,
+			"virtual_channels" => match value
+			{
+				&ConfigurationValue::Number(f) => virtual_channels=Some(f as usize),
+				_ => panic!("bad value for virtual_channels"),
+			},
+			"virtual_channel_policies" => match value
+			{
+				&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(
+					|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{cv,plugs})
+				).collect()),
+				_ => panic!("bad value for virtual_channel_policies"),
+			},
+			"buffer_size" => match value
+			{
+				&ConfigurationValue::Number(f) => buffer_size=Some(f as usize),
+				_ => panic!("bad value for buffer_size"),
+			},
+			"flit_size" => match value
+			{
+				&ConfigurationValue::Number(f) => flit_size=Some(f as usize),
+				_ => panic!("bad value for flit_size"),
+			},
+			"switch_allocator" => switch_allocator=Some(value),
+		);
+		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
+		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let buffer_size=buffer_size.expect("There were no buffer_size");
+		let flit_size=flit_size.expect("There were no flit_size");
+		let ports=topology.ports(router_index);
+		let default_switch_allocator=ConfigurationValue::Object(String::from("Token"),vec![]);
+		let switch_allocator=new_switch_allocator(SwitchAllocatorBuilderArgument{
+			cv:switch_allocator.unwrap_or(&default_switch_allocator),
+			plugs,
+			num_ports:ports,
+			num_virtual_channels:virtual_channels,
+		});
+		let transmission_mechanism = SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
+		let to_server_mechanism = TransmissionToServer();
+		let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
+		let (transmission_port_status,reception_port_space) = new_port_mechanisms(&transmission_mechanism,&to_server_mechanism,&from_server_mechanism,topology,router_index);
+		let r=Rc::new(RefCell::new(VirtualOutputQueued{
+			self_rc: Weak::new(),
+			event_pending: false,
+			last_process_at_cycle: None,
+			router_index,
+			virtual_channel_policies,
+			buffer_size,
+			transmission_port_status,
+			reception_port_space,
+			virtual_output_queues: (0..ports).map(|_|(0..virtual_channels).map(|_|(0..ports).map(|_|AugmentedBuffer::new()).collect()).collect()).collect(),
+			classification: vec![vec![None;virtual_channels];ports],
+			selected_input: vec![vec![None;virtual_channels];ports],
+			switch_allocator,
+			statistics_begin_cycle: 0,
+			statistics_queue_occupation_per_vc: vec![0f64;virtual_channels],
+			statistics_switch_allocation_grants: 0f64,
+			statistics_switch_allocation_maximum_matching: 0f64,
+		}));
+		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
+		r
+	}
+	///Selects the output port and virtual channel for the packet whose leading phit is at the input (`entry_port`,`entry_vc`).
+	///The routing is not restricted by the state of the outputs, since the packet is going to wait in its own queue.
+	fn route(&self, phit:&Rc<Phit>, entry_port:usize, entry_vc:usize, simulation:&Simulation) -> Option<(usize,usize)>
+	{
+		let topology = simulation.network.topology.as_ref();
+		let amount_virtual_channels=self.num_virtual_channels();
+		let target_server=phit.packet.message.destination;
+		let target_router=match topology.server_neighbour(target_server).0
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		let routing_candidates=simulation.routing.next(&phit.packet.routing_info.borrow(),topology,self.router_index,target_server,amount_virtual_channels,&simulation.rng);
+		if routing_candidates.len()==0
+		{
+			if routing_candidates.idempotent
+			{
+				panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
+			}
+			return None;
+		}
+		let mut good_ports=routing_candidates.into_iter().collect::<Vec<CandidateEgress>>();
+		let server_ports : Vec<usize> = (0..topology.ports(self.router_index)).filter(|&p|
+			matches!(topology.neighbour(self.router_index,p).0,Location::ServerPort(_))
+		).collect();
+		let port_last_transmission : Vec<usize> = self.transmission_port_status.iter().map(|p|p.get_last_transmission()).collect();
+		let request_info=RequestInfo{
+			target_router_index: target_router,
+			entry_port,
+			entry_virtual_channel: entry_vc,
+			performed_hops: phit.packet.routing_info.borrow().hops,
+			server_ports: Some(&server_ports),
+			port_average_neighbour_queue_length: None,
+			port_last_transmission: Some(&port_last_transmission),
+			port_occupied_output_space: None,
+			port_available_output_space: None,
+			virtual_channel_occupied_output_space: None,
+			virtual_channel_available_output_space: None,
+			time_at_front: None,
+			current_cycle: simulation.cycle,
+			phit: phit.clone(),
+		};
+		for vcp in self.virtual_channel_policies.iter()
+		{
+			good_ports=vcp.filter(good_ports,self,&request_info,topology,&simulation.rng);
+			if good_ports.len()==0
+			{
+				return None;
+			}
+		}
+		if good_ports.len()>=2
+		{
+			panic!("You need a VirtualChannelPolicy able to select a single (port,vc).");
+		}
+		simulation.routing.performed_request(&good_ports[0],&phit.packet.routing_info,topology,self.router_index,target_server,amount_virtual_channels,&simulation.rng);
+		Some((good_ports[0].port,good_ports[0].virtual_channel))
+	}
+	///Runs the switch allocator over the requests, returning the grants and the size of a maximum matching.
+	///Each input virtual channel has several queues but it can only send a phit at a time. When the allocator does not already
+	///restrict the entry ports, the grants conflicting in the input virtual channel are discarded and the allocation is repeated
+	///for the remaining exit ports.
+	fn allocate(&mut self, mut requests:Vec<SwitchRequest>, amount_virtual_channels:usize, rng:&RefCell<StdRng>) -> (Vec<SwitchRequest>,usize)
+	{
+		if self.switch_allocator.input_exclusive()
+		{
+			let maximum_matching = maximum_matching_size(&requests,true);
+			return (self.switch_allocator.allocate(requests,rng),maximum_matching);
+		}
+		//Match the input virtual channels instead of the input ports.
+		let as_input_channels:Vec<SwitchRequest> = requests.iter().map(|r|SwitchRequest{entry_port:r.entry_port*amount_virtual_channels+r.entry_virtual_channel,..r.clone()}).collect();
+		let maximum_matching = maximum_matching_size(&as_input_channels,true);
+		let mut result:Vec<SwitchRequest> = vec![];
+		while !requests.is_empty()
+		{
+			let mut new_grant=false;
+			for grant in self.switch_allocator.allocate(requests.clone(),rng)
+			{
+				if !result.iter().any(|g|g.entry_port==grant.entry_port && g.entry_virtual_channel==grant.entry_virtual_channel)
+				{
+					result.push(grant);
+					new_grant=true;
+				}
+			}
+			if !new_grant
+			{
+				break;
+			}
+			requests.retain(|r|!result.iter().any(|g|g.exit_port==r.exit_port || (g.entry_port==r.entry_port && g.entry_virtual_channel==r.entry_virtual_channel)));
+		}
+		(result,maximum_matching)
+	}
+	///The acknowledge to send to a server when a phit of `entry_port` leaves the router.
+	///The space of the reception is computed again, since the phits in the queues are still occupying it.
+	fn server_acknowledge(&self, entry_port:usize) -> AcknowledgeMessage
+	{
+		let space=&self.reception_port_space[entry_port];
+		let available_size = self.virtual_output_queues[entry_port].iter().enumerate().map(|(vc,queues)|{
+			let queued:usize = queues.iter().map(|queue|queue.len()).sum();
+			space.available_dedicated_space(vc).expect("needs to know available space").saturating_sub(queued)
+		}).max().expect("no virtual channels");
+		//As in the reception from servers, account for the phits in-flight.
+		AcknowledgeMessage::ack_fix_available_size(available_size.saturating_sub(2))
+	}
+}
+
+impl Eventful for VirtualOutputQueued
+{
+	///main routine of the router. Do all things that must be done in a cycle, if any.
+	fn process(&mut self, simulation:&Simulation) -> Vec<EventGeneration>
+	{
+		let mut cycles_span = 1;//cycles since last checked
+		if let Some(ref last)=self.last_process_at_cycle
+		{
+			cycles_span = simulation.cycle - *last;
+			if *last >= simulation.cycle
+			{
+				panic!("Trying to process at cycle {} a router::VirtualOutputQueued already processed at {}",simulation.cycle,last);
+			}
+		}
+		self.last_process_at_cycle = Some(simulation.cycle);
+		let amount_virtual_channels=self.num_virtual_channels();
+		let num_ports=self.transmission_port_status.len();
+		//-- gather cycle statistics
+		for port in self.virtual_output_queues.iter()
+		{
+			for (vc,queues) in port.iter().enumerate()
+			{
+				let queued:usize = queues.iter().map(|queue|queue.len()).sum();
+				self.statistics_queue_occupation_per_vc[vc] += (queued*cycles_span) as f64 / num_ports as f64;
+			}
+		}
+		let mut events=vec![];
+
+		//-- Route the arriving packets into their queues.
+		let mut inputs:Vec<(usize,usize)> = (0..num_ports).flat_map(|port|(0..amount_virtual_channels).map(move |vc|(port,vc))).collect();
+		inputs.shuffle(simulation.rng.borrow_mut().deref_mut());
+		for (entry_port,entry_vc) in inputs
+		{
+			while let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+			{
+				let (exit_port,exit_vc) = match self.classification[entry_port][entry_vc]
+				{
+					Some(selection) => selection,
+					None =>
+					{
+						if !phit.is_begin()
+						{
+							panic!("The phit {} of a packet at port {} virtual channel {} of router {} has no output selected.",phit.index,entry_port,entry_vc,self.router_index);
+						}
+						match self.route(&phit,entry_port,entry_vc,simulation)
+						{
+							Some(selection) =>
+							{
+								self.classification[entry_port][entry_vc]=Some(selection);
+								selection
+							},
+							None => break,
+						}
+					},
+				};
+				let (phit,ack_message) = self.reception_port_space[entry_port].extract(entry_vc).expect("there was no phit to extract");
+				if phit.is_end()
+				{
+					self.classification[entry_port][entry_vc]=None;
+				}
+				self.virtual_output_queues[entry_port][entry_vc][exit_port].push(phit,(exit_vc,ack_message));
+			}
+		}
+
+		//-- Allocate the crossbar among the heads of the queues. Links faster than a phit per cycle require several rounds.
+		let link_phits:Vec<usize> = (0..num_ports).map(|port|{
+			let (_location,link_class)=simulation.network.topology.neighbour(self.router_index,port);
+			simulation.link_classes[link_class].phits_at_cycle(simulation.cycle)
+		}).collect();
+		let mut port_budget=link_phits.clone();
+		let mut round=0;
+		while link_phits.iter().any(|&phits|phits>round)
+		{
+			let mut switch_requests=vec![];
+			for entry_port in 0..num_ports
+			{
+				for entry_vc in 0..amount_virtual_channels
+				{
+					for exit_port in 0..num_ports
+					{
+						if port_budget[exit_port]==0 || link_phits[exit_port]<=round
+						{
+							continue;
+						}
+						if let Some((phit,(exit_vc,_ack))) = self.virtual_output_queues[entry_port][entry_vc][exit_port].front()
+						{
+							//A packet must go entirely before another packet uses the same exit virtual channel.
+							let owner_ok = if phit.is_begin()
+							{
+								self.selected_input[exit_port][exit_vc].is_none()
+							}
+							else
+							{
+								self.selected_input[exit_port][exit_vc]==Some((entry_port,entry_vc))
+							};
+							if owner_ok && self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc)
+							{
+								switch_requests.push(SwitchRequest{
+									entry_port,
+									entry_virtual_channel: entry_vc,
+									exit_port,
+									exit_virtual_channel: exit_vc,
+									age: simulation.cycle - *phit.packet.cycle_into_network.borrow(),
+									last_phit: phit.is_end(),
+								});
+							}
+						}
+					}
+				}
+			}
+			let (grants,maximum_matching) = self.allocate(switch_requests,amount_virtual_channels,&simulation.rng);
+			self.statistics_switch_allocation_grants += grants.len() as f64;
+			self.statistics_switch_allocation_maximum_matching += maximum_matching as f64;
+			if grants.is_empty()
+			{
+				break;
+			}
+			for SwitchRequest{entry_port,entry_virtual_channel:entry_vc,exit_port,exit_virtual_channel:exit_vc,..} in grants
+			{
+				let (phit,(_exit_vc,ack_message))=self.virtual_output_queues[entry_port][entry_vc][exit_port].pop().expect("the granted queue is empty");
+				*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+				self.selected_input[exit_port][exit_vc] = if phit.is_end() { None } else { Some((entry_port,entry_vc)) };
+				//The space in the input is released now.
+				let ack_message = match ack_message
+				{
+					Some(AcknowledgeMessage{set_available_size:Some(_),..}) => Some(self.server_acknowledge(entry_port)),
+					message => message,
+				};
+				if let Some(message)=ack_message
+				{
+					let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+					events.push(EventGeneration{
+						delay: simulation.link_classes[previous_link_class].delay,
+						position:CyclePosition::Begin,
+						event:Event::Acknowledge{location:previous_location,message},
+					});
+				}
+				let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
+				events.push(EventGeneration{
+					delay: simulation.link_classes[link_class].delay,
+					position:CyclePosition::Begin,
+					event:Event::PhitToLocation{
+						phit,
+						previous: Location::RouterPort{
+							router_index: self.router_index,
+							router_port: entry_port,
+						},
+						new: new_location,
+					},
+				});
+				self.transmission_port_status[exit_port].notify_outcoming_phit(exit_vc,simulation.cycle);
+				port_budget[exit_port]-=1;
+			}
+			round+=1;
+		}
+
+		//Keep working while there are phits inside.
+		let stored_phits = self.reception_port_space.iter().any(|space|space.front_iter().next().is_some())
+			|| self.virtual_output_queues.iter().any(|port|port.iter().any(|vc|vc.iter().any(|queue|queue.len()>0)));
+		if stored_phits || events.len()>0
+		{
+			events.push(EventGeneration{
+				delay:1,
+				position:CyclePosition::End,
+				event:Event::Generic(self.as_eventful().upgrade().expect("missing router")),
+			});
+		}
+		else
+		{
+			self.clear_pending_events();
+		}
+		events
+	}
+	fn pending_events(&self)->usize
+	{
+		if self.event_pending { 1 } else { 0 }
+	}
+	fn add_pending_event(&mut self)
+	{
+		self.event_pending=true;
+	}
+	fn clear_pending_events(&mut self)
+	{
+		self.event_pending=false;
+	}
+	fn as_eventful(&self)->Weak<RefCell<dyn Eventful>>
+	{
+		self.self_rc.clone()
+	}
+}
+
+impl Quantifiable for VirtualOutputQueued
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<VirtualOutputQueued>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::tests::{test_plugs,new_simulation,send_message,advance_until};
+
+	#[test]
+	fn input_space_is_released_when_the_phits_leave()
+	{
+		let plugs = test_plugs();
+		//The link between the routers carries a phit every 4 cycles, so the input from the server 0 fills up.
+		let mut simulation = new_simulation("Configuration
+		{
+			random_seed: 1,
+			warmup: 0,
+			measured: 1000,
+			topology: Hamming { sides: [2], servers_per_router: 1 },
+			traffic: Scripted,
+			maximum_packet_size: 16,
+			router: VirtualOutputQueued { virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], buffer_size: 32, flit_size: 16 },
+			routing: Shortest,
+			link_classes: [ LinkClass { delay: 1, transference_speed: [1,4] }, LinkClass { delay: 1 } ],
+			launch_configurations: [],
+		}",&plugs);
+		send_message(&mut simulation,0,1,320);
+		for cycle in (20..400).step_by(20)
+		{
+			advance_until(&mut simulation,cycle);
+			let created = simulation.network.servers[0].statistics.created_phits;
+			let consumed = simulation.network.servers[1].statistics.consumed_phits;
+			//Besides the input buffer, a few phits may be in the links or the second router.
+			assert!(created <= consumed + 32 + 4,"{} phits created and {} consumed at cycle {}",created,consumed,cycle);
+			if cycle>=100
+			{
+				//The server waits for space for a whole packet, so the buffer keeps most of a packet.
+				assert!(created >= consumed + 12,"the input buffer should be full at cycle {}, with {} phits created and {} consumed",cycle,created,consumed);
+			}
+		}
+	}
+}
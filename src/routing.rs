@@ -7,7 +7,8 @@ see [`new_routing`](fn.new_routing.html) for documentation on the configuration
 
 */
 
-use std::cell::RefCell;
+use std::cell::{Cell,RefCell};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::convert::TryFrom;
 use std::ops::DerefMut;
@@ -23,7 +24,7 @@ use crate::Plugs;
 
 ///Information stored in the packet for the `Routing` algorithms to operate.
 #[derive(Quantifiable)]
-#[derive(Debug)]
+#[derive(Clone,Debug)]
 pub struct RoutingInfo
 {
 	///Number of edges traversed (Router--Router). It is computed by the advance routine of the simulator.
@@ -159,6 +160,24 @@ pub trait Routing : Debug
 	fn initialize(&mut self, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>);
 	///To be called by the router when one of the candidates is requested.
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_server:usize, num_virtual_channels:usize, rng:&RefCell<StdRng>);
+	///To be called by the server when it consumes the last phit of a packet. It gives end-to-end feedback to the routing.
+	///`network_delay` is the number of cycles since the packet entered the first router.
+	///`ecn_marked` tells whether some router marked the packet with ECN along its way.
+	///By default it does nothing.
+	fn performed_consumption(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _target_server:usize, _network_delay:usize, _ecn_marked:bool, _cycle:usize, _rng:&RefCell<StdRng>)
+	{
+	}
+	///To be called when a packet leaves the network without being consumed, because a lossy router dropped it or the retransmission protocol of its destination discarded it.
+	///It gives no feedback about the path, but lets the routing forget the packet. By default it does nothing.
+	fn performed_loss(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _target_server:usize, _cycle:usize, _rng:&RefCell<StdRng>)
+	{
+	}
+	///Indicates if going from `input_port` to `output_port` of a router leaves the ring or dimension of the packet, requiring the bubble in routers that apply it.
+	///By default it is the `Topology::is_direction_change`. Routings such as `BubbleRings` can declare other rings.
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		topology.is_direction_change(router_index,input_port,output_port)
+	}
 	///To optionally write routing statistics into the simulation output.
 	fn statistics(&self,cycle:usize) -> Option<ConfigurationValue>;
 	///Clears all collected statistics
@@ -276,6 +295,18 @@ AscendantChannelsWithLinkClass{
 }
 ```
 
+### BubbleRings declares the rings for the bubble flow control of the routers
+By default the routers with `bubble: true` require the bubble where `Topology::is_direction_change`, which is only meaningful in Cartesian topologies. This routing keeps the candidates of `routing` and declares which ports form rings, with either `LinkClass`, to take each link class as a ring, or `Ports{rings:[...]}`, to give the ring of each port index. The bubble is required when entering a ring from a server or from another ring.
+A ring cannot span several link classes, nor can the same link class hold several rings except by giving the ports. Thus it does not apply to the dimensions of a Hamming, which are cliques, nor to the global links of a dragonfly, which do not form rings by their link class.
+```
+BubbleRings{
+	routing: Shortest,
+	membership: LinkClass,//each link class a ring, as the dimensions of a torus. The dimensions of a Hamming are cliques, not rings.
+	//membership: Ports{rings:[0,0,1,1]},//as in a 2D torus, including twisted ones
+	legend_name: "minimal with bubbles on the link classes",
+}
+```
+
 ### Stubborn makes a routing to calculate candidates just once. If that candidate is not accepted is trying again every cycle.
 ```
 Stubborn{
@@ -363,6 +394,7 @@ pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 			"ChannelsPerHopPerLinkClass" => Box::new(ChannelsPerHopPerLinkClass::new(arg)),
 			"AscendantChannelsWithLinkClass" => Box::new(AscendantChannelsWithLinkClass::new(arg)),
 			"ChannelMap" => Box::new(ChannelMap::new(arg)),
+			"BubbleRings" => Box::new(BubbleRings::new(arg)),
 			_ => panic!("Unknown Routing {}",cv_name),
 		}
 	}
@@ -787,6 +819,11 @@ pub trait SourceRouting
 {
 	fn initialize(&mut self, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>);
 	fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>;
+	///As `Routing::is_direction_change`. By default it is the `Topology::is_direction_change`.
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		topology.is_direction_change(router_index,input_port,output_port)
+	}
 }
 
 pub trait InstantiableSourceRouting : SourceRouting + Debug {}
@@ -877,6 +914,10 @@ impl<R:SourceRouting+Debug> Routing for R
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_server:usize, _num_virtual_channels:usize, _rng:&RefCell<StdRng>)
 	{
 	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		SourceRouting::is_direction_change(self,topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
 		return None;
@@ -1126,6 +1167,36 @@ impl Routing for SumRouting
 		}
 		//TODO: recurse over subroutings
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		let bri=routing_info.borrow();
+		if let (Some(ref selections),Some(ref meta)) = (&bri.selections,&bri.meta)
+		{
+			//Only the routing that has been finally used receives the feedback.
+			if selections.len()==1
+			{
+				let s = selections[0] as usize;
+				self.routing[s].performed_consumption(&meta[s],topology,target_server,network_delay,ecn_marked,cycle,rng);
+			}
+		}
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		let bri=routing_info.borrow();
+		if let (Some(ref selections),Some(ref meta)) = (&bri.selections,&bri.meta)
+		{
+			if selections.len()==1
+			{
+				let s = selections[0] as usize;
+				self.routing[s].performed_loss(&meta[s],topology,target_server,cycle,rng);
+			}
+		}
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		//The packet may be using either routing, so require the bubble when any of them would.
+		self.routing.iter().any(|routing|routing.is_direction_change(topology,router_index,input_port,output_port))
+	}
 	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
 		return None;
@@ -1529,6 +1600,24 @@ impl Routing for Stubborn
 		}
 		//otherwise it is direct to server
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		if let Some(ref meta)=routing_info.borrow().meta
+		{
+			self.routing.performed_consumption(&meta[0],topology,target_server,network_delay,ecn_marked,cycle,rng);
+		}
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		if let Some(ref meta)=routing_info.borrow().meta
+		{
+			self.routing.performed_loss(&meta[0],topology,target_server,cycle,rng);
+		}
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
 		return None;
@@ -1924,6 +2013,18 @@ impl Routing for ChannelsPerHop
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_server,num_virtual_channels,rng);
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_consumption(routing_info,topology,target_server,network_delay,ecn_marked,cycle,rng);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_loss(routing_info,topology,target_server,cycle,rng);
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
 	{
 		self.routing.statistics(cycle)
@@ -2045,6 +2146,18 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_server,num_virtual_channels,rng);
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_consumption(&routing_info.borrow().meta.as_ref().unwrap()[0],topology,target_server,network_delay,ecn_marked,cycle,rng);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_loss(&routing_info.borrow().meta.as_ref().unwrap()[0],topology,target_server,cycle,rng);
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
 	{
 		self.routing.statistics(cycle)
@@ -2167,6 +2280,18 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_server,num_virtual_channels,rng);
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_consumption(&routing_info.borrow().meta.as_ref().unwrap()[0],topology,target_server,network_delay,ecn_marked,cycle,rng);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_loss(&routing_info.borrow().meta.as_ref().unwrap()[0],topology,target_server,cycle,rng);
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
 	{
 		self.routing.statistics(cycle)
@@ -2267,6 +2392,18 @@ impl Routing for ChannelMap
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_server,self.map.len(),rng);
 	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_consumption(routing_info,topology,target_server,network_delay,ecn_marked,cycle,rng);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_loss(routing_info,topology,target_server,cycle,rng);
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
 	{
 		self.routing.statistics(cycle)
@@ -2325,6 +2462,168 @@ impl ChannelMap
 }
 
 
+///The rings of the network to which each port belongs, as declared to the `BubbleRings` routing.
+#[derive(Debug)]
+pub enum RingMembership
+{
+	///Each port between routers belongs to the ring given by the class of its link. As the dimensions of a `Torus`, which has a link class for each dimension.
+	LinkClass,
+	///`rings[port]` is the ring of that port in every router. Ports beyond the list do not belong to any ring.
+	Ports(Vec<usize>),
+}
+
+impl RingMembership
+{
+	pub fn new(cv:&ConfigurationValue) -> RingMembership
+	{
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			match cv_name.as_ref()
+			{
+				"LinkClass" => RingMembership::LinkClass,
+				"Ports" =>
+				{
+					let mut rings=None;
+					for &(ref name,ref value) in cv_pairs
+					{
+						match AsRef::<str>::as_ref(&name)
+						{
+							"rings" => match value
+							{
+								&ConfigurationValue::Array(ref l) => rings=Some(l.iter().map(|v|match v{
+									&ConfigurationValue::Number(f) => f as usize,
+									_ => panic!("bad value in rings"),
+								}).collect()),
+								_ => panic!("bad value for rings"),
+							}
+							_ => panic!("Nothing to do with field {} in Ports",name),
+						}
+					}
+					RingMembership::Ports(rings.expect("There were no rings"))
+				},
+				_ => panic!("Unknown RingMembership {}",cv_name),
+			}
+		}
+		else
+		{
+			panic!("Trying to create a RingMembership from a non-Object");
+		}
+	}
+	///The ring of the given port, if it goes to another router and belongs to some ring.
+	pub fn ring(&self, topology:&dyn Topology, router_index:usize, port:usize) -> Option<usize>
+	{
+		let (location,link_class)=topology.neighbour(router_index,port);
+		if let Location::RouterPort{..} = location
+		{
+			match self
+			{
+				RingMembership::LinkClass => Some(link_class),
+				RingMembership::Ports(ref rings) => rings.get(port).cloned(),
+			}
+		}
+		else
+		{
+			None
+		}
+	}
+}
+
+///Declares the rings of the network for the bubble flow control of the routers, keeping the candidates of another routing.
+///A packet requires the bubble when it enters a ring, this is, when it comes from a server, from a port out of any ring, or from a port of a different ring.
+///Thus the bubble avoids deadlock in any topology whose routing only closes cycles inside the declared rings, without extra virtual channels.
+#[derive(Debug)]
+pub struct BubbleRings
+{
+	///The base routing to use.
+	routing: Box<dyn Routing>,
+	///The ring of each port.
+	membership: RingMembership,
+}
+
+impl Routing for BubbleRings
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_server:usize, num_virtual_channels:usize, rng: &RefCell<StdRng>) -> RoutingNextCandidates
+	{
+		self.routing.next(routing_info,topology,current_router,target_server,num_virtual_channels,rng)
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_server:usize, rng: &RefCell<StdRng>)
+	{
+		self.routing.initialize_routing_info(routing_info,topology,current_router,target_server,rng);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_server:usize, rng: &RefCell<StdRng>)
+	{
+		self.routing.update_routing_info(routing_info,topology,current_router,current_port,target_server,rng);
+	}
+	fn initialize(&mut self, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)
+	{
+		self.routing.initialize(topology,rng);
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_server:usize, num_virtual_channels:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_request(requested,routing_info,topology,current_router,target_server,num_virtual_channels,rng);
+	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_consumption(routing_info,topology,target_server,network_delay,ecn_marked,cycle,rng);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, cycle:usize, rng:&RefCell<StdRng>)
+	{
+		self.routing.performed_loss(routing_info,topology,target_server,cycle,rng);
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		match self.membership.ring(topology,router_index,input_port)
+		{
+			None => true,
+			Some(ring) => self.membership.ring(topology,router_index,output_port) != Some(ring),
+		}
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		self.routing.statistics(cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.routing.reset_statistics(next_cycle)
+	}
+}
+
+impl BubbleRings
+{
+	pub fn new(arg: RoutingBuilderArgument) -> BubbleRings
+	{
+		let mut routing =None;
+		let mut membership =None;
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
+		{
+			if cv_name!="BubbleRings"
+			{
+				panic!("A BubbleRings must be created from a `BubbleRings` object not `{}`",cv_name);
+			}
+			for &(ref name,ref value) in cv_pairs
+			{
+				match AsRef::<str>::as_ref(&name)
+				{
+					"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+					"membership" => membership=Some(RingMembership::new(value)),
+					"legend_name" => (),
+					_ => panic!("Nothing to do with field {} in BubbleRings",name),
+				}
+			}
+		}
+		else
+		{
+			panic!("Trying to create a BubbleRings from a non-Object");
+		}
+		let routing=routing.expect("There were no routing");
+		let membership=membership.expect("There were no membership");
+		BubbleRings{
+			routing,
+			membership,
+		}
+	}
+}
+
 ///Encapsulation of SourceRouting, to allow storing several paths in the packet. And then, have adaptiveness for the first hop.
 #[derive(Debug)]
 pub struct SourceAdaptiveRouting
@@ -2460,6 +2759,10 @@ impl Routing for SourceAdaptiveRouting
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_server:usize, _num_virtual_channels:usize, _rng:&RefCell<StdRng>)
 	{
 	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
 		return None;
@@ -2615,6 +2918,10 @@ impl Routing for EachLengthSourceAdaptiveRouting
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_server:usize, _num_virtual_channels:usize, _rng:&RefCell<StdRng>)
 	{
 	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
 	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
 		return None;
@@ -2624,3 +2931,378 @@ impl Routing for EachLengthSourceAdaptiveRouting
 	}
 }
 
+
+
+
+
+///How a `FeedbackSourceAdaptiveRouting` scores its paths from the packets consumed through them. Lower scores are better.
+#[derive(Debug)]
+pub enum SourcePathFeedback
+{
+	///Moving average of the network delay of the packets that followed the path.
+	Latency,
+	///Moving average of the fraction of the packets that followed the path and arrived with an ECN mark.
+	///It requires the routers to mark the packets, as with the `ecn_marking` of the Basic router.
+	Marks,
+}
+
+///The path assigned to a flow by a `FeedbackSourceAdaptiveRouting`.
+#[derive(Debug,Default)]
+struct SourceFlowRecord
+{
+	///Index of the path currently assigned to the flow.
+	path_index: Option<usize>,
+	///Number of packets of the flow that have been injected and have been neither consumed nor lost yet.
+	in_flight: usize,
+	///Number of packets injected since the path was selected.
+	flowlet_packets: usize,
+}
+
+///Encapsulation of SourceRouting, a variant of SourceAdaptiveRouting. The path is selected at injection from end-to-end feedback.
+///The servers report each consumed packet through `Routing::performed_consumption`, which updates the score of the path it followed.
+///A flow, identified by its source router and its target server, keeps its path while it has packets in the network, as in flowlet switching. The packets dropped or discarded are removed from the flow through `Routing::performed_loss`.
+///When the flow becomes idle, or after `max_flowlet_packets` packets, the path with the best score is selected again.
+///Paths without feedback yet are considered the best ones, so that they get explored.
+///As the other source adaptive routings it has no configuration builder, since the source routings are not built from configuration. It must be built with `FeedbackSourceAdaptiveRouting::new` from a plugin.
+#[derive(Debug)]
+pub struct FeedbackSourceAdaptiveRouting
+{
+	///The base routing
+	pub routing: Box<dyn InstantiableSourceRouting>,
+	///The kind of feedback used to score the paths.
+	pub feedback: SourcePathFeedback,
+	///The weight of each new sample in the moving averages. In the range (0,1].
+	pub smoothing: f64,
+	///Maximum number of packets of a flow using the same path. `None` to only change paths when the flow has no packets in the network.
+	pub max_flowlet_packets: Option<usize>,
+	///The flows, indexed by `(source_router,target_server)`.
+	flows: RefCell<BTreeMap<(usize,usize),SourceFlowRecord>>,
+	///The score of each path, indexed by `(source_router,target_router,path_index)`.
+	path_scores: RefCell<BTreeMap<(usize,usize,usize),f64>>,
+	///Statistics: number of times a path has been selected for a flow.
+	statistics_selections: Cell<usize>,
+	///Statistics: number of selections that have changed the path of a flow.
+	statistics_path_changes: Cell<usize>,
+	///Statistics: amount of feedback received.
+	statistics_feedbacks: Cell<usize>,
+}
+
+impl Routing for FeedbackSourceAdaptiveRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_server:usize, num_virtual_channels:usize, _rng: &RefCell<StdRng>) -> RoutingNextCandidates
+	{
+		let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return RoutingNextCandidates{
+							candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),
+							idempotent:true
+						};
+					}
+				}
+			}
+			unreachable!();
+		}
+		let source_router = routing_info.visited_routers.as_ref().unwrap()[0];
+		let path_index = <usize>::try_from(routing_info.selections.as_ref().unwrap()[0]).unwrap();
+		let path = &self.routing.get_paths(source_router,target_router)[path_index];
+		let next_router = path[routing_info.hops+1];
+		let length = path.len() - 1;//substract source router
+		let remain = length - routing_info.hops;
+		let num_ports=topology.ports(current_router);
+		let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
+		for i in 0..num_ports
+		{
+			if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
+			{
+				if router_index==next_router
+				{
+					r.extend((0..num_virtual_channels).map(|vc|{
+						let mut egress = CandidateEgress::new(i,vc);
+						egress.estimated_remaining_hops = Some(remain);
+						egress
+					}));
+				}
+			}
+		}
+		RoutingNextCandidates{candidates:r,idempotent:true}
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_server:usize, rng: &RefCell<StdRng>)
+	{
+		let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		routing_info.borrow_mut().visited_routers=Some(vec![current_router]);
+		if current_router!=target_router
+		{
+			let path_collection = self.routing.get_paths(current_router,target_router);
+			if path_collection.is_empty()
+			{
+				panic!("No path found from router {} to router {}",current_router,target_router);
+			}
+			let mut flows = self.flows.borrow_mut();
+			let flow = flows.entry((current_router,target_server)).or_default();
+			let reselect = match flow.path_index
+			{
+				None => true,
+				Some(_) => flow.in_flight==0 || self.max_flowlet_packets.map(|limit|flow.flowlet_packets>=limit).unwrap_or(false),
+			};
+			if reselect
+			{
+				let path_scores = self.path_scores.borrow();
+				let scores : Vec<f64> = (0..path_collection.len()).map(|index|
+					*path_scores.get(&(current_router,target_router,index)).unwrap_or(&0.0)
+				).collect();
+				let best = scores.iter().cloned().fold(f64::INFINITY,f64::min);
+				let candidates : Vec<usize> = (0..path_collection.len()).filter(|&index|scores[index]<=best).collect();
+				let r = rng.borrow_mut().gen_range(0..candidates.len());
+				let selected = candidates[r];
+				self.statistics_selections.set(self.statistics_selections.get()+1);
+				if let Some(previous) = flow.path_index
+				{
+					if previous!=selected
+					{
+						self.statistics_path_changes.set(self.statistics_path_changes.get()+1);
+					}
+				}
+				flow.path_index = Some(selected);
+				flow.flowlet_packets = 0;
+			}
+			flow.in_flight += 1;
+			flow.flowlet_packets += 1;
+			routing_info.borrow_mut().selections=Some(vec![i32::try_from(flow.path_index.unwrap()).unwrap()]);
+		}
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, _current_port:usize, _target_server:usize, _rng: &RefCell<StdRng>)
+	{
+		if let Some(ref mut visited)=routing_info.borrow_mut().visited_routers
+		{
+			visited.push(current_router);
+		}
+	}
+	fn initialize(&mut self, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>)
+	{
+		self.routing.initialize(topology,rng);
+	}
+	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_server:usize, _num_virtual_channels:usize, _rng:&RefCell<StdRng>)
+	{
+	}
+	fn performed_consumption(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, target_server:usize, network_delay:usize, ecn_marked:bool, _cycle:usize, _rng:&RefCell<StdRng>)
+	{
+		let ri = routing_info.borrow();
+		let path_index = match ri.selections
+		{
+			Some(ref selections) => <usize>::try_from(selections[0]).unwrap(),
+			//The packet has not left its source router.
+			None => return,
+		};
+		let source_router = ri.visited_routers.as_ref().expect("the packet has not visited any router")[0];
+		let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		if let Some(flow) = self.flows.borrow_mut().get_mut(&(source_router,target_server))
+		{
+			flow.in_flight = flow.in_flight.saturating_sub(1);
+		}
+		let sample = match self.feedback
+		{
+			SourcePathFeedback::Latency => network_delay as f64,
+			SourcePathFeedback::Marks => if ecn_marked { 1.0 } else { 0.0 },
+		};
+		let mut path_scores = self.path_scores.borrow_mut();
+		let score = path_scores.entry((source_router,target_router,path_index)).or_insert(sample);
+		*score = (1.0-self.smoothing)*(*score) + self.smoothing*sample;
+		self.statistics_feedbacks.set(self.statistics_feedbacks.get()+1);
+	}
+	fn performed_loss(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, target_server:usize, _cycle:usize, _rng:&RefCell<StdRng>)
+	{
+		let ri = routing_info.borrow();
+		//Only the packets given a path are counted in their flow.
+		if ri.selections.is_none()
+		{
+			return;
+		}
+		let source_router = ri.visited_routers.as_ref().expect("the packet has not visited any router")[0];
+		if let Some(flow) = self.flows.borrow_mut().get_mut(&(source_router,target_server))
+		{
+			flow.in_flight = flow.in_flight.saturating_sub(1);
+		}
+	}
+	fn is_direction_change(&self, topology:&dyn Topology, router_index:usize, input_port:usize, output_port:usize) -> bool
+	{
+		self.routing.is_direction_change(topology,router_index,input_port,output_port)
+	}
+	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
+	{
+		let content = vec![
+			(String::from("selections"),ConfigurationValue::Number(self.statistics_selections.get() as f64)),
+			(String::from("path_changes"),ConfigurationValue::Number(self.statistics_path_changes.get() as f64)),
+			(String::from("feedbacks"),ConfigurationValue::Number(self.statistics_feedbacks.get() as f64)),
+		];
+		Some(ConfigurationValue::Object(String::from("FeedbackSourceAdaptiveRouting"),content))
+	}
+	fn reset_statistics(&mut self, _next_cycle:usize)
+	{
+		self.statistics_selections.set(0);
+		self.statistics_path_changes.set(0);
+		self.statistics_feedbacks.set(0);
+	}
+}
+
+impl FeedbackSourceAdaptiveRouting
+{
+	pub fn new(routing:Box<dyn InstantiableSourceRouting>, feedback:SourcePathFeedback, smoothing:f64, max_flowlet_packets:Option<usize>) -> FeedbackSourceAdaptiveRouting
+	{
+		if smoothing<=0.0 || smoothing>1.0
+		{
+			panic!("The smoothing of a FeedbackSourceAdaptiveRouting must be in the range (0,1], but it is {}",smoothing);
+		}
+		FeedbackSourceAdaptiveRouting{
+			routing,
+			feedback,
+			smoothing,
+			max_flowlet_packets,
+			flows: RefCell::new(BTreeMap::new()),
+			path_scores: RefCell::new(BTreeMap::new()),
+			statistics_selections: Cell::new(0),
+			statistics_path_changes: Cell::new(0),
+			statistics_feedbacks: Cell::new(0),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	use crate::tests::parse_configuration;
+
+	///The two paths between each pair of routers of a triangle, the direct one and the one through the third router.
+	#[derive(Debug)]
+	struct TrianglePaths
+	{
+		paths: BTreeMap<(usize,usize),Vec<Vec<usize>>>,
+	}
+
+	impl SourceRouting for TrianglePaths
+	{
+		fn initialize(&mut self, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>)
+		{
+			for source in 0..3
+			{
+				for target in 0..3
+				{
+					if source!=target
+					{
+						self.paths.insert((source,target),vec![vec![source,target],vec![source,3-source-target,target]]);
+					}
+				}
+			}
+		}
+		fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>
+		{
+			&self.paths[&(source,target)]
+		}
+	}
+
+	fn path_of(routing_info:&RefCell<RoutingInfo>) -> usize
+	{
+		routing_info.borrow().selections.as_ref().unwrap()[0] as usize
+	}
+
+	#[test]
+	fn feedback_switches_paths_and_forgets_lost_packets()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(1));
+		let plugs = Plugs::default();
+		let topology = new_topology(TopologyBuilderArgument{
+			cv: &parse_configuration("Hamming { sides: [3], servers_per_router: 1 }"),
+			plugs: &plugs,
+			rng: &rng,
+		});
+		let mut routing = FeedbackSourceAdaptiveRouting::new(Box::new(TrianglePaths{paths:BTreeMap::new()}),SourcePathFeedback::Latency,1.0,None);
+		routing.initialize(&topology,&rng);
+		let new_packet = ||{
+			let routing_info = RefCell::new(RoutingInfo::new());
+			routing.initialize_routing_info(&routing_info,topology.as_ref(),0,1,&rng);
+			routing_info
+		};
+		let in_flight = ||routing.flows.borrow()[&(0,1)].in_flight;
+		//The flow keeps its path while it has packets in the network.
+		let first = new_packet();
+		let second = new_packet();
+		let first_path = path_of(&first);
+		assert_eq!(path_of(&second),first_path);
+		assert_eq!(in_flight(),2);
+		routing.performed_consumption(&first,topology.as_ref(),1,100,false,100,&rng);
+		routing.performed_consumption(&second,topology.as_ref(),1,100,false,101,&rng);
+		assert_eq!(in_flight(),0);
+		//Once idle, the flow moves to the path without feedback, which is considered better.
+		let third = new_packet();
+		assert_ne!(path_of(&third),first_path);
+		let fourth = new_packet();
+		routing.performed_consumption(&fourth,topology.as_ref(),1,1000,false,102,&rng);
+		assert_eq!(in_flight(),1);
+		//Losing the third packet leaves the flow idle, so it returns to the path with the best score.
+		routing.performed_loss(&third,topology.as_ref(),1,103,&rng);
+		assert_eq!(in_flight(),0);
+		let fifth = new_packet();
+		assert_eq!(path_of(&fifth),first_path);
+		assert_eq!(routing.statistics_path_changes.get(),2);
+	}
+
+	#[test]
+	fn bubble_rings_change_direction_when_leaving_their_ring()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(1));
+		let plugs = Plugs::default();
+		//The ports 0 and 1 of each router go along the first dimension, 2 and 3 along the second, and 4 to the server.
+		let topology = new_topology(TopologyBuilderArgument{
+			cv: &parse_configuration("Torus { sides: [3,3], servers_per_router: 1 }"),
+			plugs: &plugs,
+			rng: &rng,
+		});
+		let bubble_rings = |membership:&str|new_routing(RoutingBuilderArgument{
+			cv: &parse_configuration(&format!("BubbleRings {{ routing: Shortest, membership: {} }}",membership)),
+			plugs: &plugs,
+		});
+		for routing in [bubble_rings("LinkClass"),bubble_rings("Ports { rings: [0,0,1,1] }")]
+		{
+			for router in 0..topology.num_routers()
+			{
+				assert!(!routing.is_direction_change(topology.as_ref(),router,0,1));
+				assert!(!routing.is_direction_change(topology.as_ref(),router,3,2));
+				assert!(routing.is_direction_change(topology.as_ref(),router,0,2));
+				assert!(routing.is_direction_change(topology.as_ref(),router,3,1));
+				//Entering from the server.
+				assert!(routing.is_direction_change(topology.as_ref(),router,4,0));
+			}
+		}
+		//Each direction of the first dimension in its own ring, and the second dimension out of any ring.
+		let routing = bubble_rings("Ports { rings: [0,1] }");
+		assert!(routing.is_direction_change(topology.as_ref(),0,0,1));
+		assert!(!routing.is_direction_change(topology.as_ref(),0,1,1));
+		assert!(routing.is_direction_change(topology.as_ref(),0,2,3));
+		assert!(routing.is_direction_change(topology.as_ref(),0,2,0));
+	}
+}
@@ -11,16 +11,19 @@ see [`new_traffic`](fn.new_traffic.html) for documentation on the configuration
 use crate::config_parser::ConfigurationValue;
 use std::boxed::Box;
 use std::cell::{RefCell};
-use crate::{Message,Plugs};
-use ::rand::{Rng,rngs::StdRng};
-use crate::pattern::{Pattern,new_pattern,PatternBuilderArgument};
+use crate::{Message,Plugs,match_object_panic};
+use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
+use std::ops::DerefMut;
+use crate::pattern::{Pattern,new_pattern,PatternBuilderArgument,GridMapping,StencilGrid};
 use std::rc::Rc;
 use std::collections::{BTreeSet,BTreeMap,VecDeque};
-use crate::topology::Topology;
+use crate::topology::{Topology,Location};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::quantify::Quantifiable;
-//use std::mem::{size_of};
+use std::mem::{size_of};
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead,BufReader,Lines};
 
 ///Possible errors when trying to generate a message with a `Traffic`.
 #[derive(Debug)]
@@ -74,6 +77,15 @@ pub trait Traffic : Quantifiable + Debug
 	}
 	///Indicates the state of the server within the traffic.
 	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState;
+	///To optionally write traffic statistics into the simulation output.
+	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
+	{
+		None
+	}
+	///Clears the statistics, which should only include the cycles from `next_cycle` on.
+	fn reset_statistics(&mut self, _next_cycle:usize)
+	{
+	}
 }
 
 #[derive(Debug)]
@@ -105,10 +117,30 @@ HomogeneousTraffic{
 	message_size: 16,
 }
 ```
+The `message_size` may also be a [`MessageSizeDistribution`](enum.MessageSizeDistribution.html), in which case the generation rate is computed from its average size.
+```
+HomogeneousTraffic{
+	pattern:Uniform,
+	servers:1000,
+	load: 0.9,
+	message_size: EmpiricalCDF{filename:"websearch.cdf",size_scale:0.015625},
+}
+```
+The optional `arrival` field selects an [`ArrivalProcess`](enum.ArrivalProcess.html) other than the Bernoulli trials, such as `Poisson`, `MarkovOnOff`, or `ParetoOnOff`, keeping the same mean load. When it is given, the result includes `traffic_statistics` with the measured arrivals, their offered load, and the mean and coefficient of variation of the times between arrivals.
+```
+HomogeneousTraffic{
+	pattern:Uniform,
+	servers:1000,
+	load: 0.3,
+	message_size: 16,
+	arrival: MarkovOnOff{mean_burst_length:50,mean_idle_length:150},
+}
+```
+The load of each server is multiplied by the `load_scale` of the pattern, which is 1 except for patterns weighting their sources, as a `TrafficMatrix` with `scale_load`. Then `load` is the one of the busiest server.
 
 ### Burst
 In the Burst traffic each of the involved `servers` has a initial list of `messages_per_server` messages to emit. When all the messages
-are consumed the simulation is requested to end.
+are consumed the simulation is requested to end. The `message_size` may be a `MessageSizeDistribution`, as in the Homogeneous traffic.
 ```
 Burst{
 	pattern:Uniform,
@@ -118,6 +150,105 @@ Burst{
 }
 ```
 
+### Trace
+Replays the messages recorded in the file `filename`. Each line is a record `time source destination size [tag]`, separated by spaces or commas, and sorted by time. Empty lines and those beginning with `#` are ignored. The tag is accepted but not used.
+The file is read as the simulation advances, so it may be arbitrarily large. Each server generates its messages one per cycle from the cycle given by its record, and it is not queried by the simulation while waiting for its next record.
+The source and destination are ranks of the trace, mapped into servers by the optional `mapping` pattern, initialized with sizes `ranks` and the number of servers. By default the rank `r` is the server `r`.
+The times are multiplied by `time_scale` to get cycles and the sizes by `size_scale` to get phits, both 1 by default. With `looping` the trace starts again after its last record; otherwise the simulation ends when all its messages have been consumed.
+```
+Trace{
+	filename: "application.trace",
+	ranks: 64,//defaults to the number of servers
+	mapping: RandomPermutation,//optional
+	time_scale: 0.5,//cycles per unit of time of the trace
+	size_scale: 0.015625,//phits per unit of size of the trace, as for sizes in bytes and phits of 64 bytes
+	looping: false,
+}
+```
+
+### Goal
+Executes the schedule of each rank of an application, given in the file `filename` in the GOAL language of LogGOPSim. Each rank has `send`, `recv`, and `calc` operations, which start when their dependencies are satisfied. With `a requires b` the operation `a` starts after `b` is completed, and with `a irequires b` after `b` is started. A send is completed when its message is generated, a receive when its message is consumed, and a computation after its cycles. See [`parse_goal`](fn.parse_goal.html) for the syntax.
+The ranks are mapped into servers as in the `Trace` traffic. The simulation ends when all ranks have completed their schedules, and the result includes `traffic_statistics` with the `completion_cycle` and the finish cycle of each rank.
+```
+Goal{
+	filename: "allreduce.goal",
+	mapping: RandomPermutation,//optional
+	time_scale: 1,//cycles per unit of computation
+	size_scale: 0.015625,//phits per unit of size, as for sizes in bytes and phits of 64 bytes
+}
+```
+
+### Collectives
+The traffics `Allreduce`, `AllToAll`, `Broadcast`, and `Barrier` repeat a collective operation among `ranks` ranks, following the data dependencies of the chosen `algorithm`. They are executed as a `Goal` schedule, and each rank starts a repetition when it has completed its part of the previous one. The ranks are mapped into servers by the optional `mapping` pattern, as in the `Trace` traffic.
+The available algorithms are
+* `Allreduce`: `Ring` (the default), `RecursiveDoubling`, and `Rabenseifner`. The `message_size` is the size of the whole vector.
+* `AllToAll`: `LinearShift` (the default), `PairwiseExchange`, and `Bruck`. The `message_size` is the size of the block for each destination.
+* `Broadcast`: `BinomialTree` (the default) and `PipelinedChain`, from the rank `root`, 0 by default. The chain divides the message in `segments`, by default as many as ranks, and at least one.
+* `Barrier`: `Dissemination`, with messages of `message_size`, 1 by default.
+
+The result includes `traffic_statistics` with the `repetition_completion_cycles` at which the last rank completed each repetition and the `average_repetition_time`.
+```
+Allreduce{
+	algorithm: Rabenseifner,
+	ranks: 64,//defaults to the number of servers
+	message_size: 1024,
+	mapping: RandomPermutation,//optional
+	repetitions: 10,//defaults to 1
+}
+```
+
+### DeepLearningTraining
+Models the iterations of training a deep neural network with `data_parallel` replicas, each of `pipeline_stages` stages of `tensor_parallel` ranks. In each iteration the replicas make the forward pass of their `micro_batches` through the stages and then the backward pass, sending `activation_size` phits between consecutive stages for each micro-batch. The backward pass of the last micro-batch is divided among the `gradient_buckets`, and the allreduce of each bucket among the replicas starts as soon as its part of the computation ends, overlapping with the rest. With `tensor_parallel_size` greater than 0, the tensor-parallel ranks of a stage make an all-to-all of that block size after each computation. The iteration ends with `optimizer_cycles` of computation after all the communications of the rank.
+The result includes `traffic_statistics` with the completion cycle of each iteration, the `average_iteration_time`, the `communication_overlap`, as the fraction of the time of the gradient allreduces in which the rank was also computing, and the `exposed_communication_time` per rank and iteration.
+```
+DeepLearningTraining{
+	data_parallel: 8,
+	pipeline_stages: 2,//defaults to 1
+	tensor_parallel: 2,//defaults to 1
+	iterations: 5,//defaults to 1
+	micro_batches: 4,//defaults to 1
+	forward_cycles: 100,//for each micro-batch in each stage
+	backward_cycles: 200,
+	optimizer_cycles: 20,//defaults to 0
+	activation_size: 32,
+	gradient_buckets: [128,128,256],//in the order they are produced by the backward pass
+	allreduce: Ring,//or RecursiveDoubling or Rabenseifner
+	tensor_parallel_size: 16,//defaults to 0
+	all_to_all: LinearShift,//or PairwiseExchange or Bruck
+	mapping: Identity,//optional, as in the Trace traffic
+}
+```
+
+### HaloExchange
+Repeats `iterations` of a halo exchange among the processes of a logical grid, defined and placed into the servers as in the [`Stencil`](../pattern/fn.new_pattern.html) pattern. In each iteration each process computes during `compute_cycles` and then sends a message of `message_size` to every neighbour of its stencil. It starts the next iteration when it has received the messages of all its neighbours. It is executed as a `Goal` schedule.
+The result includes `traffic_statistics` with the `iteration_completion_cycles` at which the last process completed each iteration and the `average_iteration_time`.
+```
+HaloExchange{
+	sides: [8,8,8],
+	points: 27,//defaults to 2n+1
+	periodic: true,//default
+	mapping: Hilbert,//or Linear, the default, or Blocked{block:[2,2,1]}
+	message_size: 16,
+	iterations: 10,//defaults to 1
+	compute_cycles: 100,//defaults to 0
+}
+```
+
+### Workload
+Several jobs sharing the network. Each `Job` has its own `traffic` over its `servers` ranks, and the `placement` policy allocates the servers of the network to the ranks of the jobs. The servers not allocated to any job stay idle.
+The available placements are `Contiguous` (the default), `Random`, `RouterRoundRobin`, taking one server of each router in turn, and `TopologyAware{routers_per_group}`, which keeps each job in as few groups of consecutive routers as it can. With `routers_per_group: 1` the groups are the leaf routers. See [`JobPlacement`](enum.JobPlacement.html).
+The traffic of a job sees its ranks as servers `0..servers`; when it declares its own `servers` or `ranks` they must match the ones of the job.
+The result includes `traffic_statistics` with the injected and accepted load and the average message delay of each job, measured after the warmup, together with the statistics of its traffic if it has any.
+```
+Workload{
+	jobs: [
+		Job{ servers:512, traffic:HomogeneousTraffic{pattern:Uniform, servers:512, load:0.5, message_size:16} },
+		Job{ servers:256, traffic:Allreduce{ranks:256, message_size:1024, repetitions:10} },
+	],
+	placement: Random,
+}
+```
+
 ### Reactive
 
 A Reactive traffic is composed of an `action_traffic` generated normally, whose packets, when consumed create a response by the `reaction_traffic`.
@@ -217,6 +348,12 @@ pub fn new_traffic(arg:TrafficBuilderArgument) -> Box<dyn Traffic>
 			"TimeSequenced" => Box::new(TimeSequenced::new(arg)),
 			"Sequence" => Box::new(Sequence::new(arg)),
 			"BoundedDifference" => Box::new(BoundedDifference::new(arg)),
+			"Trace" => Box::new(Trace::new(arg)),
+			"Goal" => Box::new(Goal::new(arg)),
+			"Allreduce" | "AllToAll" | "Broadcast" | "Barrier" => Box::new(Collective::new(arg)),
+			"DeepLearningTraining" => Box::new(DeepLearningTraining::new(arg)),
+			"Workload" => Box::new(Workload::new(arg)),
+			"HaloExchange" => Box::new(HaloExchange::new(arg)),
 			_ => panic!("Unknown traffic {}",cv_name),
 		}
 	}
@@ -226,7 +363,523 @@ pub fn new_traffic(arg:TrafficBuilderArgument) -> Box<dyn Traffic>
 	}
 }
 
-///Traffic in which all messages have same size, follow the same pattern, and there is no change with time.
+///The distribution of the sizes of the messages generated by a traffic, given in its `message_size` field.
+///A plain number is a fixed size. Otherwise it is one of the following objects.
+///```ignore
+///Fixed{size:16}
+///Uniform{minimum:1,maximum:31}//both ends included
+///Bimodal{small:4,large:256,large_probability:0.05}
+///Geometric{mean:16,minimum:1}//minimum is 1 by default
+///EmpiricalCDF{filename:"websearch.cdf",size_scale:0.015625}
+///```
+///The file of `EmpiricalCDF` has a point `size cumulative` per line, with increasing values in both columns. Empty lines and those beginning with `#` are ignored.
+///The cumulative values are normalized by the last one, so they may be given either as fractions or as percentages, as in the published web-search and data-mining flow size distributions.
+///Sizes are interpolated linearly between consecutive points and then multiplied by `size_scale` (1 by default) and rounded up to get phits.
+#[derive(Debug,Clone)]
+pub enum MessageSizeDistribution
+{
+	Fixed{size:usize},
+	Uniform{minimum:usize,maximum:usize},
+	Bimodal{small:usize,large:usize,large_probability:f64},
+	///`minimum` plus a geometric number of failures, with the given mean.
+	Geometric{minimum:usize,mean:f64},
+	///The points `(size,cumulative)` of the distribution, with the cumulative normalized to end in 1.
+	EmpiricalCDF{points:Vec<(f64,f64)>,size_scale:f64},
+}
+
+impl Quantifiable for MessageSizeDistribution
+{
+	fn total_memory(&self) -> usize
+	{
+		match self
+		{
+			MessageSizeDistribution::EmpiricalCDF{points,..} => size_of::<MessageSizeDistribution>() + points.len()*size_of::<(f64,f64)>(),
+			_ => size_of::<MessageSizeDistribution>(),
+		}
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl MessageSizeDistribution
+{
+	pub fn new(cv:&ConfigurationValue) -> MessageSizeDistribution
+	{
+		if let &ConfigurationValue::Number(f) = cv
+		{
+			return MessageSizeDistribution::Fixed{size:f as usize};
+		}
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			let mut size=None;
+			let mut minimum=None;
+			let mut maximum=None;
+			let mut small=None;
+			let mut large=None;
+			let mut large_probability=None;
+			let mut mean=None;
+			let mut filename=None;
+			let mut size_scale=1.0;
+			for &(ref name,ref value) in cv_pairs
+			{
+				match (cv_name.as_ref(),name.as_ref())
+				{
+					("Fixed","size") => match value
+					{
+						&ConfigurationValue::Number(f) => size=Some(f as usize),
+						_ => panic!("bad value for size"),
+					}
+					("Uniform","minimum") | ("Geometric","minimum") => match value
+					{
+						&ConfigurationValue::Number(f) => minimum=Some(f as usize),
+						_ => panic!("bad value for minimum"),
+					}
+					("Uniform","maximum") => match value
+					{
+						&ConfigurationValue::Number(f) => maximum=Some(f as usize),
+						_ => panic!("bad value for maximum"),
+					}
+					("Bimodal","small") => match value
+					{
+						&ConfigurationValue::Number(f) => small=Some(f as usize),
+						_ => panic!("bad value for small"),
+					}
+					("Bimodal","large") => match value
+					{
+						&ConfigurationValue::Number(f) => large=Some(f as usize),
+						_ => panic!("bad value for large"),
+					}
+					("Bimodal","large_probability") => match value
+					{
+						&ConfigurationValue::Number(f) => large_probability=Some(f),
+						_ => panic!("bad value for large_probability"),
+					}
+					("Geometric","mean") => match value
+					{
+						&ConfigurationValue::Number(f) => mean=Some(f),
+						_ => panic!("bad value for mean"),
+					}
+					("EmpiricalCDF","filename") => match value
+					{
+						&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
+						_ => panic!("bad value for filename"),
+					}
+					("EmpiricalCDF","size_scale") => match value
+					{
+						&ConfigurationValue::Number(f) => size_scale=f,
+						_ => panic!("bad value for size_scale"),
+					}
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			let distribution = match cv_name.as_ref()
+			{
+				"Fixed" => MessageSizeDistribution::Fixed{size:size.expect("There were no size")},
+				"Uniform" => MessageSizeDistribution::Uniform{minimum:minimum.expect("There were no minimum"),maximum:maximum.expect("There were no maximum")},
+				"Bimodal" => MessageSizeDistribution::Bimodal{small:small.expect("There were no small"),large:large.expect("There were no large"),large_probability:large_probability.expect("There were no large_probability")},
+				"Geometric" => MessageSizeDistribution::Geometric{minimum:minimum.unwrap_or(1),mean:mean.expect("There were no mean")},
+				"EmpiricalCDF" => MessageSizeDistribution::EmpiricalCDF{points:read_size_cdf(&filename.expect("There were no filename")),size_scale},
+				_ => panic!("Unknown message size distribution {}",cv_name),
+			};
+			distribution.check();
+			distribution
+		}
+		else
+		{
+			panic!("Trying to create a MessageSizeDistribution from a non-Object");
+		}
+	}
+	fn check(&self)
+	{
+		match self
+		{
+			&MessageSizeDistribution::Fixed{size} => assert!(size>0,"The message size must be positive."),
+			&MessageSizeDistribution::Uniform{minimum,maximum} => assert!(0<minimum && minimum<=maximum,"Uniform message sizes require 0<minimum<=maximum."),
+			&MessageSizeDistribution::Bimodal{small,large,large_probability} => assert!(small>0 && large>0 && (0.0..=1.0).contains(&large_probability),"Bimodal message sizes require positive sizes and a large_probability in [0,1]."),
+			&MessageSizeDistribution::Geometric{minimum,mean} => assert!(minimum>0 && mean>=minimum as f64,"Geometric message sizes require 0<minimum<=mean."),
+			&MessageSizeDistribution::EmpiricalCDF{size_scale,..} => assert!(size_scale>0.0,"The size_scale must be positive."),
+		}
+	}
+	///Draws the size of a new message. The fixed size does not consume random numbers.
+	pub fn sample(&self, rng:&RefCell<StdRng>) -> usize
+	{
+		match self
+		{
+			&MessageSizeDistribution::Fixed{size} => size,
+			&MessageSizeDistribution::Uniform{minimum,maximum} => rng.borrow_mut().gen_range(minimum..=maximum),
+			&MessageSizeDistribution::Bimodal{small,large,large_probability} => if rng.borrow_mut().gen_range(0f64..1f64)<large_probability { large } else { small },
+			&MessageSizeDistribution::Geometric{minimum,mean} =>
+			{
+				let excess_mean = mean - minimum as f64;
+				if excess_mean<=0.0
+				{
+					return minimum;
+				}
+				//Number of failures before a success of probability p, which has mean (1-p)/p.
+				let p = 1.0/(excess_mean+1.0);
+				let u:f64 = rng.borrow_mut().gen_range(0f64..1f64);
+				minimum + ((1.0-u).ln()/(1.0-p).ln()).floor() as usize
+			},
+			&MessageSizeDistribution::EmpiricalCDF{ref points,size_scale} =>
+			{
+				let u:f64 = rng.borrow_mut().gen_range(0f64..1f64);
+				let index = points.partition_point(|&(_size,cumulative)|cumulative<=u).min(points.len()-1);
+				let size = if index==0
+				{
+					points[0].0
+				}
+				else
+				{
+					let (previous_size,previous_cumulative) = points[index-1];
+					let (size,cumulative) = points[index];
+					previous_size + (size-previous_size)*(u-previous_cumulative)/(cumulative-previous_cumulative)
+				};
+				((size*size_scale).ceil() as usize).max(1)
+			},
+		}
+	}
+	///The expected size of the messages, used to convert loads into generation rates.
+	pub fn average(&self) -> f64
+	{
+		match self
+		{
+			&MessageSizeDistribution::Fixed{size} => size as f64,
+			&MessageSizeDistribution::Uniform{minimum,maximum} => (minimum+maximum) as f64/2.0,
+			&MessageSizeDistribution::Bimodal{small,large,large_probability} => small as f64*(1.0-large_probability) + large as f64*large_probability,
+			&MessageSizeDistribution::Geometric{minimum,mean} => mean.max(minimum as f64),
+			&MessageSizeDistribution::EmpiricalCDF{ref points,size_scale} =>
+			{
+				//The sizes are rounded up to phits, and at least one, as in `sample`.
+				let rounded = |size:f64| (size*size_scale).ceil().max(1.0);
+				//Integral of `ceil(y)` over `[0,x]`, for `x>=0`. It is 1+2+...+n up to `n=floor(x)` and then `n+1` for the rest.
+				let ceil_integral = |x:f64| { let n=x.floor(); n*(n+1.0)/2.0 + (n+1.0)*(x-n) };
+				//The mass at the first point and then the linear segments.
+				let mut total = rounded(points[0].0)*points[0].1;
+				for pair in points.windows(2)
+				{
+					let (previous_size,previous_cumulative) = pair[0];
+					let (size,cumulative) = pair[1];
+					let (low,high) = (previous_size*size_scale,size*size_scale);
+					//Within a segment the scaled size is uniform in [low,high]. Below one phit `ceil` and `max(1)` only differ at 0, which has no mass.
+					let segment_average = if high>low { (ceil_integral(high)-ceil_integral(low))/(high-low) } else { rounded(size) };
+					total += (cumulative-previous_cumulative)*segment_average;
+				}
+				total
+			},
+		}
+	}
+}
+
+///Reads the points `size cumulative` of an empirical distribution of sizes, normalizing the cumulative to end in 1.
+fn read_size_cdf(filename:&str) -> Vec<(f64,f64)>
+{
+	let file = File::open(filename).unwrap_or_else(|e|panic!("could not open the size distribution file {}: {}",filename,e));
+	let mut points:Vec<(f64,f64)> = vec![];
+	for (line_index,line) in BufReader::new(file).lines().enumerate()
+	{
+		let line = line.unwrap_or_else(|e|panic!("could not read the size distribution file {}: {}",filename,e));
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#')
+		{
+			continue;
+		}
+		let fields:Vec<f64> = line.split(|c:char|c.is_whitespace()||c==',').filter(|s|!s.is_empty()).map(|s|s.parse::<f64>().unwrap_or_else(|_|panic!("bad number {} in line {} of {}",s,line_index+1,filename))).collect();
+		if fields.len()<2
+		{
+			panic!("line {} of {} should be `size cumulative`",line_index+1,filename);
+		}
+		let point = (fields[0],fields[fields.len()-1]);
+		if let Some(&(size,cumulative)) = points.last()
+		{
+			if point.0<size || point.1<cumulative
+			{
+				panic!("the points of {} must be increasing (line {})",filename,line_index+1);
+			}
+		}
+		if point.0<0.0
+		{
+			panic!("the sizes of {} cannot be negative (line {})",filename,line_index+1);
+		}
+		points.push(point);
+	}
+	let total = match points.last()
+	{
+		Some(&(_size,cumulative)) if cumulative>0.0 => cumulative,
+		_ => panic!("the size distribution file {} has no positive cumulative values",filename),
+	};
+	for point in points.iter_mut()
+	{
+		point.1/=total;
+	}
+	points
+}
+
+///The process deciding the cycles in which a server of a `HomogeneousTraffic` generates messages, given in its `arrival` field.
+///All of them generate on average the `load` of the traffic.
+///```ignore
+///Bernoulli//a trial in each cycle, the default
+///Poisson//exponential times between messages
+///MarkovOnOff{mean_burst_length:50,mean_idle_length:150}//geometric lengths, in cycles
+///ParetoOnOff{mean_burst_length:50,mean_idle_length:150,burst_shape:1.5,idle_shape:1.5}//heavy-tailed lengths, shapes 1.5 by default
+///```
+///In the on/off processes the servers only generate messages while in a burst, with the probability that keeps the mean load. Hence the load must not exceed `mean_burst_length/(mean_burst_length+mean_idle_length)` times the load that would generate a message each cycle.
+///A Pareto shape in the interval (1,2) makes the aggregated traffic self-similar.
+#[derive(Debug,Clone)]
+pub enum ArrivalProcess
+{
+	Bernoulli,
+	Poisson,
+	MarkovOnOff{mean_burst_length:f64,mean_idle_length:f64},
+	ParetoOnOff{mean_burst_length:f64,mean_idle_length:f64,burst_shape:f64,idle_shape:f64},
+}
+
+impl Quantifiable for ArrivalProcess
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<ArrivalProcess>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+///The state of the arrival process of a server, together with the measurements of its arrivals.
+#[derive(Debug,Clone,Default,Quantifiable)]
+struct ArrivalState
+{
+	///The time of the next arrival of a Poisson process. Arrivals in `[c,c+1)` are generated at cycle `c`.
+	next_arrival: f64,
+	///Whether an on/off process is in a burst.
+	on: bool,
+	///The cycle in which the current period of a Pareto on/off process ends.
+	period_end: usize,
+	///Number of cycles in which the process has been queried.
+	queried_cycles: usize,
+	///Number of those cycles in which the process was in a burst.
+	on_cycles: usize,
+	///Number of messages requested by the process.
+	arrivals: usize,
+	///The cycle of the last arrival, if any.
+	last_arrival: Option<usize>,
+	///Accumulated cycles between consecutive arrivals.
+	total_gap: f64,
+	///Accumulated square of the cycles between consecutive arrivals.
+	total_squared_gap: f64,
+}
+
+impl ArrivalProcess
+{
+	pub fn new(cv:&ConfigurationValue) -> ArrivalProcess
+	{
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			let mut mean_burst_length=None;
+			let mut mean_idle_length=None;
+			let mut burst_shape=1.5;
+			let mut idle_shape=1.5;
+			for &(ref name,ref value) in cv_pairs
+			{
+				match (cv_name.as_ref(),name.as_ref())
+				{
+					("MarkovOnOff","mean_burst_length") | ("ParetoOnOff","mean_burst_length") => match value
+					{
+						&ConfigurationValue::Number(f) => mean_burst_length=Some(f),
+						_ => panic!("bad value for mean_burst_length"),
+					}
+					("MarkovOnOff","mean_idle_length") | ("ParetoOnOff","mean_idle_length") => match value
+					{
+						&ConfigurationValue::Number(f) => mean_idle_length=Some(f),
+						_ => panic!("bad value for mean_idle_length"),
+					}
+					("ParetoOnOff","burst_shape") => match value
+					{
+						&ConfigurationValue::Number(f) => burst_shape=f,
+						_ => panic!("bad value for burst_shape"),
+					}
+					("ParetoOnOff","idle_shape") => match value
+					{
+						&ConfigurationValue::Number(f) => idle_shape=f,
+						_ => panic!("bad value for idle_shape"),
+					}
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			let process = match cv_name.as_ref()
+			{
+				"Bernoulli" => ArrivalProcess::Bernoulli,
+				"Poisson" => ArrivalProcess::Poisson,
+				"MarkovOnOff" => ArrivalProcess::MarkovOnOff{
+					mean_burst_length:mean_burst_length.expect("There were no mean_burst_length"),
+					mean_idle_length:mean_idle_length.expect("There were no mean_idle_length"),
+				},
+				"ParetoOnOff" => ArrivalProcess::ParetoOnOff{
+					mean_burst_length:mean_burst_length.expect("There were no mean_burst_length"),
+					mean_idle_length:mean_idle_length.expect("There were no mean_idle_length"),
+					burst_shape,
+					idle_shape,
+				},
+				_ => panic!("Unknown arrival process {}",cv_name),
+			};
+			match process
+			{
+				ArrivalProcess::MarkovOnOff{mean_burst_length,mean_idle_length} => assert!(mean_burst_length>=1.0 && mean_idle_length>=1.0,"The mean lengths of a MarkovOnOff process must be at least one cycle."),
+				ArrivalProcess::ParetoOnOff{mean_burst_length,mean_idle_length,burst_shape,idle_shape} =>
+				{
+					assert!(mean_burst_length>0.0 && mean_idle_length>0.0,"The mean lengths of a ParetoOnOff process must be positive.");
+					assert!(burst_shape>1.0 && idle_shape>1.0,"The shapes of a ParetoOnOff process must be greater than 1 to have a finite mean.");
+				},
+				_ => (),
+			}
+			process
+		}
+		else
+		{
+			panic!("Trying to create an ArrivalProcess from a non-Object");
+		}
+	}
+	pub fn name(&self) -> &'static str
+	{
+		match self
+		{
+			ArrivalProcess::Bernoulli => "Bernoulli",
+			ArrivalProcess::Poisson => "Poisson",
+			ArrivalProcess::MarkovOnOff{..} => "MarkovOnOff",
+			ArrivalProcess::ParetoOnOff{..} => "ParetoOnOff",
+		}
+	}
+	///The fraction of the time in bursts.
+	fn on_fraction(&self) -> f64
+	{
+		match self
+		{
+			&ArrivalProcess::MarkovOnOff{mean_burst_length,mean_idle_length} | &ArrivalProcess::ParetoOnOff{mean_burst_length,mean_idle_length,..} => mean_burst_length/(mean_burst_length+mean_idle_length),
+			_ => 1.0,
+		}
+	}
+	///The probability of generating in each cycle of a burst to get on average `probability` messages per cycle.
+	fn burst_probability(&self, probability:f64) -> f64
+	{
+		probability/self.on_fraction()
+	}
+	///Panics if the process cannot generate on average `probability` messages per cycle.
+	fn check_probability(&self, probability:f64)
+	{
+		if self.burst_probability(probability)>1.0
+		{
+			panic!("The {} arrival process cannot generate {} messages per cycle, as its bursts only cover a fraction {} of the cycles.",self.name(),probability,self.on_fraction());
+		}
+	}
+	///Draws the length in cycles of a period of a Pareto distribution with the given `mean` and `shape`.
+	fn pareto_length(mean:f64, shape:f64, rng:&RefCell<StdRng>) -> usize
+	{
+		let scale = mean*(shape-1.0)/shape;
+		let u:f64 = rng.borrow_mut().gen_range(0f64..1f64);
+		let length = scale/(1.0-u).powf(1.0/shape);
+		(length.round() as usize).max(1)
+	}
+	///Draws the time between arrivals of a Poisson process with `rate` arrivals per cycle.
+	fn exponential_gap(rate:f64, rng:&RefCell<StdRng>) -> f64
+	{
+		if rate<=0.0
+		{
+			return f64::INFINITY;
+		}
+		let u:f64 = rng.borrow_mut().gen_range(0f64..1f64);
+		-(1.0-u).ln()/rate
+	}
+	///The initial state of a server generating on average `probability` messages per cycle.
+	fn initial_state(&self, probability:f64, rng:&RefCell<StdRng>) -> ArrivalState
+	{
+		let mut state = ArrivalState::default();
+		match self
+		{
+			ArrivalProcess::Bernoulli => (),
+			ArrivalProcess::Poisson => state.next_arrival=ArrivalProcess::exponential_gap(probability,rng),
+			ArrivalProcess::MarkovOnOff{..} => state.on = rng.borrow_mut().gen_range(0f64..1f64)<self.on_fraction(),
+			&ArrivalProcess::ParetoOnOff{mean_burst_length,mean_idle_length,burst_shape,idle_shape} =>
+			{
+				state.on = rng.borrow_mut().gen_range(0f64..1f64)<self.on_fraction();
+				state.period_end = if state.on { ArrivalProcess::pareto_length(mean_burst_length,burst_shape,rng) } else { ArrivalProcess::pareto_length(mean_idle_length,idle_shape,rng) };
+			},
+		}
+		state
+	}
+	///Whether to generate a message in `cycle`, for a server generating on average `probability` messages per cycle.
+	fn should_generate(&self, state:&mut ArrivalState, probability:f64, cycle:usize, rng:&RefCell<StdRng>) -> bool
+	{
+		//Whether to generate and whether the server is in a burst during this cycle.
+		let (generate,on) = match self
+		{
+			//Drawn as the default `Traffic::should_generate`, to get the same generation.
+			ArrivalProcess::Bernoulli => (rng.borrow_mut().gen_range(0f32..1f32)<probability as f32,true),
+			ArrivalProcess::Poisson =>
+			{
+				//Arrivals falling in the same cycle are generated in the following ones.
+				if state.next_arrival < (cycle+1) as f64
+				{
+					state.next_arrival += ArrivalProcess::exponential_gap(probability,rng);
+					(true,true)
+				}
+				else
+				{
+					(false,true)
+				}
+			},
+			&ArrivalProcess::MarkovOnOff{mean_burst_length,mean_idle_length} =>
+			{
+				let on = state.on;
+				let generate = on && rng.borrow_mut().gen_range(0f64..1f64)<self.burst_probability(probability);
+				let leave_probability = if on { 1.0/mean_burst_length } else { 1.0/mean_idle_length };
+				if rng.borrow_mut().gen_range(0f64..1f64)<leave_probability
+				{
+					state.on = !on;
+				}
+				(generate,on)
+			},
+			&ArrivalProcess::ParetoOnOff{mean_burst_length,mean_idle_length,burst_shape,idle_shape} =>
+			{
+				while cycle>=state.period_end
+				{
+					state.on = !state.on;
+					let length = if state.on { ArrivalProcess::pareto_length(mean_burst_length,burst_shape,rng) } else { ArrivalProcess::pareto_length(mean_idle_length,idle_shape,rng) };
+					state.period_end += length;
+				}
+				(state.on && rng.borrow_mut().gen_range(0f64..1f64)<self.burst_probability(probability),state.on)
+			},
+		};
+		state.queried_cycles+=1;
+		if on
+		{
+			state.on_cycles+=1;
+		}
+		if generate
+		{
+			state.arrivals+=1;
+			if let Some(last) = state.last_arrival
+			{
+				let gap = (cycle-last) as f64;
+				state.total_gap+=gap;
+				state.total_squared_gap+=gap*gap;
+			}
+			state.last_arrival = Some(cycle);
+		}
+		generate
+	}
+}
+
+///Traffic in which all messages have sizes from the same distribution, follow the same pattern, and there is no change with time.
 #[derive(Quantifiable)]
 #[derive(Debug)]
 pub struct Homogeneous
@@ -235,12 +888,16 @@ pub struct Homogeneous
 	servers: usize,
 	///The pattern of the communication.
 	pattern: Box<dyn Pattern>,
-	///The size of each sent message.
-	message_size: usize,
+	///The distribution of the size of the sent messages.
+	message_size: MessageSizeDistribution,
 	///The load offered to the network. Proportion of the cycles that should be injecting phits.
 	load: f32,
 	///Set of generated messages.
 	generated_messages: BTreeSet<*const Message>,
+	///The process deciding when to generate, if given. Otherwise the default Bernoulli trial of the trait, without statistics.
+	arrival: Option<ArrivalProcess>,
+	///The state of the arrival process of each server.
+	arrival_states: RefCell<Vec<ArrivalState>>,
 }
 
 impl Traffic for Homogeneous
@@ -260,15 +917,20 @@ impl Traffic for Homogeneous
 		let message=Rc::new(Message{
 			origin: origin,
 			destination,
-			size:self.message_size,
+			size:self.message_size.sample(rng),
 			creation_cycle: cycle,
 		});
 		self.generated_messages.insert(message.as_ref() as *const Message);
 		Ok(message)
 	}
-	fn probability_per_cycle(&self, _server:usize) -> f32
+	fn probability_per_cycle(&self, server:usize) -> f32
 	{
-		let r=self.load/self.message_size as f32;
+		if server>=self.servers
+		{
+			//The pattern is only defined over the servers of the traffic.
+			return 0.0;
+		}
+		let r=self.load*self.pattern.load_scale(server)/self.message_size.average() as f32;
 		//println!("load={} r={} size={}",self.load,r,self.message_size);
 		if r>1.0
 		{
@@ -288,27 +950,101 @@ impl Traffic for Homogeneous
 	{
 		false
 	}
+	fn should_generate(&self, server:usize, cycle:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		let p=self.probability_per_cycle(server);
+		match self.arrival
+		{
+			None =>
+			{
+				let r=rng.borrow_mut().gen_range(0f32..1f32);
+				r<p
+			},
+			Some(ref process) =>
+			{
+				if server>=self.servers
+				{
+					//Servers out of the traffic have no arrival state.
+					return false;
+				}
+				process.should_generate(&mut self.arrival_states.borrow_mut()[server],p as f64,cycle,rng)
+			},
+		}
+	}
 	fn server_state(&self, _server:usize, _cycle:usize) -> ServerTrafficState
 	{
 		ServerTrafficState::Generating
 	}
-}
-
-impl Homogeneous
-{
-	pub fn new(arg:TrafficBuilderArgument) -> Homogeneous
+	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
 	{
-		let mut servers=None;
-		let mut load=None;
-		let mut pattern=None;
-		let mut message_size=None;
-		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
+		let process = self.arrival.as_ref()?;
+		let states = self.arrival_states.borrow();
+		let queried_cycles:usize = states.iter().map(|state|state.queried_cycles).sum();
+		let on_cycles:usize = states.iter().map(|state|state.on_cycles).sum();
+		let arrivals:usize = states.iter().map(|state|state.arrivals).sum();
+		let gaps:usize = states.iter().map(|state|state.arrivals.saturating_sub(1)).sum();
+		let total_gap:f64 = states.iter().map(|state|state.total_gap).sum();
+		let total_squared_gap:f64 = states.iter().map(|state|state.total_squared_gap).sum();
+		//Without queried cycles or without two arrivals in some server the averages are undefined.
+		let (offered_load,burst_fraction) = if queried_cycles==0
 		{
-			if cv_name!="HomogeneousTraffic"
-			{
-				panic!("A Homogeneous must be created from a `HomogeneousTraffic` object not `{}`",cv_name);
-			}
-			for &(ref name,ref value) in cv_pairs
+			(ConfigurationValue::None,ConfigurationValue::None)
+		}
+		else
+		{
+			let arrival_rate = arrivals as f64/queried_cycles as f64;
+			(ConfigurationValue::Number(arrival_rate*self.message_size.average()),ConfigurationValue::Number(on_cycles as f64/queried_cycles as f64))
+		};
+		let (average_gap,gap_coefficient_of_variation) = if gaps==0
+		{
+			(ConfigurationValue::None,ConfigurationValue::None)
+		}
+		else
+		{
+			let average_gap = total_gap/gaps as f64;
+			let gap_variance = total_squared_gap/gaps as f64 - average_gap*average_gap;
+			(ConfigurationValue::Number(average_gap),ConfigurationValue::Number(gap_variance.max(0.0).sqrt()/average_gap))
+		};
+		let content = vec![
+			(String::from("arrival_process"),ConfigurationValue::Literal(process.name().to_string())),
+			(String::from("arrivals"),ConfigurationValue::Number(arrivals as f64)),
+			(String::from("offered_load"),offered_load),
+			(String::from("average_inter_arrival_time"),average_gap),
+			(String::from("inter_arrival_coefficient_of_variation"),gap_coefficient_of_variation),
+			(String::from("burst_fraction"),burst_fraction),
+		];
+		Some(ConfigurationValue::Object(String::from("ArrivalStatistics"),content))
+	}
+	fn reset_statistics(&mut self, _next_cycle:usize)
+	{
+		for state in self.arrival_states.borrow_mut().iter_mut()
+		{
+			state.queried_cycles=0;
+			state.on_cycles=0;
+			state.arrivals=0;
+			state.last_arrival=None;
+			state.total_gap=0.0;
+			state.total_squared_gap=0.0;
+		}
+	}
+}
+
+impl Homogeneous
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Homogeneous
+	{
+		let mut servers=None;
+		let mut load=None;
+		let mut pattern=None;
+		let mut message_size=None;
+		let mut arrival=None;
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
+		{
+			if cv_name!="HomogeneousTraffic"
+			{
+				panic!("A Homogeneous must be created from a `HomogeneousTraffic` object not `{}`",cv_name);
+			}
+			for &(ref name,ref value) in cv_pairs
 			{
 				match name.as_ref()
 				{
@@ -323,11 +1059,8 @@ impl Homogeneous
 						&ConfigurationValue::Number(f) => load=Some(f as f32),
 						_ => panic!("bad value for load ({:?})",value),
 					}
-					"message_size" => match value
-					{
-						&ConfigurationValue::Number(f) => message_size=Some(f as usize),
-						_ => panic!("bad value for message_size"),
-					}
+					"message_size" => message_size=Some(MessageSizeDistribution::new(value)),
+					"arrival" => arrival=Some(ArrivalProcess::new(value)),
 					_ => panic!("Nothing to do with field {} in HomogeneousTraffic",name),
 				}
 			}
@@ -346,13 +1079,23 @@ impl Homogeneous
 			println!("WARNING: Generating traffic over {} servers when the topology has {} servers.",servers,topo_servers);
 		}
 		pattern.initialize(servers, servers, arg.topology, arg.rng);
-		Homogeneous{
+		let mut traffic = Homogeneous{
 			servers,
 			pattern,
 			message_size,
 			load,
 			generated_messages: BTreeSet::new(),
+			arrival: None,
+			arrival_states: RefCell::new(vec![]),
+		};
+		if let Some(process) = arrival
+		{
+			let p = traffic.probability_per_cycle(0) as f64;
+			process.check_probability(p);
+			traffic.arrival_states = RefCell::new( (0..servers).map(|_|process.initial_state(p,arg.rng)).collect() );
+			traffic.arrival = Some(process);
 		}
+		traffic
 	}
 }
 
@@ -450,6 +1193,28 @@ impl Traffic for Sum
 		}
 		state
 	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		list_statistics(&self.list,cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		for traffic in self.list.iter_mut()
+		{
+			traffic.reset_statistics(next_cycle);
+		}
+	}
+}
+
+///The statistics of a list of traffics, as an array with `None` for the traffics without them. `None` if no traffic has statistics.
+fn list_statistics(traffics:&[Box<dyn Traffic>], cycle:usize) -> Option<ConfigurationValue>
+{
+	let statistics:Vec<Option<ConfigurationValue>> = traffics.iter().map(|traffic|traffic.statistics(cycle)).collect();
+	if statistics.iter().all(|content|content.is_none())
+	{
+		return None;
+	}
+	Some(ConfigurationValue::Array(statistics.into_iter().map(|content|content.unwrap_or(ConfigurationValue::None)).collect()))
 }
 
 impl Sum
@@ -567,6 +1332,14 @@ impl Traffic for Shifted
 	{
 		self.traffic.server_state(server-self.shift,cycle)
 	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		self.traffic.statistics(cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.traffic.reset_statistics(next_cycle)
+	}
 }
 
 impl Shifted
@@ -762,6 +1535,14 @@ impl Traffic for SubRangeTraffic
 	{
 		self.traffic.server_state(server,cycle)
 	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		self.traffic.statistics(cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.traffic.reset_statistics(next_cycle)
+	}
 }
 
 impl SubRangeTraffic
@@ -822,8 +1603,8 @@ pub struct Burst
 	servers: usize,
 	///The pattern of the communication.
 	pattern: Box<dyn Pattern>,
-	///The size of each sent message.
-	message_size: usize,
+	///The distribution of the size of the sent messages.
+	message_size: MessageSizeDistribution,
 	///The number of messages each server has pending to sent.
 	pending_messages: Vec<usize>,
 	///Set of generated messages.
@@ -848,7 +1629,7 @@ impl Traffic for Burst
 		let message=Rc::new(Message{
 			origin: origin,
 			destination,
-			size:self.message_size,
+			size:self.message_size.sample(rng),
 			creation_cycle: cycle,
 		});
 		self.generated_messages.insert(message.as_ref() as *const Message);
@@ -927,11 +1708,7 @@ impl Burst
 						&ConfigurationValue::Number(f) => messages_per_server=Some(f as usize),
 						_ => panic!("bad value for messages_per_server ({:?})",value),
 					}
-					"message_size" => match value
-					{
-						&ConfigurationValue::Number(f) => message_size=Some(f as usize),
-						_ => panic!("bad value for message_size"),
-					}
+					"message_size" => message_size=Some(MessageSizeDistribution::new(value)),
 					_ => panic!("Nothing to do with field {} in Burst",name),
 				}
 			}
@@ -1178,10 +1955,24 @@ impl Traffic for TimeSequenced
 			return ServerTrafficState::Finished;
 		}
 		let state = self.traffics[traffic_index].server_state(server,cycle);
-		if let ServerTrafficState::Finished = state {
-			ServerTrafficState::WaitingCycle { cycle:self.times[traffic_index] }
-		} else {
-			state
+		//The cycle in which the current traffic ends and the next one begins.
+		let end = cycle - offset + self.times[traffic_index];
+		match state
+		{
+			ServerTrafficState::Finished => ServerTrafficState::WaitingCycle { cycle:end },
+			ServerTrafficState::WaitingCycle{cycle:wait} => ServerTrafficState::WaitingCycle { cycle:wait.min(end) },
+			_ => state,
+		}
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		list_statistics(&self.traffics,cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		for traffic in self.traffics.iter_mut()
+		{
+			traffic.reset_statistics(next_cycle);
 		}
 	}
 }
@@ -1724,3 +2515,2477 @@ impl BoundedDifference
 	}
 }
 
+
+///A message read from a trace file, to be generated at `cycle`.
+#[derive(Debug,Clone)]
+struct TraceRecord
+{
+	cycle: usize,
+	origin: usize,
+	destination: usize,
+	size: usize,
+}
+
+///The reading of a trace file. The records are read as the simulation advances, so the trace is never loaded whole.
+#[derive(Debug)]
+struct TraceStream
+{
+	///The file with the records.
+	filename: String,
+	///The remaining lines of the file in the current pass. `None` when the trace has been exhausted.
+	lines: Option<Lines<BufReader<File>>>,
+	///The number of the last line read, for error messages.
+	line_number: usize,
+	///The next record, not yet due.
+	lookahead: Option<TraceRecord>,
+	///The cycle added to all the records in the current pass over the file.
+	offset: usize,
+	///The greatest cycle of a record in the current pass.
+	last_cycle: usize,
+	///Whether some record has been read in the current pass.
+	pass_has_records: bool,
+	///The server of each rank of the trace.
+	rank_to_server: Vec<usize>,
+	///Multiplies the times in the trace to get cycles.
+	time_scale: f64,
+	///Multiplies the sizes in the trace to get phits.
+	size_scale: f64,
+	///Whether to start again the trace when reaching its end.
+	looping: bool,
+	///The due records of each server, not generated yet.
+	pending: Vec<VecDeque<TraceRecord>>,
+}
+
+impl TraceStream
+{
+	///Reads the records until the first one beyond `cycle`, making due records pending in their servers.
+	fn advance(&mut self, cycle:usize)
+	{
+		loop
+		{
+			if self.lookahead.is_none()
+			{
+				self.lookahead = self.read_record();
+			}
+			match self.lookahead
+			{
+				Some(ref record) if record.cycle<=cycle =>
+				{
+					let record = self.lookahead.take().unwrap();
+					self.pending[record.origin].push_back(record);
+				},
+				_ => break,
+			}
+		}
+	}
+	///Reads the next record of the file, opening it again when looping.
+	fn read_record(&mut self) -> Option<TraceRecord>
+	{
+		loop
+		{
+			let line = match self.lines
+			{
+				None => return None,
+				Some(ref mut lines) => lines.next(),
+			};
+			let line = match line
+			{
+				Some(line) => line.unwrap_or_else(|error|panic!("could not read the trace file {}: {}",self.filename,error)),
+				None =>
+				{
+					if self.looping && self.pass_has_records
+					{
+						self.offset = self.last_cycle+1;
+						self.pass_has_records = false;
+						self.lines = Some(open_trace(&self.filename));
+						self.line_number = 0;
+						continue;
+					}
+					else
+					{
+						self.lines = None;
+						return None;
+					}
+				},
+			};
+			self.line_number += 1;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#')
+			{
+				continue;
+			}
+			let fields:Vec<&str> = line.split(|c:char|c==','||c.is_whitespace()).filter(|s|!s.is_empty()).collect();
+			if fields.len()<4 || fields.len()>5
+			{
+				panic!("line {} of the trace {} does not have the form `time source destination size [tag]`",self.line_number,self.filename);
+			}
+			let time:f64 = fields[0].parse().unwrap_or_else(|_|panic!("bad time in line {} of the trace {}",self.line_number,self.filename));
+			let rank_field = |index:usize| -> usize {
+				let rank:usize = fields[index].parse().unwrap_or_else(|_|panic!("bad rank in line {} of the trace {}",self.line_number,self.filename));
+				*self.rank_to_server.get(rank).unwrap_or_else(||panic!("rank {} in line {} of the trace {} is beyond the number of ranks",rank,self.line_number,self.filename))
+			};
+			let origin = rank_field(1);
+			let destination = rank_field(2);
+			let size:f64 = fields[3].parse().unwrap_or_else(|_|panic!("bad size in line {} of the trace {}",self.line_number,self.filename));
+			//The tag, if any, is accepted but not used.
+			let cycle = self.offset + (time*self.time_scale) as usize;
+			if self.pass_has_records && cycle<self.last_cycle
+			{
+				panic!("the records of the trace {} must be sorted by time (line {})",self.filename,self.line_number);
+			}
+			self.last_cycle = cycle;
+			self.pass_has_records = true;
+			let size = ((size*self.size_scale).ceil() as usize).max(1);
+			return Some(TraceRecord{cycle,origin,destination,size});
+		}
+	}
+	fn is_exhausted(&self) -> bool
+	{
+		self.lines.is_none() && self.lookahead.is_none()
+	}
+}
+
+fn open_trace(filename:&str) -> Lines<BufReader<File>>
+{
+	let file=File::open(filename).unwrap_or_else(|error|panic!("could not open the trace file {}: {}",filename,error));
+	BufReader::new(file).lines()
+}
+
+///Replays the messages recorded in a trace file.
+///The file is read as the simulation advances, so that idle servers are in `WaitingCycle` until their next message.
+#[derive(Debug)]
+pub struct Trace
+{
+	///The file being read and the messages not yet generated. Inside a `RefCell` because the reading advances when querying the servers.
+	stream: RefCell<TraceStream>,
+	///Set of generated messages.
+	generated_messages: BTreeSet<*const Message>,
+}
+
+impl Quantifiable for Trace
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<Trace>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Traffic for Trace
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		let mut stream = self.stream.borrow_mut();
+		if origin>=stream.pending.len()
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		stream.advance(cycle);
+		let record = match stream.pending[origin].front()
+		{
+			Some(record) if record.cycle<=cycle => stream.pending[origin].pop_front().unwrap(),
+			_ => panic!("server {} has not any message of the trace at cycle {}",origin,cycle),
+		};
+		if record.destination==origin
+		{
+			return Err(TrafficError::SelfMessage);
+		}
+		let message=Rc::new(Message{
+			origin,
+			destination: record.destination,
+			size: record.size,
+			creation_cycle: cycle,
+		});
+		self.generated_messages.insert(message.as_ref() as *const Message);
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		let stream = self.stream.borrow();
+		if server<stream.pending.len() && !stream.pending[server].is_empty() { 1.0 } else { 0.0 }
+	}
+	fn try_consume(&mut self, _server:usize, message: Rc<Message>, _cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> bool
+	{
+		let message_ptr=message.as_ref() as *const Message;
+		self.generated_messages.remove(&message_ptr)
+	}
+	fn is_finished(&self) -> bool
+	{
+		let stream = self.stream.borrow();
+		self.generated_messages.is_empty() && stream.is_exhausted() && stream.pending.iter().all(|p|p.is_empty())
+	}
+	fn should_generate(&self, server:usize, cycle:usize, _rng: &RefCell<StdRng>) -> bool
+	{
+		let mut stream = self.stream.borrow_mut();
+		if server>=stream.pending.len()
+		{
+			return false;
+		}
+		stream.advance(cycle);
+		match stream.pending[server].front()
+		{
+			Some(record) => record.cycle<=cycle,
+			None => false,
+		}
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		let mut stream = self.stream.borrow_mut();
+		if server>=stream.pending.len()
+		{
+			return ServerTrafficState::Finished;
+		}
+		stream.advance(cycle);
+		if let Some(record) = stream.pending[server].front()
+		{
+			return if record.cycle<=cycle { ServerTrafficState::Generating } else { ServerTrafficState::WaitingCycle{cycle:record.cycle} };
+		}
+		match stream.lookahead
+		{
+			//No record for this server may appear before the next one in the file.
+			Some(ref record) => ServerTrafficState::WaitingCycle{cycle:record.cycle},
+			None => if self.generated_messages.is_empty() { ServerTrafficState::Finished } else { ServerTrafficState::FinishedGenerating },
+		}
+	}
+}
+
+impl Trace
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Trace
+	{
+		let mut filename=None;
+		let mut ranks=None;
+		let mut mapping=None;
+		let mut time_scale=1.0;
+		let mut size_scale=1.0;
+		let mut looping=false;
+		match_object_panic!(arg.cv,"Trace",value,
+			"filename" => match value
+			{
+				&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
+				_ => panic!("bad value for filename"),
+			},
+			"ranks" => match value
+			{
+				&ConfigurationValue::Number(f) => ranks=Some(f as usize),
+				_ => panic!("bad value for ranks"),
+			},
+			"mapping" => mapping=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+			"time_scale" => match value
+			{
+				&ConfigurationValue::Number(f) => time_scale=f,
+				_ => panic!("bad value for time_scale"),
+			},
+			"size_scale" => match value
+			{
+				&ConfigurationValue::Number(f) => size_scale=f,
+				_ => panic!("bad value for size_scale"),
+			},
+			"looping" => match value
+			{
+				&ConfigurationValue::True => looping=true,
+				&ConfigurationValue::False => looping=false,
+				_ => panic!("bad value for looping"),
+			},
+		);
+		let filename=filename.expect("There were no filename");
+		let servers=arg.topology.num_servers();
+		let ranks=ranks.unwrap_or(servers);
+		let rank_to_server:Vec<usize> = match mapping
+		{
+			Some(mut pattern) =>
+			{
+				pattern.initialize(ranks, servers, arg.topology, arg.rng);
+				(0..ranks).map(|rank|pattern.get_destination(rank,arg.topology,arg.rng)).collect()
+			},
+			None =>
+			{
+				if ranks>servers
+				{
+					panic!("The trace has {} ranks but there are only {} servers. Give a mapping.",ranks,servers);
+				}
+				(0..ranks).collect()
+			},
+		};
+		let lines = open_trace(&filename);
+		Trace{
+			stream: RefCell::new(TraceStream{
+				filename,
+				lines: Some(lines),
+				line_number: 0,
+				lookahead: None,
+				offset: 0,
+				last_cycle: 0,
+				pass_has_records: false,
+				rank_to_server,
+				time_scale,
+				size_scale,
+				looping,
+				pending: vec![VecDeque::new();servers],
+			}),
+			generated_messages: BTreeSet::new(),
+		}
+	}
+}
+
+///The kind of an operation in the schedule of a rank, as in the GOAL language.
+#[derive(Debug,Clone)]
+pub enum ScheduleOperationKind
+{
+	///Send a message of `size` phits to the rank `destination`. It is completed when the message is generated.
+	Send{destination:usize, size:usize, tag:usize},
+	///Wait for a message from the rank `source` with the given `tag`. It is completed when the message is consumed.
+	Receive{source:usize, tag:usize},
+	///Compute during the given amount of cycles.
+	Compute{cycles:usize},
+}
+
+///An operation in the schedule of a rank, with its dependencies given by the indices of other operations of the same rank.
+#[derive(Debug,Clone)]
+pub struct ScheduleOperation
+{
+	pub kind: ScheduleOperationKind,
+	///The operations that must be completed before starting this one.
+	pub requires: Vec<usize>,
+	///The operations that must have been started before starting this one.
+	pub irequires: Vec<usize>,
+}
+
+///The execution of the schedule of a rank.
+#[derive(Debug)]
+struct RankExecution
+{
+	operations: Vec<ScheduleOperation>,
+	///The number of dependencies not yet satisfied of each operation.
+	remaining_dependencies: Vec<usize>,
+	///The operations waiting for the completion of each operation.
+	completion_dependents: Vec<Vec<usize>>,
+	///The operations waiting for the start of each operation.
+	start_dependents: Vec<Vec<usize>>,
+	///The cycle at which each operation may start, after its satisfied dependencies.
+	ready_cycle: Vec<usize>,
+	///Operations with all their dependencies satisfied, to be started.
+	ready: Vec<usize>,
+	///Sends already started, waiting for the server to generate their messages.
+	pending_sends: VecDeque<usize>,
+	///Computations in progress, as `(ending_cycle,operation)`.
+	computing: BTreeSet<(usize,usize)>,
+	///Started receives waiting for their message, by `(source,tag)`.
+	posted_receives: BTreeMap<(usize,usize),VecDeque<usize>>,
+	///Number of messages arrived before their receive was started, by `(source,tag)`.
+	unexpected_messages: BTreeMap<(usize,usize),usize>,
+	completed_operations: usize,
+	///The cycle at which each operation was started.
+	start_cycles: Vec<Option<usize>>,
+	///The cycle at which each operation was completed.
+	completion_cycles: Vec<Option<usize>>,
+	///The cycle at which the last operation was completed.
+	finish_cycle: Option<usize>,
+}
+
+impl RankExecution
+{
+	fn new(operations:Vec<ScheduleOperation>) -> RankExecution
+	{
+		let n=operations.len();
+		let mut remaining_dependencies=vec![0;n];
+		let mut completion_dependents=vec![vec![];n];
+		let mut start_dependents=vec![vec![];n];
+		for (index,operation) in operations.iter().enumerate()
+		{
+			for &required in operation.requires.iter()
+			{
+				completion_dependents[required].push(index);
+				remaining_dependencies[index]+=1;
+			}
+			for &required in operation.irequires.iter()
+			{
+				start_dependents[required].push(index);
+				remaining_dependencies[index]+=1;
+			}
+		}
+		let ready=(0..n).rev().filter(|&index|remaining_dependencies[index]==0).collect();
+		let mut execution=RankExecution{
+			operations,
+			remaining_dependencies,
+			completion_dependents,
+			start_dependents,
+			ready_cycle: vec![0;n],
+			ready,
+			pending_sends: VecDeque::new(),
+			computing: BTreeSet::new(),
+			posted_receives: BTreeMap::new(),
+			unexpected_messages: BTreeMap::new(),
+			completed_operations: 0,
+			start_cycles: vec![None;n],
+			completion_cycles: vec![None;n],
+			finish_cycle: if n==0 { Some(0) } else { None },
+		};
+		execution.run();
+		execution
+	}
+	///Starts the ready operations, and the ones made ready by them.
+	fn run(&mut self)
+	{
+		while let Some(index) = self.ready.pop()
+		{
+			let cycle = self.ready_cycle[index];
+			self.start_cycles[index]=Some(cycle);
+			match self.operations[index].kind
+			{
+				ScheduleOperationKind::Send{..} => self.pending_sends.push_back(index),
+				ScheduleOperationKind::Compute{cycles} =>
+				{
+					if cycles==0
+					{
+						self.complete(index,cycle);
+					}
+					else
+					{
+						self.computing.insert((cycle+cycles,index));
+					}
+				},
+				ScheduleOperationKind::Receive{source,tag} =>
+				{
+					let arrived = match self.unexpected_messages.get_mut(&(source,tag))
+					{
+						Some(count) if *count>0 => { *count-=1; true },
+						_ => false,
+					};
+					if arrived
+					{
+						self.complete(index,cycle);
+					}
+					else
+					{
+						self.posted_receives.entry((source,tag)).or_insert_with(VecDeque::new).push_back(index);
+					}
+				},
+			}
+			for dependent_index in 0..self.start_dependents[index].len()
+			{
+				let dependent = self.start_dependents[index][dependent_index];
+				self.satisfy(dependent,cycle);
+			}
+		}
+	}
+	fn satisfy(&mut self, index:usize, cycle:usize)
+	{
+		self.remaining_dependencies[index]-=1;
+		self.ready_cycle[index]=self.ready_cycle[index].max(cycle);
+		if self.remaining_dependencies[index]==0
+		{
+			self.ready.push(index);
+		}
+	}
+	fn complete(&mut self, index:usize, cycle:usize)
+	{
+		self.completed_operations+=1;
+		self.completion_cycles[index]=Some(cycle);
+		if self.completed_operations==self.operations.len()
+		{
+			self.finish_cycle=Some(cycle);
+		}
+		for dependent_index in 0..self.completion_dependents[index].len()
+		{
+			let dependent = self.completion_dependents[index][dependent_index];
+			self.satisfy(dependent,cycle);
+		}
+	}
+	///Completes the computations ending not later than `cycle`.
+	fn advance(&mut self, cycle:usize)
+	{
+		while let Some(&(end,index)) = self.computing.iter().next()
+		{
+			if end>cycle
+			{
+				break;
+			}
+			self.computing.remove(&(end,index));
+			self.complete(index,end);
+			self.run();
+		}
+	}
+	///Takes the next send to generate, completing it.
+	fn take_send(&mut self, cycle:usize) -> Option<(usize,usize,usize)>
+	{
+		let index = self.pending_sends.pop_front()?;
+		self.complete(index,cycle);
+		self.run();
+		match self.operations[index].kind
+		{
+			ScheduleOperationKind::Send{destination,size,tag} => Some((destination,size,tag)),
+			_ => unreachable!(),
+		}
+	}
+	///A message from `source` with `tag` has been received.
+	fn receive(&mut self, source:usize, tag:usize, cycle:usize)
+	{
+		let posted = match self.posted_receives.get_mut(&(source,tag))
+		{
+			Some(queue) => queue.pop_front(),
+			None => None,
+		};
+		match posted
+		{
+			Some(index) =>
+			{
+				self.complete(index,cycle);
+				self.run();
+			},
+			None => *self.unexpected_messages.entry((source,tag)).or_insert(0)+=1,
+		}
+	}
+	fn is_waiting_messages(&self) -> bool
+	{
+		self.posted_receives.values().any(|queue|!queue.is_empty())
+	}
+}
+
+///The state of all the ranks of a schedule. Inside a `RefCell` in the traffic because the computations advance when querying the servers.
+#[derive(Debug)]
+struct ScheduleExecution
+{
+	ranks: Vec<RankExecution>,
+}
+
+impl ScheduleExecution
+{
+	///The cycle at which the last rank completed each phase, for the phases completed in sequence.
+	///`ends[phase][rank]` is the operation that completes the phase in that rank.
+	fn phase_completions(&self, ends:&[Vec<usize>]) -> Vec<usize>
+	{
+		let mut completions = vec![];
+		for ends in ends.iter()
+		{
+			let cycles:Option<Vec<usize>> = ends.iter().enumerate().map(|(rank,&end)|self.ranks[rank].completion_cycles[end]).collect();
+			match cycles
+			{
+				Some(cycles) => completions.push(cycles.into_iter().max().unwrap_or(0)),
+				None => break,
+			}
+		}
+		completions
+	}
+}
+
+///Executes a schedule of send, receive, and compute operations with dependencies for each rank of an application, as the GOAL language of LogGOPSim.
+///Several ranks may be mapped to the same server; their messages are delivered without entering the network.
+#[derive(Debug)]
+pub struct Goal
+{
+	execution: RefCell<ScheduleExecution>,
+	///The server of each rank.
+	rank_to_server: Vec<usize>,
+	///The ranks mapped to each server.
+	server_ranks: Vec<Vec<usize>>,
+	///The generated messages, with their `(source_rank,destination_rank,tag)`.
+	generated_messages: BTreeMap<*const Message,(usize,usize,usize)>,
+}
+
+impl Quantifiable for Goal
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<Goal>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Traffic for Goal
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.server_ranks.len()
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let mut execution = self.execution.borrow_mut();
+		for &rank in self.server_ranks[origin].iter()
+		{
+			execution.ranks[rank].advance(cycle);
+			if let Some((destination_rank,size,tag)) = execution.ranks[rank].take_send(cycle)
+			{
+				let destination = self.rank_to_server[destination_rank];
+				if destination==origin
+				{
+					//Both ranks are in the same server.
+					execution.ranks[destination_rank].receive(rank,tag,cycle);
+					return Err(TrafficError::SelfMessage);
+				}
+				let message=Rc::new(Message{
+					origin,
+					destination,
+					size,
+					creation_cycle: cycle,
+				});
+				self.generated_messages.insert(message.as_ref() as *const Message,(rank,destination_rank,tag));
+				return Ok(message);
+			}
+		}
+		panic!("server {} has not any send of the schedule at cycle {}",origin,cycle);
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		let execution = self.execution.borrow();
+		if server<self.server_ranks.len() && self.server_ranks[server].iter().any(|&rank|!execution.ranks[rank].pending_sends.is_empty()) { 1.0 } else { 0.0 }
+	}
+	fn try_consume(&mut self, _server:usize, message: Rc<Message>, cycle:usize, _topology:&Box<dyn Topology>, _rng: &RefCell<StdRng>) -> bool
+	{
+		let message_ptr=message.as_ref() as *const Message;
+		match self.generated_messages.remove(&message_ptr)
+		{
+			Some((source_rank,destination_rank,tag)) =>
+			{
+				let mut execution = self.execution.borrow_mut();
+				execution.ranks[destination_rank].advance(cycle);
+				execution.ranks[destination_rank].receive(source_rank,tag,cycle);
+				true
+			},
+			None => false,
+		}
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.generated_messages.is_empty() && self.execution.borrow().ranks.iter().all(|rank|rank.finish_cycle.is_some())
+	}
+	fn should_generate(&self, server:usize, cycle:usize, _rng: &RefCell<StdRng>) -> bool
+	{
+		if server>=self.server_ranks.len()
+		{
+			return false;
+		}
+		let mut execution = self.execution.borrow_mut();
+		let mut generate=false;
+		for &rank in self.server_ranks[server].iter()
+		{
+			execution.ranks[rank].advance(cycle);
+			generate = generate || !execution.ranks[rank].pending_sends.is_empty();
+		}
+		generate
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		if server>=self.server_ranks.len()
+		{
+			return ServerTrafficState::Finished;
+		}
+		let mut execution = self.execution.borrow_mut();
+		let mut waiting_messages = false;
+		let mut finished = true;
+		let mut next_cycle:Option<usize> = None;
+		for &rank in self.server_ranks[server].iter()
+		{
+			let rank = &mut execution.ranks[rank];
+			rank.advance(cycle);
+			if !rank.pending_sends.is_empty()
+			{
+				return ServerTrafficState::Generating;
+			}
+			waiting_messages = waiting_messages || rank.is_waiting_messages();
+			finished = finished && rank.finish_cycle.is_some();
+			if let Some(&(end,_)) = rank.computing.iter().next()
+			{
+				next_cycle = Some(next_cycle.map_or(end,|c|c.min(end)));
+			}
+		}
+		//A consumed message may start a send, so only without posted receives the computations tell when the state will change.
+		if waiting_messages
+		{
+			ServerTrafficState::WaitingData
+		}
+		else if let Some(cycle) = next_cycle
+		{
+			ServerTrafficState::WaitingCycle{cycle}
+		}
+		else if finished
+		{
+			ServerTrafficState::Finished
+		}
+		else
+		{
+			ServerTrafficState::UnspecifiedWait
+		}
+	}
+	fn statistics(&self, _cycle:usize) -> Option<ConfigurationValue>
+	{
+		let execution = self.execution.borrow();
+		let finish_cycles:Vec<ConfigurationValue> = execution.ranks.iter().map(|rank|match rank.finish_cycle{
+			Some(cycle) => ConfigurationValue::Number(cycle as f64),
+			None => ConfigurationValue::None,
+		}).collect();
+		let finished_ranks = execution.ranks.iter().filter(|rank|rank.finish_cycle.is_some()).count();
+		let completion_cycle = if finished_ranks==execution.ranks.len()
+		{
+			ConfigurationValue::Number(execution.ranks.iter().map(|rank|rank.finish_cycle.unwrap()).max().unwrap_or(0) as f64)
+		}
+		else
+		{
+			ConfigurationValue::None
+		};
+		Some(ConfigurationValue::Object(String::from("ScheduleStatistics"),vec![
+			(String::from("completion_cycle"),completion_cycle),
+			(String::from("finished_ranks"),ConfigurationValue::Number(finished_ranks as f64)),
+			(String::from("rank_finish_cycles"),ConfigurationValue::Array(finish_cycles)),
+		]))
+	}
+}
+
+impl Goal
+{
+	///The statistics of the schedule together with the completion of its phases, as the repetitions of a collective or the iterations of an application.
+	///They are reported as `completed_{phase}s`, `{phase}_completion_cycles`, and `average_{phase}_time`. Also returns the completion cycle of each completed phase.
+	fn phase_statistics(&self, cycle:usize, phase:&str, ends:&[Vec<usize>]) -> (Vec<(String,ConfigurationValue)>,Vec<usize>)
+	{
+		let mut content = match self.statistics(cycle)
+		{
+			Some(ConfigurationValue::Object(_,content)) => content,
+			_ => vec![],
+		};
+		let completions = self.execution.borrow().phase_completions(ends);
+		let times:Vec<usize> = completions.iter().enumerate().map(|(index,&completion)|if index==0 { completion } else { completion-completions[index-1] }).collect();
+		let average_time = if times.is_empty() { ConfigurationValue::None } else { ConfigurationValue::Number(times.iter().sum::<usize>() as f64/times.len() as f64) };
+		content.push((format!("completed_{}s",phase),ConfigurationValue::Number(completions.len() as f64)));
+		content.push((format!("{}_completion_cycles",phase),ConfigurationValue::Array(completions.iter().map(|&c|ConfigurationValue::Number(c as f64)).collect())));
+		content.push((format!("average_{}_time",phase),average_time));
+		(content,completions)
+	}
+	pub fn new(arg:TrafficBuilderArgument) -> Goal
+	{
+		let mut filename=None;
+		let mut mapping=None;
+		let mut time_scale=1.0;
+		let mut size_scale=1.0;
+		match_object_panic!(arg.cv,"Goal",value,
+			"filename" => match value
+			{
+				&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
+				_ => panic!("bad value for filename"),
+			},
+			"mapping" => mapping=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+			"time_scale" => match value
+			{
+				&ConfigurationValue::Number(f) => time_scale=f,
+				_ => panic!("bad value for time_scale"),
+			},
+			"size_scale" => match value
+			{
+				&ConfigurationValue::Number(f) => size_scale=f,
+				_ => panic!("bad value for size_scale"),
+			},
+		);
+		let filename=filename.expect("There were no filename");
+		let file=File::open(&filename).unwrap_or_else(|error|panic!("could not open the schedule file {}: {}",filename,error));
+		let schedules=parse_goal(BufReader::new(file),&filename,time_scale,size_scale);
+		Goal::from_schedules(schedules,mapping,&arg)
+	}
+	///Builds the traffic from the operations of each rank, mapping the ranks into servers with an optional pattern.
+	pub fn from_schedules(schedules:Vec<Vec<ScheduleOperation>>, mapping:Option<Box<dyn Pattern>>, arg:&TrafficBuilderArgument) -> Goal
+	{
+		let ranks=schedules.len();
+		let servers=arg.topology.num_servers();
+		let rank_to_server:Vec<usize> = match mapping
+		{
+			Some(mut pattern) =>
+			{
+				pattern.initialize(ranks, servers, arg.topology, arg.rng);
+				(0..ranks).map(|rank|pattern.get_destination(rank,arg.topology,arg.rng)).collect()
+			},
+			None =>
+			{
+				if ranks>servers
+				{
+					panic!("The schedule has {} ranks but there are only {} servers. Give a mapping.",ranks,servers);
+				}
+				(0..ranks).collect()
+			},
+		};
+		Goal::from_schedules_on_servers(schedules,rank_to_server,servers)
+	}
+	///Builds the traffic executing the `schedules` with each rank `r` in the server `rank_to_server[r]`, out of `servers`.
+	pub fn from_schedules_on_servers(schedules:Vec<Vec<ScheduleOperation>>, rank_to_server:Vec<usize>, servers:usize) -> Goal
+	{
+		check_schedules(&schedules,"given to Goal");
+		if rank_to_server.len()!=schedules.len()
+		{
+			panic!("There are {} ranks in the schedule but {} of them mapped into servers.",schedules.len(),rank_to_server.len());
+		}
+		let mut server_ranks=vec![vec![];servers];
+		for (rank,&server) in rank_to_server.iter().enumerate()
+		{
+			if server>=servers
+			{
+				panic!("The rank {} is mapped into the server {}, but there are only {} servers.",rank,server,servers);
+			}
+			server_ranks[server].push(rank);
+		}
+		Goal{
+			execution: RefCell::new(ScheduleExecution{
+				ranks: schedules.into_iter().map(RankExecution::new).collect(),
+			}),
+			rank_to_server,
+			server_ranks,
+			generated_messages: BTreeMap::new(),
+		}
+	}
+}
+
+/**Reads schedules in the GOAL language. For example
+```ignore
+num_ranks 2
+rank 0 {
+	l1: send 64b to 1 tag 3
+	l2: calc 100
+	l3: recv 64b from 1 tag 4
+	l3 requires l1
+	l2 irequires l1
+}
+rank 1 {
+	l1: recv 64b from 0 tag 3
+	l2: send 64b to 0 tag 4
+	l2 requires l1
+}
+```
+Sizes are multiplied by `size_scale` to get phits and computation times by `time_scale` to get cycles. The tag is 0 when not given, and the `cpu` and `nic` options are ignored. Lines starting with `//` or `#` are comments.
+It panics on sends or receives with ranks beyond `num_ranks` and on dependencies forming a cycle inside a rank.
+*/
+pub fn parse_goal<R:BufRead>(reader:R, name:&str, time_scale:f64, size_scale:f64) -> Vec<Vec<ScheduleOperation>>
+{
+	let mut schedules:Vec<Vec<ScheduleOperation>> = vec![];
+	//The rank being read, with its labels and dependencies `(dependent,required,immediate)`.
+	let mut current:Option<(usize,Vec<ScheduleOperation>,BTreeMap<String,usize>,Vec<(String,String,bool)>)> = None;
+	for (line_index,line) in reader.lines().enumerate()
+	{
+		let line = line.unwrap_or_else(|error|panic!("could not read the schedule {}: {}",name,error));
+		let line_number = line_index+1;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with("//") || line.starts_with('#')
+		{
+			continue;
+		}
+		let tokens:Vec<&str> = line.split_whitespace().collect();
+		let number = |token:&str| -> f64 {
+			token.trim_end_matches('b').parse().unwrap_or_else(|_|panic!("bad number `{}` in line {} of the schedule {}",token,line_number,name))
+		};
+		match tokens[0]
+		{
+			"num_ranks" =>
+			{
+				let ranks = number(tokens.get(1).unwrap_or(&"")) as usize;
+				if schedules.len()<ranks
+				{
+					schedules.resize(ranks,vec![]);
+				}
+			},
+			"rank" =>
+			{
+				if current.is_some() || tokens.len()<3 || tokens[2]!="{"
+				{
+					panic!("bad rank declaration in line {} of the schedule {}",line_number,name);
+				}
+				current = Some((number(tokens[1]) as usize,vec![],BTreeMap::new(),vec![]));
+			},
+			"}" =>
+			{
+				let (rank,mut operations,labels,dependencies) = current.take().unwrap_or_else(||panic!("unmatched `}}` in line {} of the schedule {}",line_number,name));
+				for (dependent,required,immediate) in dependencies
+				{
+					let label_index = |label:&String| *labels.get(label).unwrap_or_else(||panic!("unknown label {} in rank {} of the schedule {}",label,rank,name));
+					let required = label_index(&required);
+					let dependent = &mut operations[label_index(&dependent)];
+					if immediate { dependent.irequires.push(required) } else { dependent.requires.push(required) }
+				}
+				if schedules.len()<=rank
+				{
+					schedules.resize(rank+1,vec![]);
+				}
+				schedules[rank]=operations;
+			},
+			_ =>
+			{
+				let (rank,ref mut operations,ref mut labels,ref mut dependencies) = *current.as_mut().unwrap_or_else(||panic!("operation outside a rank in line {} of the schedule {}",line_number,name));
+				if tokens.len()==3 && (tokens[1]=="requires" || tokens[1]=="irequires")
+				{
+					dependencies.push((tokens[0].to_string(),tokens[2].to_string(),tokens[1]=="irequires"));
+					continue;
+				}
+				let label = tokens[0].strip_suffix(':').unwrap_or_else(||panic!("expected a label in line {} of the schedule {}",line_number,name));
+				let operation_tokens = &tokens[1..];
+				//The value following a keyword, as in `tag 3`.
+				let option = |keyword:&str| operation_tokens.iter().position(|&t|t==keyword).map(|p|number(operation_tokens.get(p+1).unwrap_or(&"")) as usize);
+				let kind = match operation_tokens.get(0)
+				{
+					Some(&"send") => ScheduleOperationKind::Send{
+						size: ((number(operation_tokens.get(1).unwrap_or(&""))*size_scale).ceil() as usize).max(1),
+						destination: option("to").unwrap_or_else(||panic!("send without destination in line {} of the schedule {}",line_number,name)),
+						tag: option("tag").unwrap_or(0),
+					},
+					Some(&"recv") => ScheduleOperationKind::Receive{
+						source: option("from").unwrap_or_else(||panic!("recv without source in line {} of the schedule {}",line_number,name)),
+						tag: option("tag").unwrap_or(0),
+					},
+					Some(&"calc") => ScheduleOperationKind::Compute{
+						cycles: (number(operation_tokens.get(1).unwrap_or(&""))*time_scale) as usize,
+					},
+					_ => panic!("unknown operation in line {} of the schedule {}",line_number,name),
+				};
+				if labels.insert(label.to_string(),operations.len()).is_some()
+				{
+					panic!("repeated label {} in rank {} of the schedule {}",label,rank,name);
+				}
+				operations.push(ScheduleOperation{kind,requires:vec![],irequires:vec![]});
+			},
+		}
+	}
+	if current.is_some()
+	{
+		panic!("unclosed rank at the end of the schedule {}",name);
+	}
+	check_schedules(&schedules,name);
+	schedules
+}
+
+///Checks that the operations of the `schedules` only refer to existing ranks and operations, and that the dependencies of each rank do not form cycles, which would never let it finish.
+fn check_schedules(schedules:&[Vec<ScheduleOperation>], name:&str)
+{
+	let ranks = schedules.len();
+	for (rank,operations) in schedules.iter().enumerate()
+	{
+		for (index,operation) in operations.iter().enumerate()
+		{
+			match operation.kind
+			{
+				ScheduleOperationKind::Send{destination,..} if destination>=ranks => panic!("the operation {} of rank {} sends to rank {}, but the schedule {} has {} ranks",index,rank,destination,name,ranks),
+				ScheduleOperationKind::Receive{source,..} if source>=ranks => panic!("the operation {} of rank {} receives from rank {}, but the schedule {} has {} ranks",index,rank,source,name,ranks),
+				_ => (),
+			}
+			if let Some(&required) = operation.requires.iter().chain(operation.irequires.iter()).find(|&&required|required>=operations.len())
+			{
+				panic!("the operation {} of rank {} requires the operation {}, but the rank has {} operations in the schedule {}",index,rank,required,operations.len(),name);
+			}
+		}
+		//Remove the operations without pending dependencies until none remains, or there is a cycle.
+		let mut remaining:Vec<usize> = operations.iter().map(|operation|operation.requires.len()+operation.irequires.len()).collect();
+		let mut dependents = vec![vec![];operations.len()];
+		for (index,operation) in operations.iter().enumerate()
+		{
+			for &required in operation.requires.iter().chain(operation.irequires.iter())
+			{
+				dependents[required].push(index);
+			}
+		}
+		let mut free:Vec<usize> = (0..operations.len()).filter(|&index|remaining[index]==0).collect();
+		let mut removed = 0;
+		while let Some(index) = free.pop()
+		{
+			removed+=1;
+			for &dependent in dependents[index].iter()
+			{
+				remaining[dependent]-=1;
+				if remaining[dependent]==0
+				{
+					free.push(dependent);
+				}
+			}
+		}
+		if removed<operations.len()
+		{
+			let cycle:Vec<usize> = (0..operations.len()).filter(|&index|remaining[index]>0).collect();
+			panic!("the dependencies of rank {} in the schedule {} form a cycle, blocking the operations {:?}",rank,name,cycle);
+		}
+	}
+}
+
+///Helps to build schedules, adding operations to the ranks and returning their indices.
+#[derive(Debug,Default)]
+pub struct ScheduleBuilder
+{
+	pub schedules: Vec<Vec<ScheduleOperation>>,
+}
+
+impl ScheduleBuilder
+{
+	pub fn new(ranks:usize) -> ScheduleBuilder
+	{
+		ScheduleBuilder{
+			schedules: vec![vec![];ranks],
+		}
+	}
+	pub fn add(&mut self, rank:usize, kind:ScheduleOperationKind, requires:Vec<usize>) -> usize
+	{
+		self.schedules[rank].push(ScheduleOperation{kind,requires,irequires:vec![]});
+		self.schedules[rank].len()-1
+	}
+	pub fn send(&mut self, rank:usize, destination:usize, size:usize, tag:usize, requires:Vec<usize>) -> usize
+	{
+		self.add(rank,ScheduleOperationKind::Send{destination,size,tag},requires)
+	}
+	pub fn receive(&mut self, rank:usize, source:usize, tag:usize, requires:Vec<usize>) -> usize
+	{
+		self.add(rank,ScheduleOperationKind::Receive{source,tag},requires)
+	}
+	pub fn compute(&mut self, rank:usize, cycles:usize, requires:Vec<usize>) -> usize
+	{
+		self.add(rank,ScheduleOperationKind::Compute{cycles},requires)
+	}
+}
+
+///The algorithms available for each collective operation.
+#[derive(Debug,Clone)]
+pub enum CollectiveAlgorithm
+{
+	///Allreduce by a reduce-scatter and an allgather along a ring, each in `ranks-1` steps of `message_size/ranks` phits.
+	RingAllreduce,
+	///Allreduce by exchanging the whole vector in `log2(ranks)` steps.
+	RecursiveDoublingAllreduce,
+	///Allreduce by a reduce-scatter of recursive halving and an allgather of recursive doubling.
+	RabenseifnerAllreduce,
+	///All-to-all with every rank sending in step `s` to the rank `s` positions ahead, without waiting.
+	LinearShiftAllToAll,
+	///All-to-all in `ranks-1` synchronized steps, each exchanging with a single partner.
+	PairwiseAllToAll,
+	///All-to-all in `log2(ranks)` steps, forwarding the blocks whose destination has the bit of the step.
+	BruckAllToAll,
+	///Broadcast along a binomial tree rooted at `root`.
+	BinomialBroadcast{root:usize},
+	///Broadcast along a chain starting at `root`, with the message divided into `segments` to pipeline them.
+	PipelinedChainBroadcast{root:usize, segments:usize},
+	///Barrier by the dissemination algorithm, in `log2(ranks)` rounds of messages of `message_size`.
+	DisseminationBarrier,
+}
+
+impl CollectiveAlgorithm
+{
+	///Adds a run of the collective among the ranks in `group` to the schedules. The operations of each member without other dependencies require `start[member]`.
+	///The tags used are from `tag_base` up to `tag_base+self.tags(group.len())`.
+	///Returns the operations added to each member, all of which must be completed to complete the collective in that member.
+	pub fn add_to_schedule(&self, builder:&mut ScheduleBuilder, group:&[usize], message_size:usize, start:&[Vec<usize>], tag_base:usize) -> Vec<Vec<usize>>
+	{
+		let p = group.len();
+		let m = message_size;
+		let first_operation:Vec<usize> = group.iter().map(|&rank|builder.schedules[rank].len()).collect();
+		//The operations are built over the positions in the group.
+		let send = |builder:&mut ScheduleBuilder, member:usize, destination:usize, size:usize, tag:usize, requires:Vec<usize>| builder.send(group[member],group[destination],size,tag,requires);
+		let receive = |builder:&mut ScheduleBuilder, member:usize, source:usize, tag:usize, requires:Vec<usize>| builder.receive(group[member],group[source],tag,requires);
+		//The part of `m` given by the fraction `numerator/denominator`, rounded up and at least a phit.
+		let part = |numerator:usize,denominator:usize| ((m*numerator+denominator-1)/denominator).max(1);
+		//The last operation of each rank on which the next step depends.
+		let mut current:Vec<Vec<usize>> = start.to_vec();
+		match *self
+		{
+			CollectiveAlgorithm::RingAllreduce =>
+			{
+				for step in 0..2*p.saturating_sub(1)
+				{
+					for rank in 0..p
+					{
+						send(builder,rank,(rank+1)%p,part(1,p),tag_base+step,current[rank].clone());
+						current[rank] = vec![ receive(builder,rank,(rank+p-1)%p,tag_base+step,start[rank].clone()) ];
+					}
+				}
+			},
+			CollectiveAlgorithm::RecursiveDoublingAllreduce | CollectiveAlgorithm::RabenseifnerAllreduce =>
+			{
+				//The ranks beyond the greatest power of two first give their data to a partner and finally receive the result from it.
+				let power:usize = if p==0 { 0 } else { 1<<(usize::BITS-1-p.leading_zeros()) };
+				let logarithm = power.trailing_zeros() as usize;
+				for rank in power..p
+				{
+					current[rank] = vec![ send(builder,rank,rank-power,m,tag_base,start[rank].clone()) ];
+					current[rank-power] = vec![ receive(builder,rank-power,rank,tag_base,start[rank-power].clone()) ];
+				}
+				//The distance to the partner and the size of each step.
+				let steps:Vec<(usize,usize)> = if let CollectiveAlgorithm::RecursiveDoublingAllreduce = *self
+				{
+					(0..logarithm).map(|k|(1<<k,m)).collect()
+				}
+				else
+				{
+					let halving = (0..logarithm).map(|k|(power>>(k+1),part(1,2<<k)));
+					let doubling = (0..logarithm).map(|k|(1<<k,part(1<<k,power)));
+					halving.chain(doubling).collect()
+				};
+				for (step,&(distance,size)) in steps.iter().enumerate()
+				{
+					for rank in 0..power
+					{
+						let partner = rank^distance;
+						send(builder,rank,partner,size,tag_base+1+step,current[rank].clone());
+						//The data of the next steps combines the received with that of all the previous steps.
+						let received = receive(builder,rank,partner,tag_base+1+step,start[rank].clone());
+						current[rank].push(received);
+					}
+				}
+				let tag = tag_base+1+steps.len();
+				for rank in power..p
+				{
+					send(builder,rank-power,rank,m,tag,current[rank-power].clone());
+					receive(builder,rank,rank-power,tag,current[rank].clone());
+				}
+			},
+			CollectiveAlgorithm::LinearShiftAllToAll =>
+			{
+				for shift in 1..p
+				{
+					for rank in 0..p
+					{
+						send(builder,rank,(rank+shift)%p,m,tag_base+shift,start[rank].clone());
+						receive(builder,rank,(rank+p-shift)%p,tag_base+shift,start[rank].clone());
+					}
+				}
+			},
+			CollectiveAlgorithm::PairwiseAllToAll =>
+			{
+				for step in 1..p
+				{
+					for rank in 0..p
+					{
+						//With a power of two the partners are paired; otherwise each rank sends ahead and receives from behind.
+						let (destination,source) = if p.is_power_of_two() { (rank^step,rank^step) } else { ((rank+step)%p,(rank+p-step)%p) };
+						send(builder,rank,destination,m,tag_base+step,current[rank].clone());
+						current[rank] = vec![ receive(builder,rank,source,tag_base+step,start[rank].clone()) ];
+					}
+				}
+			},
+			CollectiveAlgorithm::BruckAllToAll =>
+			{
+				let mut step=0;
+				while (1<<step)<p
+				{
+					let distance = 1<<step;
+					let blocks = (0..p).filter(|i|i&distance!=0).count();
+					for rank in 0..p
+					{
+						send(builder,rank,(rank+distance)%p,m*blocks,tag_base+step,current[rank].clone());
+						//The blocks forwarded in later steps may have been received in any previous step.
+						let received = receive(builder,rank,(rank+p-distance)%p,tag_base+step,start[rank].clone());
+						current[rank].push(received);
+					}
+					step+=1;
+				}
+			},
+			CollectiveAlgorithm::BinomialBroadcast{root} =>
+			{
+				for rank in 0..p
+				{
+					let relative = (rank+p-root)%p;
+					//Receive from the parent, which is the rank without the lowest bit.
+					let mut mask=1;
+					while mask<p
+					{
+						if relative&mask!=0
+						{
+							current[rank] = vec![ receive(builder,rank,(relative-mask+root)%p,tag_base,start[rank].clone()) ];
+							break;
+						}
+						mask<<=1;
+					}
+					//Send to the children, the farthest first.
+					mask>>=1;
+					while mask>0
+					{
+						if relative+mask<p
+						{
+							send(builder,rank,(relative+mask+root)%p,m,tag_base,current[rank].clone());
+						}
+						mask>>=1;
+					}
+				}
+			},
+			CollectiveAlgorithm::PipelinedChainBroadcast{root,segments} =>
+			{
+				let size = part(1,segments);
+				for rank in 0..p
+				{
+					let relative = (rank+p-root)%p;
+					for segment in 0..segments
+					{
+						let requires = if relative>0
+						{
+							vec![ receive(builder,rank,(rank+p-1)%p,tag_base+segment,start[rank].clone()) ]
+						}
+						else
+						{
+							start[rank].clone()
+						};
+						if relative+1<p
+						{
+							send(builder,rank,(rank+1)%p,size,tag_base+segment,requires);
+						}
+					}
+				}
+			},
+			CollectiveAlgorithm::DisseminationBarrier =>
+			{
+				let mut round=0;
+				while (1<<round)<p
+				{
+					let distance = 1<<round;
+					for rank in 0..p
+					{
+						send(builder,rank,(rank+distance)%p,m,tag_base+round,current[rank].clone());
+						//A round only tells of the arrivals known in all the previous rounds.
+						let received = receive(builder,rank,(rank+p-distance)%p,tag_base+round,start[rank].clone());
+						current[rank].push(received);
+					}
+					round+=1;
+				}
+			},
+		}
+		group.iter().enumerate().map(|(member,&rank)|(first_operation[member]..builder.schedules[rank].len()).collect()).collect()
+	}
+	///The algorithm with the given name for the `collective`, which is one of `Allreduce`, `AllToAll`, `Broadcast`, and `Barrier`. Without name, the default algorithm of the collective.
+	pub fn from_name(collective:&str, name:Option<&str>, root:usize, segments:usize) -> CollectiveAlgorithm
+	{
+		match (collective,name)
+		{
+			("Allreduce",Some("Ring")) | ("Allreduce",None) => CollectiveAlgorithm::RingAllreduce,
+			("Allreduce",Some("RecursiveDoubling")) => CollectiveAlgorithm::RecursiveDoublingAllreduce,
+			("Allreduce",Some("Rabenseifner")) => CollectiveAlgorithm::RabenseifnerAllreduce,
+			("AllToAll",Some("LinearShift")) | ("AllToAll",None) => CollectiveAlgorithm::LinearShiftAllToAll,
+			("AllToAll",Some("PairwiseExchange")) => CollectiveAlgorithm::PairwiseAllToAll,
+			("AllToAll",Some("Bruck")) => CollectiveAlgorithm::BruckAllToAll,
+			("Broadcast",Some("BinomialTree")) | ("Broadcast",None) => CollectiveAlgorithm::BinomialBroadcast{root},
+			("Broadcast",Some("PipelinedChain")) =>
+			{
+				if segments==0
+				{
+					panic!("The PipelinedChain broadcast requires at least one segment");
+				}
+				CollectiveAlgorithm::PipelinedChainBroadcast{root,segments}
+			},
+			("Barrier",Some("Dissemination")) | ("Barrier",None) => CollectiveAlgorithm::DisseminationBarrier,
+			(collective,Some(name)) => panic!("Unknown algorithm {} for {}",name,collective),
+			(collective,None) => panic!("Unknown collective {}",collective),
+		}
+	}
+	///An upper bound of the number of tags used by a run of the collective.
+	pub fn tags(&self, ranks:usize) -> usize
+	{
+		match *self
+		{
+			CollectiveAlgorithm::PipelinedChainBroadcast{segments,..} => segments,
+			_ => 2*ranks+2,
+		}
+	}
+}
+
+///Repeats a collective operation among `ranks` ranks mapped into the servers, following the data dependencies of its algorithm.
+///Each rank starts a repetition when it has completed its part in the previous one.
+#[derive(Debug)]
+pub struct Collective
+{
+	///The schedule executing the repetitions.
+	goal: Goal,
+	///`ends[repetition][rank]` is the operation that completes the repetition in that rank.
+	ends: Vec<Vec<usize>>,
+}
+
+impl Quantifiable for Collective
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<Collective>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Traffic for Collective
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		self.goal.generate_message(origin,cycle,topology,rng)
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		self.goal.probability_per_cycle(server)
+	}
+	fn try_consume(&mut self, server:usize, message: Rc<Message>, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.try_consume(server,message,cycle,topology,rng)
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.goal.is_finished()
+	}
+	fn should_generate(&self, server:usize, cycle:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.should_generate(server,cycle,rng)
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		self.goal.server_state(server,cycle)
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let (content,_completions) = self.goal.phase_statistics(cycle,"repetition",&self.ends);
+		Some(ConfigurationValue::Object(String::from("CollectiveStatistics"),content))
+	}
+}
+
+impl Collective
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Collective
+	{
+		let mut algorithm_name=None;
+		let mut ranks=None;
+		let mut message_size=None;
+		let mut mapping=None;
+		let mut repetitions=1;
+		let mut root=0;
+		let mut segments=None;
+		let cv_name;
+		if let &ConfigurationValue::Object(ref name, ref cv_pairs)=arg.cv
+		{
+			cv_name=name.clone();
+			for &(ref name,ref value) in cv_pairs
+			{
+				match name.as_ref()
+				{
+					"algorithm" => match value
+					{
+						&ConfigurationValue::Object(ref name,_) => algorithm_name=Some(name.clone()),
+						_ => panic!("bad value for algorithm"),
+					},
+					"ranks" => match value
+					{
+						&ConfigurationValue::Number(f) => ranks=Some(f as usize),
+						_ => panic!("bad value for ranks"),
+					},
+					"message_size" => match value
+					{
+						&ConfigurationValue::Number(f) => message_size=Some(f as usize),
+						_ => panic!("bad value for message_size"),
+					},
+					"mapping" => mapping=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+					"repetitions" => match value
+					{
+						&ConfigurationValue::Number(f) => repetitions=f as usize,
+						_ => panic!("bad value for repetitions"),
+					},
+					"root" => match value
+					{
+						&ConfigurationValue::Number(f) => root=f as usize,
+						_ => panic!("bad value for root"),
+					},
+					"segments" => match value
+					{
+						&ConfigurationValue::Number(f) => segments=Some(f as usize),
+						_ => panic!("bad value for segments"),
+					},
+					"legend_name" => (),
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+		}
+		else
+		{
+			panic!("Trying to create a Collective from a non-Object");
+		}
+		let ranks=ranks.unwrap_or_else(||arg.topology.num_servers());
+		let message_size = match cv_name.as_ref()
+		{
+			"Barrier" => message_size.unwrap_or(1),
+			_ => message_size.expect("There were no message_size"),
+		};
+		let algorithm = CollectiveAlgorithm::from_name(&cv_name,algorithm_name.as_deref(),root,segments.unwrap_or(ranks));
+		if root>=ranks
+		{
+			panic!("The root {} is not among the {} ranks",root,ranks);
+		}
+		let mut builder = ScheduleBuilder::new(ranks);
+		let mut start:Vec<Vec<usize>> = vec![vec![];ranks];
+		let mut ends = Vec::with_capacity(repetitions);
+		let group:Vec<usize> = (0..ranks).collect();
+		for repetition in 0..repetitions
+		{
+			let operations = algorithm.add_to_schedule(&mut builder,&group,message_size,&start,repetition*algorithm.tags(ranks));
+			//Mark the end of the repetition in each rank with an empty computation.
+			let repetition_ends:Vec<usize> = operations.into_iter().enumerate().map(|(rank,mut requires)|{
+				requires.extend(start[rank].iter());
+				builder.compute(rank,0,requires)
+			}).collect();
+			start = repetition_ends.iter().map(|&end|vec![end]).collect();
+			ends.push(repetition_ends);
+		}
+		Collective{
+			goal: Goal::from_schedules(builder.schedules,mapping,&arg),
+			ends,
+		}
+	}
+}
+
+///Models the iterations of training a deep neural network with data, pipeline, and tensor parallelism.
+///The rank of the tensor-parallel index `t` in the pipeline stage `s` of the data-parallel replica `d` is `(d*pipeline_stages+s)*tensor_parallel+t`.
+///Each replica executes the forward passes of all its micro-batches through the stages and then their backward passes, as in GPipe. The backward pass of the last micro-batch is divided by the gradient buckets, and the allreduce of each bucket among the replicas starts when its part of the computation is completed, overlapping with the rest.
+#[derive(Debug)]
+pub struct DeepLearningTraining
+{
+	///The schedule executing the iterations.
+	goal: Goal,
+	///`iteration_ends[iteration][rank]` is the operation that completes the iteration in that rank.
+	iteration_ends: Vec<Vec<usize>>,
+	///`computations[iteration][rank]` are the computations of the rank in the iteration.
+	computations: Vec<Vec<Vec<usize>>>,
+	///`gradient_reductions[iteration][rank]` are the operations of each gradient allreduce of the rank in the iteration.
+	gradient_reductions: Vec<Vec<Vec<Vec<usize>>>>,
+}
+
+impl Quantifiable for DeepLearningTraining
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<DeepLearningTraining>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+///Merges a list of intervals `[begin,end)` into disjoint sorted intervals.
+fn union_of_intervals(mut intervals:Vec<(usize,usize)>) -> Vec<(usize,usize)>
+{
+	intervals.sort();
+	let mut union:Vec<(usize,usize)> = vec![];
+	for (begin,end) in intervals
+	{
+		match union.last_mut()
+		{
+			Some(last) if begin<=last.1 => last.1=last.1.max(end),
+			_ => union.push((begin,end)),
+		}
+	}
+	union
+}
+
+///The length of the intersection of two lists of disjoint sorted intervals.
+fn intersection_length(a:&[(usize,usize)], b:&[(usize,usize)]) -> usize
+{
+	let mut length=0;
+	let (mut i,mut j)=(0,0);
+	while i<a.len() && j<b.len()
+	{
+		let begin=a[i].0.max(b[j].0);
+		let end=a[i].1.min(b[j].1);
+		if begin<end
+		{
+			length+=end-begin;
+		}
+		if a[i].1<b[j].1 { i+=1 } else { j+=1 }
+	}
+	length
+}
+
+impl Traffic for DeepLearningTraining
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		self.goal.generate_message(origin,cycle,topology,rng)
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		self.goal.probability_per_cycle(server)
+	}
+	fn try_consume(&mut self, server:usize, message: Rc<Message>, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.try_consume(server,message,cycle,topology,rng)
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.goal.is_finished()
+	}
+	fn should_generate(&self, server:usize, cycle:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.should_generate(server,cycle,rng)
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		self.goal.server_state(server,cycle)
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let (mut content,completions) = self.goal.phase_statistics(cycle,"iteration",&self.iteration_ends);
+		let execution = self.goal.execution.borrow();
+		//Time of the gradient allreduces of each rank during which the rank was computing, in the completed iterations.
+		let mut communication_time=0;
+		let mut overlapped_time=0;
+		for iteration in 0..completions.len()
+		{
+			for (rank,rank_execution) in execution.ranks.iter().enumerate()
+			{
+				let interval = |operations:&[usize]| -> Option<(usize,usize)> {
+					let begin = operations.iter().map(|&op|rank_execution.start_cycles[op].unwrap()).min()?;
+					let end = operations.iter().map(|&op|rank_execution.completion_cycles[op].unwrap()).max()?;
+					Some((begin,end))
+				};
+				let computing = union_of_intervals(self.computations[iteration][rank].iter().filter_map(|&op|interval(&[op])).collect());
+				let communicating = union_of_intervals(self.gradient_reductions[iteration][rank].iter().filter_map(|ops|interval(ops)).collect());
+				communication_time += communicating.iter().map(|(begin,end)|end-begin).sum::<usize>();
+				overlapped_time += intersection_length(&computing,&communicating);
+			}
+		}
+		let rank_iterations = (completions.len()*execution.ranks.len()).max(1);
+		content.push((String::from("communication_overlap"),if communication_time==0 { ConfigurationValue::None } else { ConfigurationValue::Number(overlapped_time as f64/communication_time as f64) }));
+		content.push((String::from("exposed_communication_time"),ConfigurationValue::Number((communication_time-overlapped_time) as f64/rank_iterations as f64)));
+		Some(ConfigurationValue::Object(String::from("TrainingStatistics"),content))
+	}
+}
+
+impl DeepLearningTraining
+{
+	pub fn new(arg:TrafficBuilderArgument) -> DeepLearningTraining
+	{
+		let mut data_parallel=None;
+		let mut pipeline_stages=1;
+		let mut tensor_parallel=1;
+		let mut iterations=1;
+		let mut micro_batches=1;
+		let mut forward_cycles=None;
+		let mut backward_cycles=None;
+		let mut optimizer_cycles=0;
+		let mut activation_size=0;
+		let mut gradient_buckets=None;
+		let mut tensor_parallel_size=0;
+		let mut allreduce=None;
+		let mut all_to_all=None;
+		let mut mapping=None;
+		let number = |value:&ConfigurationValue,name:&str| match value
+		{
+			&ConfigurationValue::Number(f) => f as usize,
+			_ => panic!("bad value for {}",name),
+		};
+		match_object_panic!(arg.cv,"DeepLearningTraining",value,
+			"data_parallel" => data_parallel=Some(number(value,"data_parallel")),
+			"pipeline_stages" => pipeline_stages=number(value,"pipeline_stages"),
+			"tensor_parallel" => tensor_parallel=number(value,"tensor_parallel"),
+			"iterations" => iterations=number(value,"iterations"),
+			"micro_batches" => micro_batches=number(value,"micro_batches"),
+			"forward_cycles" => forward_cycles=Some(number(value,"forward_cycles")),
+			"backward_cycles" => backward_cycles=Some(number(value,"backward_cycles")),
+			"optimizer_cycles" => optimizer_cycles=number(value,"optimizer_cycles"),
+			"activation_size" => activation_size=number(value,"activation_size"),
+			"gradient_buckets" => match value
+			{
+				&ConfigurationValue::Array(ref a) => gradient_buckets=Some(a.iter().map(|v|number(v,"gradient_buckets")).collect::<Vec<usize>>()),
+				_ => panic!("bad value for gradient_buckets"),
+			},
+			"tensor_parallel_size" => tensor_parallel_size=number(value,"tensor_parallel_size"),
+			"allreduce" => match value
+			{
+				&ConfigurationValue::Object(ref name,_) => allreduce=Some(name.clone()),
+				_ => panic!("bad value for allreduce"),
+			},
+			"all_to_all" => match value
+			{
+				&ConfigurationValue::Object(ref name,_) => all_to_all=Some(name.clone()),
+				_ => panic!("bad value for all_to_all"),
+			},
+			"mapping" => mapping=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+		);
+		let data_parallel=data_parallel.expect("There were no data_parallel");
+		let forward_cycles=forward_cycles.expect("There were no forward_cycles");
+		let backward_cycles=backward_cycles.expect("There were no backward_cycles");
+		let gradient_buckets=gradient_buckets.expect("There were no gradient_buckets");
+		let allreduce=CollectiveAlgorithm::from_name("Allreduce",allreduce.as_deref(),0,0);
+		let all_to_all=CollectiveAlgorithm::from_name("AllToAll",all_to_all.as_deref(),0,0);
+		let (d_size,s_size,t_size) = (data_parallel,pipeline_stages,tensor_parallel);
+		let ranks = d_size*s_size*t_size;
+		let rank_of = |d:usize,s:usize,t:usize| (d*s_size+s)*t_size+t;
+		let mut builder = ScheduleBuilder::new(ranks);
+		let mut next_tag = 0;
+		let mut start:Vec<Vec<usize>> = vec![vec![];ranks];
+		let mut iteration_ends = Vec::with_capacity(iterations);
+		let mut all_computations = Vec::with_capacity(iterations);
+		let mut all_reductions = Vec::with_capacity(iterations);
+		for _iteration in 0..iterations
+		{
+			let first_operation:Vec<usize> = builder.schedules.iter().map(|s|s.len()).collect();
+			let mut computations:Vec<Vec<usize>> = vec![vec![];ranks];
+			let mut reductions:Vec<Vec<Vec<usize>>> = vec![vec![];ranks];
+			//The operations that the next computation of each rank requires.
+			let mut previous:Vec<Vec<usize>> = start.clone();
+			//The receptions of activations or gradients from a neighbour stage, required by the next computation.
+			let mut incoming:Vec<Vec<usize>> = vec![vec![];ranks];
+			for backward in [false,true]
+			{
+				for micro_batch in 0..micro_batches
+				{
+					let stages:Vec<usize> = if backward { (0..s_size).rev().collect() } else { (0..s_size).collect() };
+					for &s in stages.iter()
+					{
+						//The gradients are reduced while computing the backward pass of the last micro-batch.
+						let buckets:Vec<usize> = if backward && micro_batch+1==micro_batches { gradient_buckets.clone() } else { vec![] };
+						let parts = buckets.len().max(1);
+						let cycles = if backward { backward_cycles } else { forward_cycles };
+						for part in 0..parts
+						{
+							let part_cycles = cycles*(part+1)/parts - cycles*part/parts;
+							for d in 0..d_size
+							{
+								for t in 0..t_size
+								{
+									let rank = rank_of(d,s,t);
+									let mut requires = std::mem::take(&mut previous[rank]);
+									requires.append(&mut incoming[rank]);
+									let operation = builder.compute(rank,part_cycles,requires);
+									computations[rank].push(operation);
+									previous[rank] = vec![operation];
+								}
+							}
+							if part<buckets.len() && d_size>1
+							{
+								for t in 0..t_size
+								{
+									let group:Vec<usize> = (0..d_size).map(|d|rank_of(d,s,t)).collect();
+									let group_start:Vec<Vec<usize>> = group.iter().map(|&rank|previous[rank].clone()).collect();
+									let operations = allreduce.add_to_schedule(&mut builder,&group,buckets[part],&group_start,next_tag);
+									next_tag += allreduce.tags(d_size);
+									for (member,&rank) in group.iter().enumerate()
+									{
+										reductions[rank].push(operations[member].clone());
+									}
+								}
+							}
+						}
+						//The tensor-parallel ranks exchange their partial results before continuing.
+						if t_size>1 && tensor_parallel_size>0
+						{
+							for d in 0..d_size
+							{
+								let group:Vec<usize> = (0..t_size).map(|t|rank_of(d,s,t)).collect();
+								let group_start:Vec<Vec<usize>> = group.iter().map(|&rank|previous[rank].clone()).collect();
+								let operations = all_to_all.add_to_schedule(&mut builder,&group,tensor_parallel_size,&group_start,next_tag);
+								next_tag += all_to_all.tags(t_size);
+								for (member,&rank) in group.iter().enumerate()
+								{
+									previous[rank].extend(operations[member].iter());
+								}
+							}
+						}
+						//Send the activations to the next stage, or the gradients to the previous one.
+						let neighbour = if backward { s.checked_sub(1) } else { Some(s+1).filter(|&n|n<s_size) };
+						if let Some(n) = neighbour
+						{
+							for d in 0..d_size
+							{
+								for t in 0..t_size
+								{
+									let (rank,target) = (rank_of(d,s,t),rank_of(d,n,t));
+									builder.send(rank,target,activation_size.max(1),next_tag,previous[rank].clone());
+									incoming[target].push(builder.receive(target,rank,next_tag,start[target].clone()));
+									next_tag += 1;
+								}
+							}
+						}
+					}
+				}
+			}
+			//The optimizer step ends the iteration when all the operations of the rank are completed.
+			let ends:Vec<usize> = (0..ranks).map(|rank|{
+				let mut requires:Vec<usize> = (first_operation[rank]..builder.schedules[rank].len()).collect();
+				requires.extend(start[rank].iter());
+				builder.compute(rank,optimizer_cycles,requires)
+			}).collect();
+			for (rank,&end) in ends.iter().enumerate()
+			{
+				computations[rank].push(end);
+			}
+			start = ends.iter().map(|&end|vec![end]).collect();
+			iteration_ends.push(ends);
+			all_computations.push(computations);
+			all_reductions.push(reductions);
+		}
+		DeepLearningTraining{
+			goal: Goal::from_schedules(builder.schedules,mapping,&arg),
+			iteration_ends,
+			computations: all_computations,
+			gradient_reductions: all_reductions,
+		}
+	}
+}
+
+///The policy to allocate the servers of the network to the jobs of a `Workload`.
+#[derive(Debug,Clone)]
+pub enum JobPlacement
+{
+	///Each job takes the next free servers in index order.
+	Contiguous,
+	///Each job takes servers at random.
+	Random,
+	///Each job takes one server from each router in turn, spreading it over the network.
+	RouterRoundRobin,
+	///The routers are divided into groups of `routers_per_group` consecutive routers, as a leaf switch or a Dragonfly group.
+	///Each job takes the servers of the group whose free servers fit it most tightly. If no group has enough free servers, it takes the emptiest groups whole until the rest fits into one.
+	TopologyAware{routers_per_group:usize},
+}
+
+impl JobPlacement
+{
+	pub fn new(cv:&ConfigurationValue) -> JobPlacement
+	{
+		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
+		{
+			let mut routers_per_group=1;
+			for &(ref name,ref value) in cv_pairs
+			{
+				match (cv_name.as_ref(),name.as_ref())
+				{
+					("TopologyAware","routers_per_group") => match value
+					{
+						&ConfigurationValue::Number(f) => routers_per_group=f as usize,
+						_ => panic!("bad value for routers_per_group"),
+					}
+					_ => panic!("Nothing to do with field {} in {}",name,cv_name),
+				}
+			}
+			match cv_name.as_ref()
+			{
+				"Contiguous" => JobPlacement::Contiguous,
+				"Random" => JobPlacement::Random,
+				"RouterRoundRobin" => JobPlacement::RouterRoundRobin,
+				"TopologyAware" =>
+				{
+					assert!(routers_per_group>0,"TopologyAware placement requires positive routers_per_group.");
+					JobPlacement::TopologyAware{routers_per_group}
+				},
+				_ => panic!("Unknown job placement {}",cv_name),
+			}
+		}
+		else
+		{
+			panic!("Trying to create a JobPlacement from a non-Object");
+		}
+	}
+	///Returns the list of servers of each job, in the order of its ranks.
+	pub fn place(&self, job_sizes:&[usize], topology:&dyn Topology, rng:&RefCell<StdRng>) -> Vec<Vec<usize>>
+	{
+		let servers = topology.num_servers();
+		let total:usize = job_sizes.iter().sum();
+		if total>servers
+		{
+			panic!("The jobs require {} servers but there are only {}.",total,servers);
+		}
+		let router_of = |server:usize| match topology.server_neighbour(server).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("The server {} is not attached to a router.",server),
+		};
+		let order:Vec<usize> = match self
+		{
+			JobPlacement::Contiguous => (0..servers).collect(),
+			JobPlacement::Random =>
+			{
+				let mut order:Vec<usize> = (0..servers).collect();
+				order.shuffle(rng.borrow_mut().deref_mut());
+				order
+			},
+			JobPlacement::RouterRoundRobin =>
+			{
+				let mut router_servers = vec![vec![];topology.num_routers()];
+				for server in 0..servers
+				{
+					router_servers[router_of(server)].push(server);
+				}
+				let most = router_servers.iter().map(|list|list.len()).max().unwrap_or(0);
+				(0..most).flat_map(|turn|router_servers.iter().filter_map(move |list|list.get(turn).copied())).collect()
+			},
+			&JobPlacement::TopologyAware{routers_per_group} =>
+			{
+				let num_groups = (topology.num_routers()+routers_per_group-1)/routers_per_group;
+				//The free servers of each group, in reverse order to pop them in index order.
+				let mut free = vec![vec![];num_groups];
+				for server in (0..servers).rev()
+				{
+					free[router_of(server)/routers_per_group].push(server);
+				}
+				return job_sizes.iter().map(|&size|{
+					let mut job = Vec::with_capacity(size);
+					while job.len()<size
+					{
+						let need = size-job.len();
+						let best_fit = (0..num_groups).filter(|&group|free[group].len()>=need).min_by_key(|&group|free[group].len());
+						let group = match best_fit
+						{
+							Some(group) => group,
+							//Take the group with most free servers, the first of them in case of tie.
+							None => (0..num_groups).max_by_key(|&group|(free[group].len(),num_groups-group)).expect("there are no groups"),
+						};
+						let taken = need.min(free[group].len());
+						for _ in 0..taken
+						{
+							job.push(free[group].pop().unwrap());
+						}
+					}
+					job
+				}).collect();
+			},
+		};
+		let mut next = 0;
+		job_sizes.iter().map(|&size|{
+			let job = order[next..next+size].to_vec();
+			next+=size;
+			job
+		}).collect()
+	}
+}
+
+///Get the field `name` of a `ConfigurationValue::Object`, if present.
+fn object_field<'a>(cv:&'a ConfigurationValue, name:&str) -> Option<&'a (String,ConfigurationValue)>
+{
+	match cv
+	{
+		&ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(key,_)|key==name),
+		_ => None,
+	}
+}
+
+///The measurements of a job of a `Workload` since the last reset of statistics.
+#[derive(Debug,Clone,Default,Quantifiable)]
+struct JobMeasurement
+{
+	generated_messages: usize,
+	generated_phits: usize,
+	consumed_messages: usize,
+	consumed_phits: usize,
+	total_message_delay: usize,
+}
+
+///Several jobs sharing the network, each with its own traffic over its own servers.
+///The ranks of each job are mapped to servers by a `JobPlacement`. Servers not allocated to any job do not generate traffic.
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Workload
+{
+	///The traffic of each job, over its ranks.
+	jobs: Vec<Box<dyn Traffic>>,
+	///The servers of each job, indexed by rank.
+	job_servers: Vec<Vec<usize>>,
+	///For each server the job to which it is allocated and its rank in it.
+	server_ranks: Vec<Option<(usize,usize)>>,
+	///The generated messages, with their job and the message of the job traffic.
+	generated_messages: BTreeMap<*const Message,(usize,Rc<Message>)>,
+	///The measurements of each job.
+	job_measurements: Vec<JobMeasurement>,
+	///The first cycle of the measurements.
+	begin_cycle: usize,
+}
+
+impl Traffic for Workload
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		let (job,rank) = match self.server_ranks[origin]
+		{
+			Some(x) => x,
+			None => return Err(TrafficError::OriginOutsideTraffic),
+		};
+		let inner_message=self.jobs[job].generate_message(rank,cycle,topology,rng)?;
+		let destination = *self.job_servers[job].get(inner_message.destination).unwrap_or_else(||panic!("The job {} has sent a message to rank {}, but it only has {} servers.",job,inner_message.destination,self.job_servers[job].len()));
+		let outer_message=Rc::new(Message{
+			origin,
+			destination,
+			size:inner_message.size,
+			creation_cycle: cycle,
+		});
+		let measurement = &mut self.job_measurements[job];
+		measurement.generated_messages+=1;
+		measurement.generated_phits+=outer_message.size;
+		self.generated_messages.insert(outer_message.as_ref() as *const Message,(job,inner_message));
+		Ok(outer_message)
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		match self.server_ranks[server]
+		{
+			Some((job,rank)) => self.jobs[job].probability_per_cycle(rank),
+			None => 0.0,
+		}
+	}
+	fn should_generate(&self, server:usize, cycle:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		match self.server_ranks[server]
+		{
+			Some((job,rank)) => self.jobs[job].should_generate(rank,cycle,rng),
+			None => false,
+		}
+	}
+	fn try_consume(&mut self, server:usize, message: Rc<Message>, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> bool
+	{
+		let message_ptr=message.as_ref() as *const Message;
+		let (job,inner_message)=match self.generated_messages.remove(&message_ptr)
+		{
+			None => return false,
+			Some(x) => x,
+		};
+		let rank = match self.server_ranks[server]
+		{
+			Some((server_job,rank)) if server_job==job => rank,
+			_ => panic!("The server {} consumed a message of job {} to which it does not belong.",server,job),
+		};
+		let measurement = &mut self.job_measurements[job];
+		measurement.consumed_messages+=1;
+		measurement.consumed_phits+=message.size;
+		measurement.total_message_delay+=cycle-message.creation_cycle;
+		if !self.jobs[job].try_consume(rank,inner_message,cycle,topology,rng)
+		{
+			panic!("Workload traffic consumed a message but its job did not.");
+		}
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.jobs.iter().all(|job|job.is_finished())
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		match self.server_ranks[server]
+		{
+			Some((job,rank)) => self.jobs[job].server_state(rank,cycle),
+			None => ServerTrafficState::Finished,
+		}
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let cycles = (cycle-self.begin_cycle) as f64;
+		let jobs_content = self.jobs.iter().zip(self.job_servers.iter()).zip(self.job_measurements.iter()).map(|((traffic,servers),measurement)|{
+			let num_servers = servers.len() as f64;
+			let mut job_content = vec![
+				(String::from("servers"),ConfigurationValue::Number(num_servers)),
+				(String::from("generated_messages"),ConfigurationValue::Number(measurement.generated_messages as f64)),
+				(String::from("consumed_messages"),ConfigurationValue::Number(measurement.consumed_messages as f64)),
+				(String::from("injected_load"),ConfigurationValue::Number(measurement.generated_phits as f64/cycles/num_servers)),
+				(String::from("accepted_load"),ConfigurationValue::Number(measurement.consumed_phits as f64/cycles/num_servers)),
+				(String::from("average_message_delay"),if measurement.consumed_messages==0 { ConfigurationValue::None } else { ConfigurationValue::Number(measurement.total_message_delay as f64/measurement.consumed_messages as f64) }),
+			];
+			if let Some(content) = traffic.statistics(cycle)
+			{
+				job_content.push( (String::from("traffic_statistics"),content) );
+			}
+			ConfigurationValue::Object(String::from("JobStatistics"),job_content)
+		}).collect();
+		let content = vec![
+			(String::from("jobs"),ConfigurationValue::Array(jobs_content)),
+		];
+		Some(ConfigurationValue::Object(String::from("WorkloadStatistics"),content))
+	}
+	fn reset_statistics(&mut self, next_cycle:usize)
+	{
+		self.begin_cycle = next_cycle;
+		for measurement in self.job_measurements.iter_mut()
+		{
+			*measurement = Default::default();
+		}
+		for job in self.jobs.iter_mut()
+		{
+			job.reset_statistics(next_cycle);
+		}
+	}
+}
+
+impl Workload
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Workload
+	{
+		let mut jobs=None;
+		let mut placement=JobPlacement::Contiguous;
+		match_object_panic!(arg.cv,"Workload",value,
+			"jobs" => match value
+			{
+				&ConfigurationValue::Array(ref a) => jobs=Some(a.iter().map(|job_cv|{
+					let mut traffic=None;
+					let mut servers=None;
+					match_object_panic!(job_cv,"Job",job_value,
+						"traffic" => traffic=Some(new_traffic(TrafficBuilderArgument{cv:job_value,..arg})),
+						"servers" => match job_value
+						{
+							&ConfigurationValue::Number(f) => servers=Some(f as usize),
+							_ => panic!("bad value for servers"),
+						},
+					);
+					let servers=servers.expect("There were no servers");
+					//The inner traffic is built over its ranks, so any size it declares must be the one of the job.
+					if let Some((_,traffic_cv)) = object_field(job_cv,"traffic")
+					{
+						for size_name in ["servers","ranks"]
+						{
+							if let Some((_,ConfigurationValue::Number(traffic_size))) = object_field(traffic_cv,size_name)
+							{
+								if *traffic_size as usize != servers
+								{
+									panic!("The traffic of a job declares {} {} but the job has {} servers.",traffic_size,size_name,servers);
+								}
+							}
+						}
+					}
+					(traffic.expect("There were no traffic"),servers)
+				}).collect::<Vec<_>>()),
+				_ => panic!("bad value for jobs"),
+			},
+			"placement" => placement=JobPlacement::new(value),
+		);
+		let (jobs,job_sizes):(Vec<Box<dyn Traffic>>,Vec<usize>) = jobs.expect("There were no jobs").into_iter().unzip();
+		let job_servers = placement.place(&job_sizes,arg.topology.as_ref(),arg.rng);
+		let mut server_ranks = vec![None;arg.topology.num_servers()];
+		for (job,servers) in job_servers.iter().enumerate()
+		{
+			for (rank,&server) in servers.iter().enumerate()
+			{
+				server_ranks[server] = Some((job,rank));
+			}
+		}
+		let job_measurements = vec![Default::default();jobs.len()];
+		Workload{
+			jobs,
+			job_servers,
+			server_ranks,
+			generated_messages: BTreeMap::new(),
+			job_measurements,
+			begin_cycle: 0,
+		}
+	}
+}
+
+///Iterations of a halo exchange among the processes of a logical grid, placed into the servers as in the `Stencil` pattern.
+///In each iteration each process computes and then sends a message to each neighbour of its stencil, completing the iteration when it has also received the messages of all its neighbours.
+#[derive(Debug)]
+pub struct HaloExchange
+{
+	///The schedule executing the iterations.
+	goal: Goal,
+	///`iteration_ends[iteration][process]` is the operation that completes the iteration in that process.
+	iteration_ends: Vec<Vec<usize>>,
+}
+
+impl Quantifiable for HaloExchange
+{
+	fn total_memory(&self) -> usize
+	{
+		return size_of::<HaloExchange>();
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Traffic for HaloExchange
+{
+	fn generate_message(&mut self, origin:usize, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> Result<Rc<Message>,TrafficError>
+	{
+		self.goal.generate_message(origin,cycle,topology,rng)
+	}
+	fn probability_per_cycle(&self, server:usize) -> f32
+	{
+		self.goal.probability_per_cycle(server)
+	}
+	fn try_consume(&mut self, server:usize, message: Rc<Message>, cycle:usize, topology:&Box<dyn Topology>, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.try_consume(server,message,cycle,topology,rng)
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.goal.is_finished()
+	}
+	fn should_generate(&self, server:usize, cycle:usize, rng: &RefCell<StdRng>) -> bool
+	{
+		self.goal.should_generate(server,cycle,rng)
+	}
+	fn server_state(&self, server:usize, cycle:usize) -> ServerTrafficState
+	{
+		self.goal.server_state(server,cycle)
+	}
+	fn statistics(&self, cycle:usize) -> Option<ConfigurationValue>
+	{
+		let (content,_completions) = self.goal.phase_statistics(cycle,"iteration",&self.iteration_ends);
+		Some(ConfigurationValue::Object(String::from("HaloExchangeStatistics"),content))
+	}
+}
+
+impl HaloExchange
+{
+	pub fn new(arg:TrafficBuilderArgument) -> HaloExchange
+	{
+		let mut sides=None;
+		let mut points=None;
+		let mut periodic=true;
+		let mut mapping=GridMapping::Linear;
+		let mut message_size=None;
+		let mut iterations=1;
+		let mut compute_cycles=0;
+		match_object_panic!(arg.cv,"HaloExchange",value,
+			"sides" => match value
+			{
+				&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
+					&ConfigurationValue::Number(f) => f as usize,
+					_ => panic!("bad value in sides"),
+				}).collect()),
+				_ => panic!("bad value for sides"),
+			},
+			"points" => match value
+			{
+				&ConfigurationValue::Number(f) => points=Some(f as usize),
+				_ => panic!("bad value for points"),
+			},
+			"periodic" => match value
+			{
+				&ConfigurationValue::True => periodic=true,
+				&ConfigurationValue::False => periodic=false,
+				_ => panic!("bad value for periodic"),
+			},
+			"mapping" => mapping=GridMapping::new(value),
+			"message_size" => match value
+			{
+				&ConfigurationValue::Number(f) => message_size=Some(f as usize),
+				_ => panic!("bad value for message_size"),
+			},
+			"iterations" => match value
+			{
+				&ConfigurationValue::Number(f) => iterations=f as usize,
+				_ => panic!("bad value for iterations"),
+			},
+			"compute_cycles" => match value
+			{
+				&ConfigurationValue::Number(f) => compute_cycles=f as usize,
+				_ => panic!("bad value for compute_cycles"),
+			},
+		);
+		let sides:Vec<usize>=sides.expect("There were no sides");
+		let points=points.unwrap_or(2*sides.len()+1);
+		let message_size=message_size.expect("There were no message_size");
+		let servers=arg.topology.num_servers();
+		let grid = StencilGrid::new(&sides,points,periodic,&mapping,servers);
+		let processes = grid.processes();
+		let neighbours:Vec<Vec<usize>> = (0..processes).map(|process|grid.neighbours(process)).collect();
+		let mut builder = ScheduleBuilder::new(processes);
+		let mut start:Vec<Vec<usize>> = vec![vec![];processes];
+		let mut iteration_ends = Vec::with_capacity(iterations);
+		for iteration in 0..iterations
+		{
+			let ends:Vec<usize> = (0..processes).map(|process|{
+				let computation = builder.compute(process,compute_cycles,start[process].clone());
+				let mut requires:Vec<usize> = neighbours[process].iter().map(|&neighbour|builder.send(process,neighbour,message_size,iteration,vec![computation])).collect();
+				requires.extend(neighbours[process].iter().map(|&neighbour|builder.receive(process,neighbour,iteration,start[process].clone())).collect::<Vec<usize>>());
+				requires.push(computation);
+				builder.compute(process,0,requires)
+			}).collect();
+			start = ends.iter().map(|&end|vec![end]).collect();
+			iteration_ends.push(ends);
+		}
+		let rank_to_server = (0..processes).map(|process|grid.server(process)).collect();
+		HaloExchange{
+			goal: Goal::from_schedules_on_servers(builder.schedules,rank_to_server,servers),
+			iteration_ends,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::tests::{parse_configuration,test_plugs,new_simulation,advance_until};
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+
+	///Executes the schedules, delivering the messages in order, and returns for each rank the set of ranks whose data have reached it.
+	///Panics if some operation cannot be completed.
+	fn execute_schedules(schedules:&[Vec<ScheduleOperation>]) -> Vec<BTreeSet<usize>>
+	{
+		//The ranks whose data is available at the completion of each operation.
+		let mut knowledge:Vec<Vec<Option<BTreeSet<usize>>>> = schedules.iter().map(|operations|vec![None;operations.len()]).collect();
+		let mut in_flight:BTreeMap<(usize,usize,usize),VecDeque<BTreeSet<usize>>> = BTreeMap::new();
+		let mut progress = true;
+		while progress
+		{
+			progress = false;
+			for (rank,operations) in schedules.iter().enumerate()
+			{
+				for (index,operation) in operations.iter().enumerate()
+				{
+					if knowledge[rank][index].is_some() || operation.requires.iter().chain(operation.irequires.iter()).any(|&required|knowledge[rank][required].is_none())
+					{
+						continue;
+					}
+					let mut known:BTreeSet<usize> = std::iter::once(rank).collect();
+					for &required in operation.requires.iter().chain(operation.irequires.iter())
+					{
+						known.extend(knowledge[rank][required].as_ref().unwrap().iter());
+					}
+					match operation.kind
+					{
+						ScheduleOperationKind::Send{destination,tag,..} => in_flight.entry((rank,destination,tag)).or_insert_with(VecDeque::new).push_back(known.clone()),
+						ScheduleOperationKind::Receive{source,tag} => match in_flight.get_mut(&(source,rank,tag)).and_then(|queue|queue.pop_front())
+						{
+							Some(data) => known.extend(data),
+							None => continue,
+						},
+						ScheduleOperationKind::Compute{..} => (),
+					}
+					knowledge[rank][index] = Some(known);
+					progress = true;
+				}
+			}
+		}
+		assert!(in_flight.values().all(|queue|queue.is_empty()),"there are messages never received");
+		knowledge.into_iter().enumerate().map(|(rank,operations)|{
+			operations.into_iter().fold(std::iter::once(rank).collect(),|mut all:BTreeSet<usize>,known|{
+				all.extend(known.unwrap_or_else(||panic!("an operation of rank {} is never completed",rank)));
+				all
+			})
+		}).collect()
+	}
+
+	#[test]
+	fn collective_algorithms_spread_the_data()
+	{
+		for ranks in 1..=9
+		{
+			let everyone:BTreeSet<usize> = (0..ranks).collect();
+			for &(collective,name) in [("Allreduce","Ring"),("Allreduce","RecursiveDoubling"),("Allreduce","Rabenseifner"),("AllToAll","LinearShift"),("AllToAll","PairwiseExchange"),("AllToAll","Bruck"),("Barrier","Dissemination")].iter()
+			{
+				let algorithm = CollectiveAlgorithm::from_name(collective,Some(name),0,ranks);
+				let mut builder = ScheduleBuilder::new(ranks);
+				let group:Vec<usize> = (0..ranks).collect();
+				algorithm.add_to_schedule(&mut builder,&group,16,&vec![vec![];ranks],0);
+				check_schedules(&builder.schedules,name);
+				for (rank,known) in execute_schedules(&builder.schedules).into_iter().enumerate()
+				{
+					assert_eq!(known,everyone,"rank {} of {} {} with {} ranks",rank,name,collective,ranks);
+				}
+			}
+			for root in 0..ranks
+			{
+				for algorithm in vec![CollectiveAlgorithm::from_name("Broadcast",Some("BinomialTree"),root,ranks),CollectiveAlgorithm::from_name("Broadcast",Some("PipelinedChain"),root,3)]
+				{
+					let mut builder = ScheduleBuilder::new(ranks);
+					let group:Vec<usize> = (0..ranks).collect();
+					algorithm.add_to_schedule(&mut builder,&group,16,&vec![vec![];ranks],0);
+					for (rank,known) in execute_schedules(&builder.schedules).into_iter().enumerate()
+					{
+						assert!(known.contains(&root),"rank {} does not receive from the root {} in {:?} with {} ranks",rank,root,algorithm,ranks);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn collective_repetitions_use_separate_tags()
+	{
+		for &(collective,name) in [("Allreduce","Ring"),("Allreduce","Rabenseifner"),("AllToAll","Bruck"),("Broadcast","PipelinedChain"),("Barrier","Dissemination")].iter()
+		{
+			let ranks = 6;
+			let algorithm = CollectiveAlgorithm::from_name(collective,Some(name),0,4);
+			let mut builder = ScheduleBuilder::new(ranks);
+			let group:Vec<usize> = (0..ranks).collect();
+			let tags = algorithm.tags(ranks);
+			algorithm.add_to_schedule(&mut builder,&group,16,&vec![vec![];ranks],5*tags);
+			for operations in builder.schedules.iter()
+			{
+				for operation in operations.iter()
+				{
+					match operation.kind
+					{
+						ScheduleOperationKind::Send{tag,..} | ScheduleOperationKind::Receive{tag,..} => assert!(5*tags<=tag && tag<6*tags,"{} uses the tag {} out of its range",name,tag),
+						ScheduleOperationKind::Compute{..} => (),
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn empirical_average_counts_the_rounding()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(7));
+		for &size_scale in [1.0,0.3,0.0625].iter()
+		{
+			let distribution = MessageSizeDistribution::EmpiricalCDF{points:vec![(0.0,0.2),(2.5,0.5),(40.0,0.9),(41.0,1.0)],size_scale};
+			let samples = 200000;
+			let mean = (0..samples).map(|_|distribution.sample(&rng) as f64).sum::<f64>()/samples as f64;
+			let average = distribution.average();
+			assert!((mean-average).abs()<0.01*average,"sampled mean {} and average {} differ with size_scale {}",mean,average,size_scale);
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "at least one segment")]
+	fn pipelined_chain_requires_segments()
+	{
+		CollectiveAlgorithm::from_name("Broadcast",Some("PipelinedChain"),0,0);
+	}
+
+	#[test]
+	fn goal_reads_operations_dependencies_and_comments()
+	{
+		let goal = "# time and sizes scaled by 2 and 1/8
+num_ranks 2
+rank 0 {
+	l1: send 64b to 1 tag 3
+	// a computation
+	l2: calc 100 cpu 1
+	l3: recv 64b from 1 tag 4
+
+	l3 requires l1
+	l2 irequires l1
+}
+rank 1 {
+	l1: recv 64b from 0 tag 3
+	l2: send 3b to 0 tag 4 nic 0
+	l3: send 8b to 0
+	l2 requires l1
+}
+";
+		let schedules = parse_goal(goal.as_bytes(),"test",2.0,0.125);
+		assert_eq!(schedules.len(),2);
+		let kinds:Vec<Vec<String>> = schedules.iter().map(|operations|operations.iter().map(|operation|format!("{:?}",operation.kind)).collect()).collect();
+		assert_eq!(kinds,vec![
+			vec!["Send { destination: 1, size: 8, tag: 3 }","Compute { cycles: 200 }","Receive { source: 1, tag: 4 }"],
+			//The sizes are rounded up to whole phits and the tag is 0 when not given.
+			vec!["Receive { source: 0, tag: 3 }","Send { destination: 0, size: 1, tag: 4 }","Send { destination: 0, size: 1, tag: 0 }"],
+		]);
+		let dependencies:Vec<Vec<(Vec<usize>,Vec<usize>)>> = schedules.iter().map(|operations|operations.iter().map(|operation|(operation.requires.clone(),operation.irequires.clone())).collect()).collect();
+		assert_eq!(dependencies,vec![
+			vec![(vec![],vec![]),(vec![],vec![0]),(vec![0],vec![])],
+			vec![(vec![],vec![]),(vec![0],vec![]),(vec![],vec![])],
+		]);
+	}
+
+	#[test]
+	#[should_panic(expected = "sends to rank 2, but the schedule test has 2 ranks")]
+	fn goal_rejects_unknown_ranks()
+	{
+		parse_goal("num_ranks 2\nrank 0 {\nl1: send 8b to 2\n}\nrank 1 {\n}\n".as_bytes(),"test",1.0,1.0);
+	}
+
+	#[test]
+	#[should_panic(expected = "form a cycle")]
+	fn goal_rejects_cyclic_dependencies()
+	{
+		parse_goal("num_ranks 1\nrank 0 {\nl1: calc 1\nl2: calc 1\nl3: calc 1\nl1 requires l3\nl2 requires l1\nl3 irequires l2\n}\n".as_bytes(),"test",1.0,1.0);
+	}
+
+	#[test]
+	fn arrival_processes_keep_the_mean_load()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(1));
+		let plugs = test_plugs();
+		let topology = new_topology(TopologyBuilderArgument{
+			cv: &parse_configuration("Hamming { sides: [4], servers_per_router: 4 }"),
+			plugs: &plugs,
+			rng: &rng,
+		});
+		for process in ["Bernoulli","Poisson","MarkovOnOff { mean_burst_length: 50, mean_idle_length: 150 }","ParetoOnOff { mean_burst_length: 50, mean_idle_length: 150 }"]
+		{
+			let cv = parse_configuration(&format!("HomogeneousTraffic {{ pattern: Uniform, servers: 16, load: 0.2, message_size: 4, arrival: {} }}",process));
+			let mut traffic = new_traffic(TrafficBuilderArgument{ cv:&cv, plugs:&plugs, topology:&topology, rng:&rng });
+			let field = |traffic:&Box<dyn Traffic>,name:&str| match traffic.statistics(0)
+			{
+				Some(ConfigurationValue::Object(_,content)) => content.into_iter().find(|(key,_)|key==name).expect("missing field").1,
+				statistics => panic!("{} has bad statistics {:?}",process,statistics),
+			};
+			//Nothing has been generated yet.
+			assert_eq!(field(&traffic,"offered_load"),ConfigurationValue::None);
+			assert_eq!(field(&traffic,"average_inter_arrival_time"),ConfigurationValue::None);
+			for cycle in 0..100_000
+			{
+				for server in 0..16
+				{
+					traffic.should_generate(server,cycle,&rng);
+				}
+			}
+			match field(&traffic,"offered_load")
+			{
+				ConfigurationValue::Number(load) => assert!((load-0.2).abs()<0.01,"{} offered {} instead of 0.2",process,load),
+				load => panic!("{} offered {:?}",process,load),
+			}
+			traffic.reset_statistics(100_000);
+			assert_eq!(field(&traffic,"offered_load"),ConfigurationValue::None);
+		}
+	}
+
+	#[test]
+	fn trace_loops_and_waits_for_its_records()
+	{
+		let filename = std::env::temp_dir().join(format!("caminos_trace_test_{}.trace",std::process::id()));
+		std::fs::write(&filename,"# time source destination size\n0 0 1 4\n\n5 2 3 4\n20,1,0,4\n").unwrap();
+		let plugs = test_plugs();
+		let mut simulation = new_simulation(&format!("Configuration
+		{{
+			random_seed: 1,
+			warmup: 0,
+			measured: 1000,
+			topology: Hamming {{ sides: [2], servers_per_router: 2 }},
+			traffic: Trace {{ filename: \"{}\", looping: true }},
+			maximum_packet_size: 4,
+			router: Basic {{ virtual_channels: 1, virtual_channel_policies: [ EnforceFlowControl, Random ], delay: 0, buffer_size: 16, bubble: false, flit_size: 4, intransit_priority: false, allow_request_busy_port: true, output_priorize_lowest_label: false, output_buffer_size: 16 }},
+			routing: Shortest,
+			link_classes: [ LinkClass {{ delay: 1 }}, LinkClass {{ delay: 1 }} ],
+			launch_configurations: [],
+		}}",filename.display()),&plugs);
+		advance_until(&mut simulation,10);
+		//The rank 1 has no record until the cycle 20.
+		assert_eq!(simulation.network.servers[1].traffic_waiting_until,20);
+		//Each pass begins in the cycle after the last record of the previous one, at the cycles 0, 21, 42, 63, and 84.
+		advance_until(&mut simulation,100);
+		let created:Vec<usize> = simulation.network.servers.iter().map(|server|server.statistics.created_phits).collect();
+		assert_eq!(created,vec![5*4,4*4,5*4,0]);
+		assert!(!simulation.traffic.is_finished());
+		std::fs::remove_file(&filename).unwrap();
+	}
+
+	#[test]
+	fn job_placements_allocate_the_servers()
+	{
+		let rng = RefCell::new(StdRng::seed_from_u64(1));
+		let plugs = test_plugs();
+		//4 routers with the servers 2r and 2r+1 each.
+		let topology = new_topology(TopologyBuilderArgument{
+			cv: &parse_configuration("Hamming { sides: [4], servers_per_router: 2 }"),
+			plugs: &plugs,
+			rng: &rng,
+		});
+		let place = |placement:&str, job_sizes:&[usize]| JobPlacement::new(&parse_configuration(placement)).place(job_sizes,topology.as_ref(),&rng);
+		assert_eq!(place("Contiguous",&[3,2,3]),vec![vec![0,1,2],vec![3,4],vec![5,6,7]]);
+		assert_eq!(place("RouterRoundRobin",&[3,2,3]),vec![vec![0,2,4],vec![6,1],vec![3,5,7]]);
+		//Each job goes to the group that fits it most tightly. The last one does not fit in any, so it takes the emptiest group and the rest from another.
+		assert_eq!(place("TopologyAware { routers_per_group: 2 }",&[3,2,3]),vec![vec![0,1,2],vec![4,5],vec![6,7,3]]);
+		//The second job skips the router partially taken by the first one.
+		assert_eq!(place("TopologyAware { routers_per_group: 1 }",&[1,2]),vec![vec![0],vec![2,3]]);
+		let random = place("Random",&[3,2,3]);
+		assert_eq!(random.iter().map(|job|job.len()).collect::<Vec<usize>>(),vec![3,2,3]);
+		let mut servers:Vec<usize> = random.into_iter().flatten().collect();
+		servers.sort_unstable();
+		assert_eq!(servers,(0..8).collect::<Vec<usize>>());
+	}
+}